@@ -20,7 +20,10 @@ if [ ! -z "$1" ] && [ $1 = "--system" ]; then
         sudo cp target/release/memflow-daemon /usr/bin/memflowd
 
         sudo mkdir -p /etc/memflow/
-        sudo cp daemon.conf /etc/memflow/daemon.conf
+        if [[ ! -f /etc/memflow/daemon.conf ]]; then
+            sudo cp daemon.conf /etc/memflow/daemon.conf
+            echo "installed /etc/memflow/daemon.conf, see daemon.example.conf for tls, authentication and persistent connections"
+        fi
 
         sudo cp memflow.service /etc/systemd/system/
         sudo systemctl enable memflow.service
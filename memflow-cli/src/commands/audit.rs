@@ -0,0 +1,129 @@
+use crate::commands::util::{exit_with_error, parse_since};
+use crate::Config;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_client::dispatch::dispatch_request;
+use memflow_daemon::memflow_rpc::{AuditLogEntry, QueryAuditLogRequest};
+
+pub const COMMAND_STR: &str = "audit";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const PID: &str = "PID";
+const SINCE: &str = "SINCE";
+const LIMIT: &str = "LIMIT";
+const DATA: &str = "DATA";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("queries the audit log of memory writes")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("only show writes to this connection")
+                .long("conn")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(PID)
+                .help("only show writes to this process")
+                .long("pid")
+                .short("p")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(SINCE)
+                .help("only show writes since a unix timestamp in milliseconds or a relative time (e.g. 30s, 10m, 2h, 1d)")
+                .long("since")
+                .short("s")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(LIMIT)
+                .help("only show the most recent writes (default: 100)")
+                .long("limit")
+                .short("n")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(DATA)
+                .help("shows the previous and the written bytes if they have been recorded")
+                .long("data")
+                .short("d"),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let pid = matches
+        .value_of(PID)
+        .map(|p| {
+            p.parse::<u32>()
+                .expect("integer parse failed, pid must be u32 value")
+        })
+        .unwrap_or_default();
+    let since = matches
+        .value_of(SINCE)
+        .map(|s| parse_since(s).expect("invalid time, use a unix timestamp or e.g. 10m"))
+        .unwrap_or_default();
+    let limit = matches
+        .value_of(LIMIT)
+        .map(|l| {
+            l.parse::<u64>()
+                .expect("integer parse failed, limit must be u64 value")
+        })
+        .unwrap_or_default();
+
+    let result = dispatch_request(
+        conf,
+        QueryAuditLogRequest {
+            conn_id: matches
+                .value_of(CONNECTION_ID)
+                .unwrap_or_default()
+                .to_string(),
+            pid,
+            since,
+            limit,
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => {
+            for entry in r.entries.iter() {
+                print_entry(entry, matches.is_present(DATA));
+            }
+        }
+    }
+}
+
+fn print_entry(entry: &AuditLogEntry, data: bool) {
+    let target = if entry.pid == 0 {
+        "phys".to_string()
+    } else {
+        format!("pid {}", entry.pid)
+    };
+
+    println!(
+        "{} {} {} conn={} {} addr=0x{:x} len={}",
+        entry.timestamp, entry.source, entry.peer, entry.conn_id, target, entry.addr, entry.len
+    );
+
+    if data {
+        println!("  old: {}", hex(&entry.old_data));
+        println!("  new: {}", hex(&entry.new_data));
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    if data.is_empty() {
+        "-".to_string()
+    } else {
+        data.iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
@@ -9,8 +9,9 @@ use memflow_client::dispatch::{
 };
 
 use memflow_daemon::memflow_rpc::{
-    ProcessInfoRequest, ReadPhysicalMemoryEntryRequest, ReadPhysicalMemoryRequest,
-    ReadVirtualMemoryEntryRequest, ReadVirtualMemoryRequest,
+    process_selector::Selector, ProcessInfoRequest, ProcessSelector,
+    ReadPhysicalMemoryEntryRequest, ReadPhysicalMemoryRequest, ReadVirtualMemoryEntryRequest,
+    ReadVirtualMemoryRequest,
 };
 
 pub const COMMAND_STR: &str = "benchmark";
@@ -23,6 +24,8 @@ const ASYNC_MODE: &str = "ASYNC_MODE";
 
 const PHYSICAL_MODE: &str = "PHYSICAL_MODE";
 
+const CLIENTS: &str = "clients";
+
 pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name(COMMAND_STR)
         .about(
@@ -30,7 +33,7 @@ pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
         )
         .arg(
             Arg::with_name(CONNECTION_ID)
-                .help("the connector to be used for process listing")
+                .help("the connectors to be benchmarked, multiple connections can be separated by a comma")
                 .index(1)
                 .required(true),
         )
@@ -55,12 +58,27 @@ pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
                 .required(false)
                 .default_value("true"),
         )
+        .arg(
+            Arg::with_name(CLIENTS)
+                .help("number of concurrent clients, clients are distributed evenly across all connections")
+                .long(CLIENTS)
+                .short("c")
+                .takes_value(true)
+                .required(false)
+                .default_value("1"),
+        )
 }
 
 pub fn handle_command(conf: &Config, matches: &ArgMatches) {
     trace!("handling command");
 
-    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let conn_ids = matches
+        .value_of(CONNECTION_ID)
+        .unwrap()
+        .split(',')
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .collect::<Vec<_>>();
     let read_size: u64 = matches
         .value_of(READ_SIZE)
         .unwrap_or("64")
@@ -76,96 +94,89 @@ pub fn handle_command(conf: &Config, matches: &ArgMatches) {
         .unwrap_or("true")
         .parse()
         .expect("bool parse failed, pysical must be true or false");
+    let clients: usize = matches
+        .value_of(CLIENTS)
+        .unwrap_or("1")
+        .parse()
+        .expect("integer parse failed, clients must be usize value");
 
-    benchmark(conf, physical_mode, conn_id, read_size, async_mode)
+    if conn_ids.is_empty() || clients == 0 {
+        error!("at least one connection and one client are required");
+        return;
+    }
+
+    benchmark(
+        conf,
+        physical_mode,
+        &conn_ids,
+        read_size,
+        async_mode,
+        clients,
+    )
 }
 
 pub fn benchmark(
     conf: &Config,
     physical_mode: bool,
-    conn_id: &str,
+    conn_ids: &[String],
     read_size: u64,
     async_mode: bool,
+    clients: usize,
 ) {
-    if async_mode {
-        benchmark_async(conf, physical_mode, conn_id, read_size);
-    } else {
-        benchmark_sync(conf, physical_mode, conn_id, read_size);
-    }
-}
-
-fn benchmark_sync(conf: &Config, physical_mode: bool, conn_id: &str, read_size: u64) {
-    let pid = 0;
-    let address = dispatch_request(
-        conf,
-        ProcessInfoRequest {
-            conn_id: conn_id.to_string(),
-            pid: pid,
-        },
-    )
-    .expect("could not access process info")
-    .process
-    .unwrap()
-    .address;
-
-    let entry = ReadVirtualMemoryEntryRequest {
-        addr: address,
-        len: read_size,
-    };
-    let req = ReadVirtualMemoryRequest {
-        conn_id: conn_id.to_string(),
-        pid: pid,
-        base_offsets: false,
-        reads: vec![entry],
-    };
-    let phys_entry = ReadPhysicalMemoryEntryRequest {
-        addr: address,
-        len: read_size,
-    };
-    let phys_req = ReadPhysicalMemoryRequest {
-        conn_id: conn_id.to_string(),
-        reads: vec![phys_entry],
-    };
-
-    let (mut client, rt) = create_client(conf);
+    // every client reads from the connection it has been assigned to
+    let requests = conn_ids
+        .iter()
+        .map(|conn_id| create_requests(conf, conn_id, read_size))
+        .collect::<Vec<_>>();
+    let client_requests = (0..clients)
+        .map(|i| requests[i % requests.len()].clone())
+        .collect::<Vec<_>>();
 
     let start_time = std::time::Instant::now();
-    let mut total_runs = 0;
-    loop {
-        total_runs += 1;
-
-        let response = if !physical_mode {
-            dispatch_request_client(conf, req.clone(), &mut client, &rt).map(|_| ())
-        } else {
-            dispatch_request_client(conf, phys_req.clone(), &mut client, &rt).map(|_| ())
-        };
-        match response {
-            Err(e) => error!("{:#?}", e),
-            Ok(_) => (),
-        }
+    let runs = if async_mode {
+        benchmark_async(conf, physical_mode, client_requests)
+    } else {
+        benchmark_sync(conf, physical_mode, client_requests)
+    };
+    let end_time = std::time::Instant::now();
 
-        if (std::time::Instant::now() - start_time).as_secs() > 10 {
-            break;
+    let total_sec = (end_time - start_time).as_secs_f64();
+    if clients > 1 {
+        for (i, client_runs) in runs.iter().enumerate() {
+            println!(
+                "Client {} ({}): Total: {}, Each: {} ms",
+                i,
+                conn_ids[i % conn_ids.len()],
+                client_runs,
+                total_sec * 1000.0 / *client_runs as f64
+            );
         }
     }
-    let end_time = std::time::Instant::now();
 
-    let total_sec = (end_time - start_time).as_secs_f64();
+    let total_runs: usize = runs.iter().sum();
     println!(
-        "Total: {} s, Total: {}, Each: {} ms",
+        "Total: {} s, Total: {}, Each: {} ms, Throughput: {} ops/s",
         total_sec,
         total_runs,
-        total_sec * 1000.0 / total_runs as f64
+        total_sec * 1000.0 / total_runs as f64,
+        total_runs as f64 / total_sec
     );
 }
 
-fn benchmark_async(conf: &Config, physical_mode: bool, conn_id: &str, read_size: u64) {
-    let pid = 0;
+fn create_requests(
+    conf: &Config,
+    conn_id: &str,
+    read_size: u64,
+) -> (ReadVirtualMemoryRequest, ReadPhysicalMemoryRequest) {
+    let process = Some(ProcessSelector {
+        selector: Some(Selector::Pid(0)),
+    });
     let address = dispatch_request(
         conf,
         ProcessInfoRequest {
             conn_id: conn_id.to_string(),
-            pid: pid,
+            process: process.clone(),
+            ..Default::default()
         },
     )
     .expect("could not access process info")
@@ -179,9 +190,11 @@ fn benchmark_async(conf: &Config, physical_mode: bool, conn_id: &str, read_size:
     };
     let req = ReadVirtualMemoryRequest {
         conn_id: conn_id.to_string(),
-        pid: pid,
+        process,
         base_offsets: false,
         reads: vec![entry],
+        module: String::new(),
+        ..Default::default()
     };
     let phys_entry = ReadPhysicalMemoryEntryRequest {
         addr: address,
@@ -192,52 +205,112 @@ fn benchmark_async(conf: &Config, physical_mode: bool, conn_id: &str, read_size:
         reads: vec![phys_entry],
     };
 
+    (req, phys_req)
+}
+
+/// Runs one blocking client per thread and returns the number of requests each client sent.
+fn benchmark_sync(
+    conf: &Config,
+    physical_mode: bool,
+    client_requests: Vec<(ReadVirtualMemoryRequest, ReadPhysicalMemoryRequest)>,
+) -> Vec<usize> {
+    let threads = client_requests
+        .into_iter()
+        .map(|(req, phys_req)| {
+            let conf = conf.clone();
+            std::thread::spawn(move || {
+                let (mut client, rt) = match create_client(&conf) {
+                    Ok(client) => client,
+                    Err(e) => {
+                        error!("{}", e);
+                        return 0;
+                    }
+                };
+
+                let start_time = std::time::Instant::now();
+                let mut total_runs = 0;
+                loop {
+                    total_runs += 1;
+
+                    let response = if !physical_mode {
+                        dispatch_request_client(&conf, req.clone(), &mut client, &rt).map(|_| ())
+                    } else {
+                        dispatch_request_client(&conf, phys_req.clone(), &mut client, &rt)
+                            .map(|_| ())
+                    };
+                    match response {
+                        Err(e) => error!("{}", e),
+                        Ok(_) => (),
+                    }
+
+                    if (std::time::Instant::now() - start_time).as_secs() > 10 {
+                        break;
+                    }
+                }
+                total_runs
+            })
+        })
+        .collect::<Vec<_>>();
+
+    threads
+        .into_iter()
+        .map(|t| t.join().unwrap_or_default())
+        .collect()
+}
+
+/// Runs all clients on a single runtime and returns the number of requests each client sent.
+fn benchmark_async(
+    conf: &Config,
+    physical_mode: bool,
+    client_requests: Vec<(ReadVirtualMemoryRequest, ReadPhysicalMemoryRequest)>,
+) -> Vec<usize> {
     let rt = tokio::runtime::Runtime::new().unwrap();
 
     let start_time = std::time::Instant::now();
-    let mut total_runs = 0;
-
-    let bench = async {
-        let client = create_client_async(conf).await;
-        let mut responses = vec![];
-        loop {
-            total_runs += 1;
-
-            let response = async {
-                if !physical_mode {
-                    let mut client_cp = client.clone();
-                    dispatch_request_async_client(conf, req.clone(), &mut client_cp)
-                        .await
-                        .map(|_| ())
-                } else {
-                    let mut client_cp = client.clone();
-                    dispatch_request_async_client(conf, phys_req.clone(), &mut client_cp)
-                        .await
-                        .map(|_| ())
+
+    let benches = client_requests
+        .into_iter()
+        .map(|(req, phys_req)| async move {
+            let client = match create_client_async(conf).await {
+                Ok(client) => client,
+                Err(e) => {
+                    error!("{}", e);
+                    return 0;
                 }
             };
-            responses.push(response);
+            let mut total_runs = 0;
+            let mut responses = vec![];
+            loop {
+                total_runs += 1;
+
+                let response = async {
+                    if !physical_mode {
+                        let mut client_cp = client.clone();
+                        dispatch_request_async_client(conf, req.clone(), &mut client_cp)
+                            .await
+                            .map(|_| ())
+                    } else {
+                        let mut client_cp = client.clone();
+                        dispatch_request_async_client(conf, phys_req.clone(), &mut client_cp)
+                            .await
+                            .map(|_| ())
+                    }
+                };
+                responses.push(response);
 
-            if (std::time::Instant::now() - start_time).as_secs() > 10 || total_runs >= 20000 {
-                break;
+                if (std::time::Instant::now() - start_time).as_secs() > 10 || total_runs >= 20000 {
+                    break;
+                }
             }
-        }
-        let results = futures::future::join_all(responses).await;
-        for res in results {
-            match res {
-                Err(e) => error!("{:#?}", e),
-                Ok(_) => (),
+            let results = futures::future::join_all(responses).await;
+            for res in results {
+                match res {
+                    Err(e) => error!("{}", e),
+                    Ok(_) => (),
+                }
             }
-        }
-    };
-    rt.block_on(bench);
-    let end_time = std::time::Instant::now();
+            total_runs
+        });
 
-    let total_sec = (end_time - start_time).as_secs_f64();
-    println!(
-        "Total: {} s, Total: {}, Each: {} ms",
-        total_sec,
-        total_runs,
-        total_sec * 1000.0 / total_runs as f64
-    );
+    rt.block_on(futures::future::join_all(benches))
 }
@@ -0,0 +1,52 @@
+use crate::commands::util::exit_with_error;
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+pub const COMMAND_STR: &str = "attach";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const CONNECTOR_OS: &str = "CONNECTOR_OS";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("attaches an os to a connection that has been opened without one")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection the os should be attached to")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(CONNECTOR_OS)
+                .help("the os layer to be used on top of the connector")
+                .long("os")
+                .short("o")
+                .takes_value(true)
+                .possible_values(&["win32"])
+                .required(false),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let os = matches.value_of(CONNECTOR_OS);
+
+    let result = dispatch_request(
+        conf,
+        memflow_daemon::memflow_rpc::AttachOsRequest {
+            conn_id: conn_id.to_string(),
+            os: os.unwrap_or_default().to_string(),
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(_) => println!("Os attached"),
+    }
+}
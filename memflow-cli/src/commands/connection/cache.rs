@@ -0,0 +1,64 @@
+use crate::commands::util::exit_with_error;
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+pub const COMMAND_STR: &str = "cache";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const FLUSH: &str = "FLUSH";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("shows the cache configuration and statistics of a connection")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be inspected")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(FLUSH)
+                .help("drops all cached pages and address translations")
+                .long("flush")
+                .short("f")
+                .takes_value(false)
+                .required(false),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+
+    let result = dispatch_request(
+        conf,
+        memflow_daemon::memflow_rpc::ConnectionCacheRequest {
+            conn_id: conn_id.to_string(),
+            flush: matches.is_present(FLUSH),
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => {
+            if matches.is_present(FLUSH) {
+                println!("Caches flushed");
+            }
+            println!("{:#?}", r.options);
+            if let Some(stats) = r.stats {
+                let hit_ratio = if stats.read_bytes > 0 {
+                    stats.hit_bytes as f64 / stats.read_bytes as f64 * 100.0
+                } else {
+                    0.0
+                };
+                println!("{:#?}", stats);
+                println!("Cache hit ratio: {:.2}%", hit_ratio);
+            }
+        }
+    }
+}
@@ -1,9 +1,10 @@
+use crate::commands::util::exit_with_error;
 use crate::Config;
 use memflow_client::dispatch::dispatch_request;
 
 use clap::{App, ArgMatches, SubCommand};
 
-use log::{error, trace};
+use log::trace;
 
 pub const COMMAND_STR: &str = "ls";
 
@@ -17,7 +18,7 @@ pub fn handle_command(conf: &Config, _matches: &ArgMatches) {
     let result = dispatch_request(conf, memflow_daemon::memflow_rpc::ListConnectionsRequest {});
 
     match result {
-        Err(e) => error!("{:#?}", e),
+        Err(e) => exit_with_error(e),
         Ok(r) => println!("{:#?}", r.connections),
     }
 }
@@ -1,5 +1,8 @@
+mod attach;
+mod cache;
 mod ls;
 mod new;
+mod revert;
 mod rm;
 
 use crate::Config;
@@ -16,6 +19,9 @@ pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
         .subcommand(new::command_definition())
         .subcommand(ls::command_definition())
         .subcommand(rm::command_definition())
+        .subcommand(attach::command_definition())
+        .subcommand(cache::command_definition())
+        .subcommand(revert::command_definition())
 }
 
 pub fn handle_command(conf: &Config, matches: &ArgMatches) {
@@ -25,6 +31,9 @@ pub fn handle_command(conf: &Config, matches: &ArgMatches) {
         (new::COMMAND_STR, Some(matches)) => new::handle_command(conf, matches),
         (ls::COMMAND_STR, Some(matches)) => ls::handle_command(conf, matches),
         (rm::COMMAND_STR, Some(matches)) => rm::handle_command(conf, matches),
+        (attach::COMMAND_STR, Some(matches)) => attach::handle_command(conf, matches),
+        (cache::COMMAND_STR, Some(matches)) => cache::handle_command(conf, matches),
+        (revert::COMMAND_STR, Some(matches)) => revert::handle_command(conf, matches),
         _ => {
             command_definition().print_help().ok();
             println!();
@@ -1,15 +1,25 @@
+use crate::commands::util::exit_with_error;
 use crate::Config;
 use memflow_client::dispatch::dispatch_request;
+use memflow_daemon::memflow_rpc::CacheOptions;
 
 use clap::{App, Arg, ArgMatches, SubCommand};
 
-use log::{error, trace};
+use log::trace;
 
 pub const COMMAND_STR: &str = "new";
 
 const CONNECTOR_NAME: &str = "CONNECTOR_NAME";
 const CONNECTOR_ARGS: &str = "CONNECTOR_ARGS";
 const CONNECTOR_ALIAS: &str = "CONNECTOR_ALIAS";
+const CONNECTOR_OS: &str = "CONNECTOR_OS";
+const JOURNAL: &str = "JOURNAL";
+
+const NO_CACHE: &str = "NO_CACHE";
+const PAGE_CACHE_SIZE: &str = "PAGE_CACHE_SIZE";
+const PAGE_CACHE_VALIDITY: &str = "PAGE_CACHE_VALIDITY";
+const TLB_SIZE: &str = "TLB_SIZE";
+const TLB_VALIDITY: &str = "TLB_VALIDITY";
 
 pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name(COMMAND_STR)
@@ -34,6 +44,67 @@ pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
                 .takes_value(true)
                 .required(false),
         )
+        .arg(
+            Arg::with_name(CONNECTOR_OS)
+                .help("the os layer to be used on top of the connector")
+                .long("os")
+                .short("o")
+                .takes_value(true)
+                .possible_values(&["win32", "none"])
+                .required(false),
+        )
+        .arg(
+            Arg::with_name(JOURNAL)
+                .help("records the original bytes of all writes so they can be restored with 'conn revert'")
+                .long("journal")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name(NO_CACHE)
+                .help("disables all caches for this connection")
+                .long("no-cache")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name(PAGE_CACHE_SIZE)
+                .help("size of the page cache in kilobytes")
+                .long("page-cache-size")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name(PAGE_CACHE_VALIDITY)
+                .help("validity of cached pages in milliseconds")
+                .long("page-cache-validity")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name(TLB_SIZE)
+                .help("number of entries in the translation lookaside buffer")
+                .long("tlb-size")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name(TLB_VALIDITY)
+                .help("validity of cached address translations in milliseconds")
+                .long("tlb-validity")
+                .takes_value(true)
+                .required(false),
+        )
+}
+
+fn parse_u64(matches: &ArgMatches, name: &str) -> u64 {
+    matches
+        .value_of(name)
+        .map(|v| {
+            v.parse()
+                .unwrap_or_else(|_| panic!("integer parse failed, {} must be u64 value", name))
+        })
+        .unwrap_or_default()
 }
 
 pub fn handle_command(conf: &Config, matches: &ArgMatches) {
@@ -42,6 +113,15 @@ pub fn handle_command(conf: &Config, matches: &ArgMatches) {
     let name = matches.value_of(CONNECTOR_NAME).unwrap();
     let args = matches.value_of(CONNECTOR_ARGS);
     let alias = matches.value_of(CONNECTOR_ALIAS);
+    let os = matches.value_of(CONNECTOR_OS);
+
+    let cache = CacheOptions {
+        disabled: matches.is_present(NO_CACHE),
+        page_cache_size: parse_u64(matches, PAGE_CACHE_SIZE) * 1024,
+        page_cache_validity_ms: parse_u64(matches, PAGE_CACHE_VALIDITY),
+        tlb_size: parse_u64(matches, TLB_SIZE),
+        tlb_validity_ms: parse_u64(matches, TLB_VALIDITY),
+    };
 
     let result = dispatch_request(
         conf,
@@ -49,11 +129,14 @@ pub fn handle_command(conf: &Config, matches: &ArgMatches) {
             name: name.to_string(),
             args: args.unwrap_or_default().to_string(),
             alias: alias.unwrap_or_default().to_string(),
+            os: os.unwrap_or_default().to_string(),
+            cache: Some(cache),
+            journal: matches.is_present(JOURNAL),
         },
     );
 
     match result {
-        Err(e) => error!("{:#?}", e),
+        Err(e) => exit_with_error(e),
         Ok(r) => println!("New connection id: {}", r.conn_id),
     }
 }
@@ -0,0 +1,58 @@
+use crate::commands::util::{exit_with_error, parse_since};
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+pub const COMMAND_STR: &str = "revert";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const SINCE: &str = "SINCE";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("restores the original bytes of all writes, requires a connection opened with --journal")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection whose writes should be reverted")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(SINCE)
+                .help("only revert writes since a unix timestamp in milliseconds or a relative time (e.g. 30s, 10m, 2h, 1d)")
+                .long("since")
+                .short("s")
+                .takes_value(true),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let since = matches
+        .value_of(SINCE)
+        .map(|s| parse_since(s).expect("invalid time, use a unix timestamp or e.g. 10m"))
+        .unwrap_or_default();
+
+    let result = dispatch_request(
+        conf,
+        memflow_daemon::memflow_rpc::RevertWritesRequest {
+            conn_id: conn_id.to_string(),
+            since,
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => {
+            println!("Reverted {} writes", r.reverted);
+            if r.failed > 0 {
+                println!("{} writes could not be reverted", r.failed);
+            }
+        }
+    }
+}
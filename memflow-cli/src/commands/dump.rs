@@ -0,0 +1,40 @@
+use memflow_client::dispatch::DumpStream;
+use memflow_client::error::{Error, Result};
+
+use log::warn;
+
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+
+/// Writes all chunks of a memory dump stream into the given file.
+///
+/// Unreadable ranges are reported by the daemon and are zero-filled in the output file.
+/// Returns the number of bytes written and the number of bytes that could not be read.
+pub async fn write_dump(mut stream: DumpStream, output: &str) -> Result<(u64, u64)> {
+    let mut file = File::create(output)
+        .map_err(|err| Error::Local(format!("unable to create {}: {}", output, err)))?;
+
+    let mut written = 0;
+    let mut unreadable = 0;
+    while let Some(chunk) = stream.message().await? {
+        file.seek(SeekFrom::Start(chunk.offset))
+            .and_then(|_| file.write_all(&chunk.data))
+            .map_err(|err| Error::Local(format!("unable to write {}: {}", output, err)))?;
+
+        for range in chunk.unreadable.iter() {
+            warn!("unable to read {:x} bytes at {:x}", range.len, range.addr);
+            unreadable += range.len;
+        }
+
+        written += chunk.data.len() as u64;
+        eprint!(
+            "\r{:x} / {:x} bytes ({:.1}%)",
+            written,
+            chunk.total_len,
+            written as f64 / chunk.total_len as f64 * 100.0
+        );
+    }
+    eprintln!();
+
+    Ok((written, unreadable))
+}
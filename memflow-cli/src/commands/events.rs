@@ -0,0 +1,84 @@
+use crate::commands::util::exit_with_error;
+use crate::Config;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_client::dispatch::{dispatch_request_async, EventStream};
+use memflow_client::error::Result;
+use memflow_daemon::memflow_rpc::{Event, EventKind, WatchEventsRequest};
+
+pub const COMMAND_STR: &str = "events";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("prints connection, file system and gdb stub events until interrupted")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("only show events of this connection")
+                .long("conn")
+                .takes_value(true),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let request = WatchEventsRequest {
+        conn_id: matches
+            .value_of(CONNECTION_ID)
+            .unwrap_or_default()
+            .to_string(),
+    };
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let result = rt.block_on(async {
+        let stream = dispatch_request_async(conf, request).await?;
+        print_events(stream).await
+    });
+
+    if let Err(e) = result {
+        exit_with_error(e)
+    }
+}
+
+async fn print_events(mut stream: EventStream) -> Result<()> {
+    while let Some(event) = stream.message().await? {
+        print_event(&event);
+    }
+    Ok(())
+}
+
+fn print_event(event: &Event) {
+    let kind = match EventKind::from_i32(event.kind) {
+        Some(EventKind::ConnectionOpened) => "connection opened",
+        Some(EventKind::ConnectionClosed) => "connection closed",
+        Some(EventKind::ConnectionRefcount) => "connection refcount",
+        Some(EventKind::FuseMounted) => "fuse mounted",
+        Some(EventKind::FuseUnmounted) => "fuse unmounted",
+        Some(EventKind::GdbStubStarted) => "gdb stub started",
+        Some(EventKind::GdbClientAttached) => "gdb client attached",
+        Some(EventKind::GdbClientDetached) => "gdb client detached",
+        Some(EventKind::GdbStubStopped) => "gdb stub stopped",
+        Some(EventKind::Error) => "error",
+        None => "unknown",
+    };
+
+    let mut line = format!("{} {}", event.timestamp, kind);
+    if !event.conn_id.is_empty() {
+        line.push_str(&format!(" conn={}", event.conn_id));
+    }
+    if !event.id.is_empty() {
+        line.push_str(&format!(" id={}", event.id));
+    }
+    if event.kind == EventKind::ConnectionRefcount as i32 {
+        line.push_str(&format!(" refcount={}", event.refcount));
+    }
+    if !event.message.is_empty() {
+        line.push_str(&format!(": {}", event.message));
+    }
+    println!("{}", line);
+}
@@ -0,0 +1,225 @@
+use memflow_client::partial::PartialRead;
+use memflow_daemon::memflow_rpc::ReadStatus;
+
+use log::{error, warn};
+
+use std::convert::TryInto;
+use std::io::Write;
+
+/// Output formats supported by the read commands.
+pub const OUTPUT_FORMATS: &[&str] = &[
+    "hex", "raw", "u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64", "f32", "f64", "ptr",
+    "ptr32", "utf8", "utf16",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Hexdump,
+    Raw,
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    Pointer,
+    Pointer32,
+    Utf8,
+    Utf16,
+}
+
+impl OutputFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format {
+            "hex" => Some(OutputFormat::Hexdump),
+            "raw" => Some(OutputFormat::Raw),
+            "u8" => Some(OutputFormat::U8),
+            "u16" => Some(OutputFormat::U16),
+            "u32" => Some(OutputFormat::U32),
+            "u64" => Some(OutputFormat::U64),
+            "i8" => Some(OutputFormat::I8),
+            "i16" => Some(OutputFormat::I16),
+            "i32" => Some(OutputFormat::I32),
+            "i64" => Some(OutputFormat::I64),
+            "f32" => Some(OutputFormat::F32),
+            "f64" => Some(OutputFormat::F64),
+            "ptr" => Some(OutputFormat::Pointer),
+            "ptr32" => Some(OutputFormat::Pointer32),
+            "utf8" => Some(OutputFormat::Utf8),
+            "utf16" => Some(OutputFormat::Utf16),
+            _ => None,
+        }
+    }
+
+    /// Returns the size of a single value or None for formats without a fixed size.
+    pub fn value_size(self) -> Option<usize> {
+        match self {
+            OutputFormat::U8 | OutputFormat::I8 => Some(1),
+            OutputFormat::U16 | OutputFormat::I16 => Some(2),
+            OutputFormat::U32 | OutputFormat::I32 | OutputFormat::F32 | OutputFormat::Pointer32 => {
+                Some(4)
+            }
+            OutputFormat::U64 | OutputFormat::I64 | OutputFormat::F64 | OutputFormat::Pointer => {
+                Some(8)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the number of bytes read when no length is given.
+    pub fn default_len(self) -> u64 {
+        self.value_size().map(|s| s as u64).unwrap_or(256)
+    }
+}
+
+/// Prints a read entry in the given format.
+///
+/// Unreadable bytes are shown as `??` in hexdumps and typed values
+/// which overlap unreadable bytes are omitted, strings end at the first unreadable byte.
+pub fn print_entry<E: PartialRead>(addr: u64, entry: &E, format: OutputFormat) {
+    match entry.read_status() {
+        ReadStatus::Ok => (),
+        ReadStatus::Partial => warn!(
+            "only parts of the memory at {:x} could be read: {:x?}",
+            addr,
+            entry.valid_ranges(addr)
+        ),
+        ReadStatus::Failed => {
+            error!(
+                "unable to read {:x} bytes at {:x}",
+                entry.data().len(),
+                addr
+            );
+            return;
+        }
+    }
+
+    let data = entry.data();
+    let is_valid = |offset| entry.is_valid(addr, offset);
+
+    match format {
+        OutputFormat::Hexdump => print_hexdump(addr, data, is_valid),
+        OutputFormat::Raw => {
+            std::io::stdout().write_all(data).ok();
+        }
+        OutputFormat::Utf8 => {
+            let data = readable_string(addr, data, 1, is_valid);
+            let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+            println!("{}", String::from_utf8_lossy(&data[..end]));
+        }
+        OutputFormat::Utf16 => {
+            let chars = readable_string(addr, data, 2, is_valid)
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .take_while(|c| *c != 0)
+                .collect::<Vec<_>>();
+            println!("{}", String::from_utf16_lossy(&chars));
+        }
+        _ => print_values(addr, data, format, is_valid),
+    }
+}
+
+/// Returns the characters of a string up to the first character which overlaps unreadable bytes.
+///
+/// The daemon zero-fills unreadable bytes so they must not be printed or treated as a terminator.
+fn readable_string<F: Fn(usize) -> bool>(
+    addr: u64,
+    data: &[u8],
+    char_size: usize,
+    is_valid: F,
+) -> &[u8] {
+    let end = match (0..data.len()).position(|offset| !is_valid(offset)) {
+        Some(offset) => offset - offset % char_size,
+        None => return data,
+    };
+
+    // the string is complete if it is terminated before the unreadable bytes
+    let readable = &data[..end];
+    if !readable
+        .chunks_exact(char_size)
+        .any(|c| c.iter().all(|b| *b == 0))
+    {
+        warn!(
+            "the string at {:x} is cut off at the unreadable byte at {:x}",
+            addr,
+            addr + end as u64
+        );
+    }
+    readable
+}
+
+fn print_hexdump<F: Fn(usize) -> bool>(addr: u64, data: &[u8], is_valid: F) {
+    for (line, chunk) in data.chunks(16).enumerate() {
+        let offset = line * 16;
+
+        let mut hex = String::new();
+        let mut ascii = String::new();
+        for i in 0..16 {
+            if i == 8 {
+                hex.push(' ');
+            }
+
+            match chunk.get(i) {
+                Some(b) if is_valid(offset + i) => {
+                    hex.push_str(&format!("{:02x} ", b));
+                    ascii.push(if b.is_ascii_graphic() || *b == b' ' {
+                        *b as char
+                    } else {
+                        '.'
+                    });
+                }
+                Some(_) => {
+                    hex.push_str("?? ");
+                    ascii.push('?');
+                }
+                None => hex.push_str("   "),
+            }
+        }
+
+        println!("{:016x}  {} |{}|", addr + offset as u64, hex, ascii);
+    }
+}
+
+fn print_values<F: Fn(usize) -> bool>(addr: u64, data: &[u8], format: OutputFormat, is_valid: F) {
+    let size = format.value_size().unwrap_or(1);
+    if data.len() % size != 0 {
+        warn!(
+            "ignoring the last {} bytes as they do not form a complete value",
+            data.len() % size
+        );
+    }
+
+    for (i, value) in data.chunks_exact(size).enumerate() {
+        let offset = i * size;
+        let value_addr = addr + offset as u64;
+        if !(offset..offset + size).all(|o| is_valid(o)) {
+            println!("{:016x}: ??", value_addr);
+            continue;
+        }
+
+        let value = match format {
+            OutputFormat::U8 => value[0].to_string(),
+            OutputFormat::U16 => u16::from_le_bytes(value.try_into().unwrap()).to_string(),
+            OutputFormat::U32 => u32::from_le_bytes(value.try_into().unwrap()).to_string(),
+            OutputFormat::U64 => u64::from_le_bytes(value.try_into().unwrap()).to_string(),
+            OutputFormat::I8 => (value[0] as i8).to_string(),
+            OutputFormat::I16 => i16::from_le_bytes(value.try_into().unwrap()).to_string(),
+            OutputFormat::I32 => i32::from_le_bytes(value.try_into().unwrap()).to_string(),
+            OutputFormat::I64 => i64::from_le_bytes(value.try_into().unwrap()).to_string(),
+            OutputFormat::F32 => f32::from_le_bytes(value.try_into().unwrap()).to_string(),
+            OutputFormat::F64 => f64::from_le_bytes(value.try_into().unwrap()).to_string(),
+            OutputFormat::Pointer => {
+                format!("0x{:x}", u64::from_le_bytes(value.try_into().unwrap()))
+            }
+            OutputFormat::Pointer32 => {
+                format!("0x{:x}", u32::from_le_bytes(value.try_into().unwrap()))
+            }
+            _ => unreachable!(),
+        };
+        println!("{:016x}: {}", value_addr, value);
+    }
+}
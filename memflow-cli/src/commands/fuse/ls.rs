@@ -1,8 +1,9 @@
+use crate::commands::util::exit_with_error;
 use crate::Config;
 
 use clap::{App, ArgMatches, SubCommand};
 
-use log::{error, trace};
+use log::trace;
 
 use memflow_client::dispatch::dispatch_request;
 use memflow_daemon::memflow_rpc::FuseListRequest;
@@ -19,7 +20,7 @@ pub fn handle_command(conf: &Config, _matches: &ArgMatches) {
     let result = dispatch_request(conf, FuseListRequest {});
 
     match result {
-        Err(e) => error!("{:#?}", e),
+        Err(e) => exit_with_error(e),
         Ok(r) => println!("{:#?}", r),
     }
 }
@@ -1,5 +1,6 @@
 mod ls;
 mod mount;
+mod umount;
 
 use crate::Config;
 
@@ -14,6 +15,7 @@ pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
         .about("manages fuse virtual filesystem mount")
         .subcommand(mount::command_definition())
         .subcommand(ls::command_definition())
+        .subcommand(umount::command_definition())
 }
 
 pub fn handle_command(conf: &Config, matches: &ArgMatches) {
@@ -22,6 +24,7 @@ pub fn handle_command(conf: &Config, matches: &ArgMatches) {
     match matches.subcommand() {
         (mount::COMMAND_STR, Some(matches)) => mount::handle_command(conf, matches),
         (ls::COMMAND_STR, Some(matches)) => ls::handle_command(conf, matches),
+        (umount::COMMAND_STR, Some(matches)) => umount::handle_command(conf, matches),
         _ => {
             command_definition().print_help().ok();
             println!();
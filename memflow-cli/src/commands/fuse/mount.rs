@@ -1,8 +1,9 @@
+use crate::commands::util::exit_with_error;
 use crate::Config;
 
 use clap::{App, Arg, ArgMatches, SubCommand};
 
-use log::{error, trace};
+use log::trace;
 use std::fs;
 
 use memflow_client::dispatch::dispatch_request;
@@ -51,7 +52,7 @@ pub fn handle_command(conf: &Config, matches: &ArgMatches) {
     );
 
     match result {
-        Err(e) => error!("{:#?}", e),
-        Ok(_) => println!("Fuse mount succeed"),
+        Err(e) => exit_with_error(e),
+        Ok(r) => println!("Fuse mount succeed, id: {}", r.id),
     }
 }
@@ -0,0 +1,44 @@
+use crate::commands::util::exit_with_error;
+use crate::Config;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+use std::fs;
+
+use memflow_client::dispatch::dispatch_request;
+use memflow_daemon::memflow_rpc::FuseUnmountRequest;
+
+pub const COMMAND_STR: &str = "umount";
+
+const ID: &str = "ID";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("unmounts a fuse virtual filesystem")
+        .arg(
+            Arg::with_name(ID)
+                .help("the id or the mount point of the filesystem")
+                .index(1)
+                .required(true),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let id = matches.value_of(ID).unwrap();
+
+    // mount points are stored as canonical paths by the daemon
+    let id = fs::canonicalize(id)
+        .ok()
+        .and_then(|path| path.to_str().map(String::from))
+        .unwrap_or_else(|| id.to_string());
+
+    let result = dispatch_request(conf, FuseUnmountRequest { id });
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(_) => println!("Fuse unmount succeed"),
+    }
+}
@@ -1,8 +1,9 @@
+use crate::commands::util::{exit_with_error, parse_process_selector, PROCESS_SELECTOR_HELP};
 use crate::Config;
 
 use clap::{App, Arg, ArgMatches, SubCommand};
 
-use log::{error, trace};
+use log::trace;
 
 use memflow_client::dispatch::dispatch_request;
 use memflow_daemon::memflow_rpc::GdbAttachRequest;
@@ -10,7 +11,7 @@ use memflow_daemon::memflow_rpc::GdbAttachRequest;
 pub const COMMAND_STR: &str = "attach";
 
 const CONNECTION_ID: &str = "CONNECTION_ID";
-const PROCESS_ID: &str = "PROCESS_ID";
+const PROCESS: &str = "PROCESS";
 const ADDRESS: &str = "ADDRESS";
 
 pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
@@ -23,8 +24,8 @@ pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
                 .required(true),
         )
         .arg(
-            Arg::with_name(PROCESS_ID)
-                .help("the process id of the process to be attached")
+            Arg::with_name(PROCESS)
+                .help(PROCESS_SELECTOR_HELP)
                 .index(2)
                 .required(true),
         )
@@ -40,24 +41,21 @@ pub fn handle_command(conf: &Config, matches: &ArgMatches) {
     trace!("handling command");
 
     let conn_id = matches.value_of(CONNECTION_ID).unwrap();
-    let pid: u32 = matches
-        .value_of(PROCESS_ID)
-        .unwrap()
-        .parse()
-        .expect("integer parse failed, read size must be u32 value");
+    let process = matches.value_of(PROCESS).unwrap();
     let addr = matches.value_of(ADDRESS).unwrap();
 
     let result = dispatch_request(
         conf,
         GdbAttachRequest {
             conn_id: conn_id.to_string(),
-            pid: pid,
+            process: Some(parse_process_selector(process)),
             addr: addr.to_string(),
+            ..Default::default()
         },
     );
 
     match result {
-        Err(e) => error!("{:#?}", e),
+        Err(e) => exit_with_error(e),
         Ok(r) => {
             println!("gdb stub with id {} spawned at address {}", r.id, addr);
             println!("the gdb stub will automatically be closed on disconnect");
@@ -1,6 +1,14 @@
+pub mod audit;
 pub mod benchmark;
 pub mod connection;
+pub mod events;
+pub mod phys;
 pub mod proc;
+pub mod scan;
+
+mod dump;
+mod format;
+mod util;
 
 pub mod fuse;
 pub mod gdb;
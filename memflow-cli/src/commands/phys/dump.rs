@@ -0,0 +1,92 @@
+use crate::commands::dump::write_dump;
+use crate::commands::util::{exit_with_error, parse_u64};
+use crate::Config;
+use memflow_client::dispatch::dispatch_request_async;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+pub const COMMAND_STR: &str = "dump";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const OUTPUT: &str = "OUTPUT";
+const ADDR: &str = "ADDR";
+const LEN: &str = "LEN";
+const CHUNK_SIZE: &str = "CHUNK_SIZE";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("dumps physical memory into a file")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be dumped")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(OUTPUT)
+                .help("the file the dump is written to")
+                .index(2)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(ADDR)
+                .help("physical address to start the dump at")
+                .long("addr")
+                .short("a")
+                .takes_value(true)
+                .required(false)
+                .default_value("0"),
+        )
+        .arg(
+            Arg::with_name(LEN)
+                .help("number of bytes to dump (default: until the end of physical memory)")
+                .long("len")
+                .short("l")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name(CHUNK_SIZE)
+                .help("size of each transferred chunk in bytes (default: 1 MiB)")
+                .long("chunk-size")
+                .takes_value(true)
+                .required(false),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let output = matches.value_of(OUTPUT).unwrap();
+
+    let request = memflow_daemon::memflow_rpc::DumpPhysicalMemoryRequest {
+        conn_id: conn_id.to_string(),
+        addr: parse_u64(matches.value_of(ADDR).unwrap())
+            .expect("integer parse failed, address must be u64 value"),
+        len: matches
+            .value_of(LEN)
+            .map(|l| parse_u64(l).expect("integer parse failed, len must be u64 value"))
+            .unwrap_or_default(),
+        chunk_size: matches
+            .value_of(CHUNK_SIZE)
+            .map(|c| parse_u64(c).expect("integer parse failed, chunk size must be u64 value"))
+            .unwrap_or_default(),
+    };
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let result = rt.block_on(async {
+        let stream = dispatch_request_async(conf, request).await?;
+        write_dump(stream, output).await
+    });
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok((written, unreadable)) => println!(
+            "dumped {:x} bytes to {} ({:x} bytes unreadable)",
+            written, output, unreadable
+        ),
+    }
+}
@@ -0,0 +1,34 @@
+mod dump;
+mod read;
+mod write;
+
+use crate::Config;
+
+use clap::{App, ArgMatches, SubCommand};
+
+use log::trace;
+
+pub const COMMAND_STR: &str = "phys";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("access physical memory")
+        .subcommand(dump::command_definition())
+        .subcommand(read::command_definition())
+        .subcommand(write::command_definition())
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    match matches.subcommand() {
+        (dump::COMMAND_STR, Some(matches)) => dump::handle_command(conf, matches),
+        (read::COMMAND_STR, Some(matches)) => read::handle_command(conf, matches),
+        (write::COMMAND_STR, Some(matches)) => write::handle_command(conf, matches),
+        _ => {
+            command_definition().print_help().ok();
+            println!();
+            ::std::process::exit(1)
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use crate::commands::format::{print_entry, OutputFormat, OUTPUT_FORMATS};
+use crate::commands::util::{exit_with_error, parse_u64};
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::{ReadPhysicalMemoryEntryRequest, ReadPhysicalMemoryRequest};
+
+pub const COMMAND_STR: &str = "read";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const ADDR: &str = "ADDR";
+const LEN: &str = "LEN";
+const FORMAT: &str = "FORMAT";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("reads physical memory, use 'phys dump' for large regions")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be read from")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(ADDR)
+                .help("the physical address to be read")
+                .index(2)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(LEN)
+                .help("number of bytes to read (default: the size of a single value or 256 bytes)")
+                .index(3)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name(FORMAT)
+                .help("the output format")
+                .long("format")
+                .short("f")
+                .takes_value(true)
+                .possible_values(OUTPUT_FORMATS)
+                .default_value("hex"),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let addr = parse_u64(matches.value_of(ADDR).unwrap())
+        .expect("integer parse failed, address must be u64 value");
+    let format = OutputFormat::parse(matches.value_of(FORMAT).unwrap()).unwrap();
+    let len = matches
+        .value_of(LEN)
+        .map(|l| parse_u64(l).expect("integer parse failed, len must be u64 value"))
+        .unwrap_or_else(|| format.default_len());
+
+    let result = dispatch_request(
+        conf,
+        ReadPhysicalMemoryRequest {
+            conn_id: conn_id.to_string(),
+            reads: vec![ReadPhysicalMemoryEntryRequest { addr, len }],
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => {
+            if let Some(entry) = r.reads.first() {
+                print_entry(addr, entry, format);
+            }
+        }
+    }
+}
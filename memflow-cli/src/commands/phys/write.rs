@@ -0,0 +1,74 @@
+use crate::commands::util::{exit_with_error, parse_u64, read_input};
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+use memflow_client::error::Error;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::{WritePhysicalMemoryEntryRequest, WritePhysicalMemoryRequest};
+
+pub const COMMAND_STR: &str = "write";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const ADDR: &str = "ADDR";
+const DATA: &str = "DATA";
+const FILE: &str = "FILE";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("writes physical memory")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be written to")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(ADDR)
+                .help("the physical address to be written")
+                .index(2)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(DATA)
+                .help("the bytes to be written as hex string (e.g. \"90 90 cc\")")
+                .index(3)
+                .required_unless(FILE)
+                .conflicts_with(FILE),
+        )
+        .arg(
+            Arg::with_name(FILE)
+                .help("a file containing the bytes to be written")
+                .long("file")
+                .short("i")
+                .takes_value(true),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let addr = parse_u64(matches.value_of(ADDR).unwrap())
+        .expect("integer parse failed, address must be u64 value");
+    let data = match read_input(matches.value_of(DATA), matches.value_of(FILE)) {
+        Ok(data) => data,
+        Err(e) => exit_with_error(Error::Local(e)),
+    };
+    let len = data.len();
+
+    let result = dispatch_request(
+        conf,
+        WritePhysicalMemoryRequest {
+            conn_id: conn_id.to_string(),
+            writes: vec![WritePhysicalMemoryEntryRequest { addr, data }],
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(_) => println!("wrote {:x} bytes at {:x}", len, addr),
+    }
+}
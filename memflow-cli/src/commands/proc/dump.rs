@@ -0,0 +1,157 @@
+use crate::commands::dump::write_dump;
+use crate::commands::util::{
+    exit_with_error, parse_address, parse_process_selector, parse_u64, PROCESS_SELECTOR_HELP,
+};
+use crate::Config;
+use memflow_client::dispatch::{dispatch_request, dispatch_request_async};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::{error, info, trace};
+
+pub const COMMAND_STR: &str = "dump";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const PROCESS: &str = "PROCESS";
+const OUTPUT: &str = "OUTPUT";
+const ADDR: &str = "ADDR";
+const LEN: &str = "LEN";
+const BASE_OFFSETS: &str = "BASE_OFFSETS";
+const CHUNK_SIZE: &str = "CHUNK_SIZE";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("dumps virtual memory of a process into a file")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be used")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(PROCESS)
+                .help(PROCESS_SELECTOR_HELP)
+                .index(2)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(OUTPUT)
+                .help("the file the dump is written to")
+                .index(3)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(ADDR)
+                .help("virtual address to start the dump at, either absolute or relative to a module (e.g. ntdll.dll+0x1000) (default: the main module)")
+                .long("addr")
+                .short("a")
+                .takes_value(true)
+                .requires(LEN)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name(LEN)
+                .help("number of bytes to dump")
+                .long("len")
+                .short("l")
+                .takes_value(true)
+                .requires(ADDR)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name(BASE_OFFSETS)
+                .help("treat the address as an offset to the base address of the process")
+                .long("base-offsets")
+                .short("b")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name(CHUNK_SIZE)
+                .help("size of each transferred chunk in bytes (default: 1 MiB)")
+                .long("chunk-size")
+                .takes_value(true)
+                .required(false),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let process = matches.value_of(PROCESS).unwrap();
+    let output = matches.value_of(OUTPUT).unwrap();
+
+    let (module, addr, len, base_offsets) = match (matches.value_of(ADDR), matches.value_of(LEN)) {
+        (Some(addr), Some(len)) => {
+            let (module, addr) = parse_address(addr)
+                .expect("address parse failed, address must be a u64 value or module+offset");
+            (
+                module,
+                addr,
+                parse_u64(len).expect("integer parse failed, len must be u64 value"),
+                matches.is_present(BASE_OFFSETS),
+            )
+        }
+        _ => {
+            // default to the main module of the process
+            let result = dispatch_request(
+                conf,
+                memflow_daemon::memflow_rpc::ProcessInfoRequest {
+                    conn_id: conn_id.to_string(),
+                    process: Some(parse_process_selector(process)),
+                    ..Default::default()
+                },
+            );
+
+            let result = match result {
+                Ok(r) => r,
+                Err(e) => exit_with_error(e),
+            };
+
+            let module = result
+                .process
+                .as_ref()
+                .and_then(|p| result.modules.iter().find(|m| m.base == p.section_base));
+
+            match module {
+                Some(module) => {
+                    info!("dumping main module {}", module.name);
+                    (String::new(), module.base, module.size, false)
+                }
+                None => {
+                    error!("unable to find the main module of process {}", process);
+                    return;
+                }
+            }
+        }
+    };
+
+    let request = memflow_daemon::memflow_rpc::DumpVirtualMemoryRequest {
+        conn_id: conn_id.to_string(),
+        process: Some(parse_process_selector(process)),
+        base_offsets,
+        module,
+        addr,
+        len,
+        chunk_size: matches
+            .value_of(CHUNK_SIZE)
+            .map(|c| parse_u64(c).expect("integer parse failed, chunk size must be u64 value"))
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let result = rt.block_on(async {
+        let stream = dispatch_request_async(conf, request).await?;
+        write_dump(stream, output).await
+    });
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok((written, unreadable)) => println!(
+            "dumped {:x} bytes to {} ({:x} bytes unreadable)",
+            written, output, unreadable
+        ),
+    }
+}
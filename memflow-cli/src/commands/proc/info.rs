@@ -1,15 +1,16 @@
+use crate::commands::util::{exit_with_error, parse_process_selector, PROCESS_SELECTOR_HELP};
 use crate::Config;
 use memflow_client::dispatch::dispatch_request;
 
 use clap::{App, Arg, ArgMatches, SubCommand};
 
-use log::{error, trace};
+use log::trace;
 
 pub const COMMAND_STR: &str = "info";
 
 const CONNECTION_ID: &str = "CONNECTION_ID";
 
-const PID: &str = "PID";
+const PROCESS: &str = "PROCESS";
 
 pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name(COMMAND_STR)
@@ -21,8 +22,8 @@ pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
                 .required(true),
         )
         .arg(
-            Arg::with_name(PID)
-                .help("pid of process to gather information")
+            Arg::with_name(PROCESS)
+                .help(PROCESS_SELECTOR_HELP)
                 .index(2)
                 .required(true),
         )
@@ -32,20 +33,19 @@ pub fn handle_command(conf: &Config, matches: &ArgMatches) {
     trace!("handling command");
 
     let conn_id = matches.value_of(CONNECTION_ID).unwrap();
-    let pid = matches.value_of(PID).unwrap();
+    let process = matches.value_of(PROCESS).unwrap();
 
     let result = dispatch_request(
         conf,
         memflow_daemon::memflow_rpc::ProcessInfoRequest {
             conn_id: conn_id.to_string(),
-            pid: pid
-                .parse()
-                .expect("integer parse failed, pid must be u32 value"),
+            process: Some(parse_process_selector(process)),
+            ..Default::default()
         },
     );
 
     match result {
-        Err(e) => error!("{:#?}", e),
+        Err(e) => exit_with_error(e),
         Ok(r) => println!("{:#?}", r.process),
     }
 }
@@ -1,9 +1,10 @@
+use crate::commands::util::exit_with_error;
 use crate::Config;
 use memflow_client::dispatch::dispatch_request;
 
 use clap::{App, Arg, ArgMatches, SubCommand};
 
-use log::{error, trace};
+use log::trace;
 
 pub const COMMAND_STR: &str = "ls";
 
@@ -33,7 +34,7 @@ pub fn handle_command(conf: &Config, matches: &ArgMatches) {
     );
 
     match result {
-        Err(e) => error!("{:#?}", e),
+        Err(e) => exit_with_error(e),
         // Ok(r) => println!("{:#?}", "asdf"),
         Ok(r) => {
             let s: Vec<String> = r
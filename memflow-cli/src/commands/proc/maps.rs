@@ -0,0 +1,85 @@
+use crate::commands::util::{exit_with_error, parse_process_selector, PROCESS_SELECTOR_HELP};
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::{ListMemoryRegionsRequest, MemoryRegion, MemoryRegionKind};
+
+pub const COMMAND_STR: &str = "maps";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const PROCESS: &str = "PROCESS";
+const PROTECTION: &str = "PROTECTION";
+const KERNEL: &str = "KERNEL";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("lists the virtual memory regions of a process")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be used")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(PROCESS)
+                .help(PROCESS_SELECTOR_HELP)
+                .index(2)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(PROTECTION)
+                .help("only lists regions with all of the given permissions (e.g. rw or x)")
+                .long("prot")
+                .short("p")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(KERNEL)
+                .help("also lists the kernel space regions mapped into the process")
+                .long("kernel")
+                .short("k"),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let process = matches.value_of(PROCESS).unwrap();
+
+    let result = dispatch_request(
+        conf,
+        ListMemoryRegionsRequest {
+            conn_id: conn_id.to_string(),
+            process: Some(parse_process_selector(process)),
+            protection: matches.value_of(PROTECTION).unwrap_or_default().to_string(),
+            kernel: matches.is_present(KERNEL),
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => r.regions.iter().for_each(print_region),
+    }
+}
+
+fn print_region(region: &MemoryRegion) {
+    let kind = match MemoryRegionKind::from_i32(region.kind) {
+        Some(MemoryRegionKind::Image) => "image  ",
+        Some(MemoryRegionKind::Kernel) => "kernel ",
+        _ => "private",
+    };
+    println!(
+        "0x{:016x}-0x{:016x} r{}{} {} {}",
+        region.start,
+        region.start + region.size,
+        if region.writable { 'w' } else { '-' },
+        if region.executable { 'x' } else { '-' },
+        kind,
+        region.module
+    );
+}
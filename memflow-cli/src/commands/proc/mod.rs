@@ -1,6 +1,20 @@
 mod ls;
+mod watch;
 
 mod info;
+mod maps;
+mod modules;
+
+mod dump;
+
+mod read;
+mod write;
+
+mod pointer;
+mod pointer_scan;
+mod scan;
+
+mod translate;
 
 use crate::Config;
 
@@ -14,7 +28,17 @@ pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name(COMMAND_STR)
         .about("manage processes")
         .subcommand(ls::command_definition())
+        .subcommand(watch::command_definition())
         .subcommand(info::command_definition())
+        .subcommand(modules::command_definition())
+        .subcommand(maps::command_definition())
+        .subcommand(translate::command_definition())
+        .subcommand(dump::command_definition())
+        .subcommand(read::command_definition())
+        .subcommand(write::command_definition())
+        .subcommand(scan::command_definition())
+        .subcommand(pointer::command_definition())
+        .subcommand(pointer_scan::command_definition())
 }
 
 pub fn handle_command(conf: &Config, matches: &ArgMatches) {
@@ -22,7 +46,17 @@ pub fn handle_command(conf: &Config, matches: &ArgMatches) {
 
     match matches.subcommand() {
         (ls::COMMAND_STR, Some(matches)) => ls::handle_command(conf, matches),
+        (watch::COMMAND_STR, Some(matches)) => watch::handle_command(conf, matches),
         (info::COMMAND_STR, Some(matches)) => info::handle_command(conf, matches),
+        (modules::COMMAND_STR, Some(matches)) => modules::handle_command(conf, matches),
+        (maps::COMMAND_STR, Some(matches)) => maps::handle_command(conf, matches),
+        (translate::COMMAND_STR, Some(matches)) => translate::handle_command(conf, matches),
+        (dump::COMMAND_STR, Some(matches)) => dump::handle_command(conf, matches),
+        (read::COMMAND_STR, Some(matches)) => read::handle_command(conf, matches),
+        (write::COMMAND_STR, Some(matches)) => write::handle_command(conf, matches),
+        (scan::COMMAND_STR, Some(matches)) => scan::handle_command(conf, matches),
+        (pointer::COMMAND_STR, Some(matches)) => pointer::handle_command(conf, matches),
+        (pointer_scan::COMMAND_STR, Some(matches)) => pointer_scan::handle_command(conf, matches),
         _ => {
             command_definition().print_help().ok();
             println!();
@@ -0,0 +1,122 @@
+use crate::commands::util::{exit_with_error, parse_process_selector, PROCESS_SELECTOR_HELP};
+use crate::Config;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_client::dispatch::{dispatch_request, dispatch_request_async, ModuleEventStream};
+use memflow_client::error::Result;
+use memflow_daemon::memflow_rpc::{
+    ModuleEventKind, ProcessInfoRequest, WatchModulesRequest, Win32ModuleInfo,
+};
+
+pub const COMMAND_STR: &str = "modules";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const PROCESS: &str = "PROCESS";
+const WATCH: &str = "WATCH";
+const INTERVAL: &str = "INTERVAL";
+const INITIAL: &str = "INITIAL";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("lists the modules of a process")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be used")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(PROCESS)
+                .help(PROCESS_SELECTOR_HELP)
+                .index(2)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(WATCH)
+                .help("prints modules as they are loaded and unloaded until the process exits")
+                .long("watch")
+                .short("w"),
+        )
+        .arg(
+            Arg::with_name(INTERVAL)
+                .help("poll interval in milliseconds when watching")
+                .long("interval")
+                .short("i")
+                .takes_value(true)
+                .default_value("1000"),
+        )
+        .arg(
+            Arg::with_name(INITIAL)
+                .help("also prints the modules which are already loaded when watching")
+                .long("initial"),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let process = matches.value_of(PROCESS).unwrap();
+
+    if matches.is_present(WATCH) {
+        let request = WatchModulesRequest {
+            conn_id: conn_id.to_string(),
+            process: Some(parse_process_selector(process)),
+            interval_ms: matches
+                .value_of(INTERVAL)
+                .unwrap()
+                .parse()
+                .expect("integer parse failed, interval must be u64 value"),
+            initial: matches.is_present(INITIAL),
+        };
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt.block_on(async {
+            let stream = dispatch_request_async(conf, request).await?;
+            print_events(stream).await
+        });
+
+        if let Err(e) = result {
+            exit_with_error(e)
+        }
+    } else {
+        let result = dispatch_request(
+            conf,
+            ProcessInfoRequest {
+                conn_id: conn_id.to_string(),
+                process: Some(parse_process_selector(process)),
+                ..Default::default()
+            },
+        );
+
+        match result {
+            Err(e) => exit_with_error(e),
+            Ok(r) => r.modules.iter().for_each(print_module),
+        }
+    }
+}
+
+async fn print_events(mut stream: ModuleEventStream) -> Result<()> {
+    while let Some(event) = stream.message().await? {
+        if let Some(module) = &event.module {
+            let kind = if event.kind == ModuleEventKind::Unloaded as i32 {
+                "unloaded"
+            } else {
+                "loaded  "
+            };
+            print!("{} {} ", event.timestamp, kind);
+            print_module(module);
+        }
+    }
+    Ok(())
+}
+
+fn print_module(module: &Win32ModuleInfo) {
+    println!(
+        "0x{:016x} 0x{:08x} {} ({})",
+        module.base, module.size, module.name, module.path
+    );
+}
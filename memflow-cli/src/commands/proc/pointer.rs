@@ -0,0 +1,73 @@
+use crate::commands::util::{exit_with_error, parse_process_selector, PROCESS_SELECTOR_HELP};
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::ResolvePointerRequest;
+
+pub const COMMAND_STR: &str = "pointer";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const PROCESS: &str = "PROCESS";
+const PATH: &str = "PATH";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("resolves a pointer path and prints each dereferenced pointer")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be used")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(PROCESS)
+                .help(PROCESS_SELECTOR_HELP)
+                .index(2)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(PATH)
+                .help("pointer path where brackets dereference a pointer, e.g. \"[[game.exe+0x1A2B]+0x10]+0x8\"")
+                .index(3)
+                .required(true),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let process = matches.value_of(PROCESS).unwrap();
+
+    let result = dispatch_request(
+        conf,
+        ResolvePointerRequest {
+            conn_id: conn_id.to_string(),
+            process: Some(parse_process_selector(process)),
+            path: matches.value_of(PATH).unwrap().to_string(),
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => {
+            for hop in r.hops.iter() {
+                if hop.readable {
+                    println!("[0x{:x}] = 0x{:x}", hop.addr, hop.value);
+                } else {
+                    println!("[0x{:x}] = unreadable", hop.addr);
+                }
+            }
+            if r.valid {
+                println!("0x{:x}", r.addr);
+            } else {
+                eprintln!("pointer path could not be resolved");
+                ::std::process::exit(1)
+            }
+        }
+    }
+}
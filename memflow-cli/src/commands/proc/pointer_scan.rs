@@ -0,0 +1,113 @@
+use crate::commands::util::{
+    exit_with_error, parse_address, parse_process_selector, parse_u64, PROCESS_SELECTOR_HELP,
+};
+use crate::Config;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_client::dispatch::{dispatch_request_async, PointerPathStream};
+use memflow_client::error::Result;
+use memflow_daemon::memflow_rpc::ScanPointersRequest;
+
+pub const COMMAND_STR: &str = "pointer-scan";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const PROCESS: &str = "PROCESS";
+const TARGET: &str = "TARGET";
+const MAX_DEPTH: &str = "MAX_DEPTH";
+const MAX_OFFSET: &str = "MAX_OFFSET";
+const MAX_RESULTS: &str = "MAX_RESULTS";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("finds pointer paths starting in a module which lead to an address")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be used")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(PROCESS)
+                .help(PROCESS_SELECTOR_HELP)
+                .index(2)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(TARGET)
+                .help("the address the paths lead to, either absolute or relative to a module (e.g. ntdll.dll+0x1000)")
+                .index(3)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(MAX_DEPTH)
+                .help("maximum number of dereferenced pointers")
+                .long("depth")
+                .short("d")
+                .takes_value(true)
+                .default_value("3"),
+        )
+        .arg(
+            Arg::with_name(MAX_OFFSET)
+                .help("maximum offset added to each pointer")
+                .long("offset")
+                .short("o")
+                .takes_value(true)
+                .default_value("0x1000"),
+        )
+        .arg(
+            Arg::with_name(MAX_RESULTS)
+                .help("maximum number of paths")
+                .long("max")
+                .short("n")
+                .takes_value(true)
+                .default_value("100"),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let process = matches.value_of(PROCESS).unwrap();
+    let (module, target) = parse_address(matches.value_of(TARGET).unwrap())
+        .expect("address parse failed, address must be a u64 value or module+offset");
+
+    let request = ScanPointersRequest {
+        conn_id: conn_id.to_string(),
+        process: Some(parse_process_selector(process)),
+        target,
+        max_depth: matches
+            .value_of(MAX_DEPTH)
+            .unwrap()
+            .parse()
+            .expect("integer parse failed, depth must be u32 value"),
+        max_offset: parse_u64(matches.value_of(MAX_OFFSET).unwrap())
+            .expect("integer parse failed, offset must be u64 value"),
+        max_results: matches
+            .value_of(MAX_RESULTS)
+            .unwrap()
+            .parse()
+            .expect("integer parse failed, max must be u32 value"),
+        module,
+    };
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let result = rt.block_on(async {
+        let stream = dispatch_request_async(conf, request).await?;
+        print_paths(stream).await
+    });
+
+    if let Err(e) = result {
+        exit_with_error(e)
+    }
+}
+
+async fn print_paths(mut stream: PointerPathStream) -> Result<()> {
+    while let Some(path) = stream.message().await? {
+        println!("{}", path.path);
+    }
+    Ok(())
+}
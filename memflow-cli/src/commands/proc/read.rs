@@ -0,0 +1,103 @@
+use crate::commands::format::{print_entry, OutputFormat, OUTPUT_FORMATS};
+use crate::commands::util::{
+    exit_with_error, parse_address, parse_process_selector, parse_u64, PROCESS_SELECTOR_HELP,
+};
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::{ReadVirtualMemoryEntryRequest, ReadVirtualMemoryRequest};
+
+pub const COMMAND_STR: &str = "read";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const PROCESS: &str = "PROCESS";
+const ADDR: &str = "ADDR";
+const LEN: &str = "LEN";
+const FORMAT: &str = "FORMAT";
+const BASE_OFFSETS: &str = "BASE_OFFSETS";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("reads virtual memory of a process, use 'proc dump' for large regions")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be used")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(PROCESS)
+                .help(PROCESS_SELECTOR_HELP)
+                .index(2)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(ADDR)
+                .help("virtual address to be read, either absolute or relative to a module (e.g. ntdll.dll+0x1000)")
+                .index(3)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(LEN)
+                .help("number of bytes to read (default: the size of a single value or 256 bytes)")
+                .index(4)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name(FORMAT)
+                .help("the output format")
+                .long("format")
+                .short("f")
+                .takes_value(true)
+                .possible_values(OUTPUT_FORMATS)
+                .default_value("hex"),
+        )
+        .arg(
+            Arg::with_name(BASE_OFFSETS)
+                .help("treat the address as an offset to the base address of the process")
+                .long("base-offsets")
+                .short("b")
+                .takes_value(false)
+                .required(false),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let process = matches.value_of(PROCESS).unwrap();
+    let (module, addr) = parse_address(matches.value_of(ADDR).unwrap())
+        .expect("address parse failed, address must be a u64 value or module+offset");
+    let format = OutputFormat::parse(matches.value_of(FORMAT).unwrap()).unwrap();
+    let len = matches
+        .value_of(LEN)
+        .map(|l| parse_u64(l).expect("integer parse failed, len must be u64 value"))
+        .unwrap_or_else(|| format.default_len());
+
+    let result = dispatch_request(
+        conf,
+        ReadVirtualMemoryRequest {
+            conn_id: conn_id.to_string(),
+            process: Some(parse_process_selector(process)),
+            base_offsets: matches.is_present(BASE_OFFSETS),
+            reads: vec![ReadVirtualMemoryEntryRequest { addr, len }],
+            module,
+            ..Default::default()
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => {
+            // module and base relative addresses are shown as the resolved absolute address
+            if let Some(entry) = r.reads.first() {
+                print_entry(r.base + addr, entry, format);
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::commands::util::{
+    exit_with_error, parse_address, parse_process_selector, parse_u64, PROCESS_SELECTOR_HELP,
+};
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::{ScanPatternRequest, ScanRange};
+
+pub const COMMAND_STR: &str = "scan";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const PROCESS: &str = "PROCESS";
+const PATTERN: &str = "PATTERN";
+const MASK: &str = "MASK";
+const MODULE: &str = "MODULE";
+const RANGE: &str = "RANGE";
+const MAX_MATCHES: &str = "MAX_MATCHES";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("scans the modules of a process for a byte pattern")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be used")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(PROCESS)
+                .help(PROCESS_SELECTOR_HELP)
+                .index(2)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(PATTERN)
+                .help("IDA-style pattern with '??' wildcards, e.g. \"48 8B 05 ?? ?? ?? ?? 48 85 C0\"")
+                .index(3)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(MASK)
+                .help("code-style mask where '?' marks a wildcard byte, e.g. xxx????xxx")
+                .long("mask")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(MODULE)
+                .help("only scan this module (can be repeated)")
+                .long("module")
+                .short("m")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name(RANGE)
+                .help("scan the range ADDR:LEN, the address is either absolute or relative to a module, e.g. ntdll.dll+0x1000:0x2000 (can be repeated)")
+                .long("range")
+                .short("r")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name(MAX_MATCHES)
+                .help("maximum number of matches")
+                .long("max")
+                .short("n")
+                .takes_value(true)
+                .default_value("1000"),
+        )
+}
+
+fn parse_range(value: &str) -> Option<ScanRange> {
+    let idx = value.rfind(':')?;
+    let (module, addr) = parse_address(&value[..idx])?;
+    let len = parse_u64(&value[idx + 1..])?;
+    Some(ScanRange { addr, len, module })
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let process = matches.value_of(PROCESS).unwrap();
+    let ranges = matches
+        .values_of(RANGE)
+        .map(|ranges| {
+            ranges
+                .map(|r| parse_range(r).expect("range parse failed, range must be ADDR:LEN"))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let result = dispatch_request(
+        conf,
+        ScanPatternRequest {
+            conn_id: conn_id.to_string(),
+            process: Some(parse_process_selector(process)),
+            pattern: matches.value_of(PATTERN).unwrap().to_string(),
+            mask: matches.value_of(MASK).unwrap_or_default().to_string(),
+            modules: matches
+                .values_of(MODULE)
+                .map(|modules| modules.map(str::to_string).collect())
+                .unwrap_or_default(),
+            ranges,
+            max_matches: matches
+                .value_of(MAX_MATCHES)
+                .unwrap()
+                .parse()
+                .expect("integer parse failed, max must be u32 value"),
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => {
+            for m in r.matches.iter() {
+                if m.module.is_empty() {
+                    println!("0x{:x}", m.addr);
+                } else {
+                    println!("0x{:x} {}+0x{:x}", m.addr, m.module, m.offset);
+                }
+            }
+            if r.truncated {
+                println!(
+                    "stopped after {} matches, use --max to show more",
+                    r.matches.len()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::commands::util::{
+    exit_with_error, parse_process_selector, parse_u64, PROCESS_SELECTOR_HELP,
+};
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::{
+    PageTableEntry, PageTableLevel, TranslateAddressesRequest, Translation,
+};
+
+pub const COMMAND_STR: &str = "translate";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const ADDRS: &str = "ADDRS";
+const PROCESS: &str = "PROCESS";
+const DTB: &str = "DTB";
+const WALK: &str = "WALK";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("translates virtual addresses to physical addresses")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be used")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(ADDRS)
+                .help("the virtual addresses to be translated")
+                .index(2)
+                .multiple(true)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(PROCESS)
+                .help(PROCESS_SELECTOR_HELP)
+                .long("process")
+                .short("p")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(DTB)
+                .help("physical address of the page tables to be used instead of a process")
+                .long("dtb")
+                .short("d")
+                .takes_value(true),
+        )
+        .group(
+            ArgGroup::with_name("ADDRESS_SPACE")
+                .args(&[PROCESS, DTB])
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(WALK)
+                .help("prints the page table entries read for each address")
+                .long("walk")
+                .short("w"),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let addrs = matches
+        .values_of(ADDRS)
+        .unwrap()
+        .map(|addr| parse_u64(addr).expect("address parse failed, address must be u64 value"))
+        .collect();
+
+    let result = dispatch_request(
+        conf,
+        TranslateAddressesRequest {
+            conn_id: conn_id.to_string(),
+            process: matches.value_of(PROCESS).map(parse_process_selector),
+            dtb: matches
+                .value_of(DTB)
+                .map(|dtb| parse_u64(dtb).expect("integer parse failed, dtb must be u64 value"))
+                .unwrap_or_default(),
+            addrs,
+            walk: matches.is_present(WALK),
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => r.translations.iter().for_each(print_translation),
+    }
+}
+
+fn print_translation(translation: &Translation) {
+    if translation.valid {
+        println!(
+            "0x{:016x} -> 0x{:x} ({})",
+            translation.virt_addr,
+            translation.phys_addr,
+            format_page_size(translation.page_size)
+        );
+    } else {
+        println!("0x{:016x} -> not mapped", translation.virt_addr);
+    }
+    translation.walk.iter().for_each(print_entry);
+}
+
+fn format_page_size(page_size: u64) -> String {
+    match page_size {
+        s if s >= 1 << 30 => format!("{}G", s >> 30),
+        s if s >= 1 << 20 => format!("{}M", s >> 20),
+        s => format!("{}K", s >> 10),
+    }
+}
+
+fn print_entry(entry: &PageTableEntry) {
+    let level = match PageTableLevel::from_i32(entry.level) {
+        Some(PageTableLevel::Pml4) => "PML4",
+        Some(PageTableLevel::Pdpt) => "PDPT",
+        Some(PageTableLevel::Pd) => "PD  ",
+        _ => "PT  ",
+    };
+
+    if !entry.readable {
+        println!("  {} [0x{:x}] unreadable", level, entry.addr);
+        return;
+    }
+
+    let flags = [
+        (entry.present, "present"),
+        (entry.writable, "writable"),
+        (entry.user, "user"),
+        (entry.accessed, "accessed"),
+        (entry.dirty, "dirty"),
+        (entry.large_page, "large"),
+        (entry.no_execute, "nx"),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, name)| *name)
+    .collect::<Vec<_>>()
+    .join(" ");
+    println!(
+        "  {} [0x{:x}] = 0x{:016x} {}",
+        level, entry.addr, entry.value, flags
+    );
+}
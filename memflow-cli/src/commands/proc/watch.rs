@@ -0,0 +1,102 @@
+use crate::commands::util::exit_with_error;
+use crate::Config;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_client::dispatch::{dispatch_request_async, ProcessEventStream};
+use memflow_client::error::Result;
+use memflow_daemon::memflow_rpc::{ProcessEvent, ProcessEventKind, WatchProcessesRequest};
+
+pub const COMMAND_STR: &str = "watch";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const NAMES: &str = "NAMES";
+const INTERVAL: &str = "INTERVAL";
+const INITIAL: &str = "INITIAL";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("prints processes as they are created and exit until interrupted")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be watched")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(NAMES)
+                .help("only show processes matching one of these names or name patterns containing '*' or '?'")
+                .index(2)
+                .multiple(true)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name(INTERVAL)
+                .help("poll interval in milliseconds")
+                .long("interval")
+                .short("i")
+                .takes_value(true)
+                .default_value("1000"),
+        )
+        .arg(
+            Arg::with_name(INITIAL)
+                .help("also prints the processes which are already running")
+                .long("initial"),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let request = WatchProcessesRequest {
+        conn_id: matches.value_of(CONNECTION_ID).unwrap().to_string(),
+        interval_ms: matches
+            .value_of(INTERVAL)
+            .unwrap()
+            .parse()
+            .expect("integer parse failed, interval must be u64 value"),
+        names: matches
+            .values_of(NAMES)
+            .map(|names| names.map(str::to_string).collect())
+            .unwrap_or_default(),
+        initial: matches.is_present(INITIAL),
+    };
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let result = rt.block_on(async {
+        let stream = dispatch_request_async(conf, request).await?;
+        print_events(stream).await
+    });
+
+    if let Err(e) = result {
+        exit_with_error(e)
+    }
+}
+
+async fn print_events(mut stream: ProcessEventStream) -> Result<()> {
+    while let Some(event) = stream.message().await? {
+        print_event(&event);
+    }
+    Ok(())
+}
+
+fn print_event(event: &ProcessEvent) {
+    let process = match &event.process {
+        Some(process) => process,
+        None => return,
+    };
+
+    if event.kind == ProcessEventKind::Exited as i32 {
+        println!(
+            "{} exited  Name: {}, Pid: {}, Exit status: {}",
+            event.timestamp, process.name, process.pid, process.exit_status
+        );
+    } else {
+        println!(
+            "{} created Name: {}, Pid: {}, Address: 0x{:x}",
+            event.timestamp, process.name, process.pid, process.address
+        );
+    }
+}
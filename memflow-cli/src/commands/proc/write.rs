@@ -0,0 +1,97 @@
+use crate::commands::util::{
+    exit_with_error, parse_address, parse_process_selector, read_input, PROCESS_SELECTOR_HELP,
+};
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+use memflow_client::error::Error;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::{WriteVirtualMemoryEntryRequest, WriteVirtualMemoryRequest};
+
+pub const COMMAND_STR: &str = "write";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const PROCESS: &str = "PROCESS";
+const ADDR: &str = "ADDR";
+const DATA: &str = "DATA";
+const FILE: &str = "FILE";
+const BASE_OFFSETS: &str = "BASE_OFFSETS";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("writes virtual memory of a process")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be used")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(PROCESS)
+                .help(PROCESS_SELECTOR_HELP)
+                .index(2)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(ADDR)
+                .help("virtual address to be written, either absolute or relative to a module (e.g. ntdll.dll+0x1000)")
+                .index(3)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(DATA)
+                .help("the bytes to be written as hex string (e.g. \"90 90 cc\")")
+                .index(4)
+                .required_unless(FILE)
+                .conflicts_with(FILE),
+        )
+        .arg(
+            Arg::with_name(FILE)
+                .help("a file containing the bytes to be written")
+                .long("file")
+                .short("i")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(BASE_OFFSETS)
+                .help("treat the address as an offset to the base address of the process")
+                .long("base-offsets")
+                .short("b")
+                .takes_value(false)
+                .required(false),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let process = matches.value_of(PROCESS).unwrap();
+    let (module, addr) = parse_address(matches.value_of(ADDR).unwrap())
+        .expect("address parse failed, address must be a u64 value or module+offset");
+    let data = match read_input(matches.value_of(DATA), matches.value_of(FILE)) {
+        Ok(data) => data,
+        Err(e) => exit_with_error(Error::Local(e)),
+    };
+    let len = data.len();
+
+    let result = dispatch_request(
+        conf,
+        WriteVirtualMemoryRequest {
+            conn_id: conn_id.to_string(),
+            process: Some(parse_process_selector(process)),
+            base_offsets: matches.is_present(BASE_OFFSETS),
+            writes: vec![WriteVirtualMemoryEntryRequest { addr, data }],
+            module,
+            ..Default::default()
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => println!("wrote {:x} bytes at {:x}", len, r.base + addr),
+    }
+}
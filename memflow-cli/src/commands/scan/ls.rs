@@ -0,0 +1,31 @@
+use super::print_session;
+use crate::commands::util::exit_with_error;
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::ListScanSessionsRequest;
+
+pub const COMMAND_STR: &str = "ls";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR).about("lists all scan sessions")
+}
+
+pub fn handle_command(conf: &Config, _matches: &ArgMatches) {
+    trace!("handling command");
+
+    let result = dispatch_request(conf, ListScanSessionsRequest {});
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => {
+            for session in r.sessions.iter() {
+                print_session(session);
+            }
+        }
+    }
+}
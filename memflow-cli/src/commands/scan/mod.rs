@@ -0,0 +1,104 @@
+mod ls;
+mod new;
+mod next;
+mod results;
+mod rm;
+
+use crate::Config;
+
+use clap::{App, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::{ScanCompare, ScanSession, ScanValueType};
+
+pub const COMMAND_STR: &str = "scan";
+
+/// Value types supported by scan sessions, in the order of [`ScanValueType`].
+const VALUE_TYPES: &[&str] = &[
+    "u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64", "f32", "f64", "string",
+];
+
+/// Comparisons supported by scan sessions.
+const COMPARISONS: &[&str] = &[
+    "equal",
+    "changed",
+    "unchanged",
+    "increased",
+    "decreased",
+    "range",
+];
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("manages value scan sessions")
+        .subcommand(new::command_definition())
+        .subcommand(next::command_definition())
+        .subcommand(ls::command_definition())
+        .subcommand(results::command_definition())
+        .subcommand(rm::command_definition())
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    match matches.subcommand() {
+        (new::COMMAND_STR, Some(matches)) => new::handle_command(conf, matches),
+        (next::COMMAND_STR, Some(matches)) => next::handle_command(conf, matches),
+        (ls::COMMAND_STR, Some(matches)) => ls::handle_command(conf, matches),
+        (results::COMMAND_STR, Some(matches)) => results::handle_command(conf, matches),
+        (rm::COMMAND_STR, Some(matches)) => rm::handle_command(conf, matches),
+        _ => {
+            command_definition().print_help().ok();
+            println!();
+            ::std::process::exit(1)
+        }
+    }
+}
+
+fn parse_value_type(value_type: &str) -> ScanValueType {
+    match value_type {
+        "u8" => ScanValueType::U8,
+        "u16" => ScanValueType::U16,
+        "u32" => ScanValueType::U32,
+        "u64" => ScanValueType::U64,
+        "i8" => ScanValueType::I8,
+        "i16" => ScanValueType::I16,
+        "i32" => ScanValueType::I32,
+        "i64" => ScanValueType::I64,
+        "f32" => ScanValueType::F32,
+        "f64" => ScanValueType::F64,
+        _ => ScanValueType::String,
+    }
+}
+
+fn parse_compare(compare: &str) -> ScanCompare {
+    match compare {
+        "changed" => ScanCompare::Changed,
+        "unchanged" => ScanCompare::Unchanged,
+        "increased" => ScanCompare::Increased,
+        "decreased" => ScanCompare::Decreased,
+        "range" => ScanCompare::Range,
+        _ => ScanCompare::Equal,
+    }
+}
+
+fn print_session(session: &ScanSession) {
+    let value_type = ScanValueType::from_i32(session.value_type)
+        .map(|t| VALUE_TYPES[t as usize])
+        .unwrap_or("unknown");
+    println!(
+        "{} (conn {}, pid {}, {}): {} results after {} scans{}",
+        session.id,
+        session.conn_id,
+        session.pid,
+        value_type,
+        session.results,
+        session.scans,
+        if session.truncated {
+            ", the first scan has been truncated"
+        } else {
+            ""
+        }
+    );
+}
@@ -0,0 +1,104 @@
+use super::{parse_value_type, print_session, VALUE_TYPES};
+use crate::commands::util::{exit_with_error, parse_process_selector, PROCESS_SELECTOR_HELP};
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::{CreateScanSessionRequest, ScanCompare};
+
+pub const COMMAND_STR: &str = "new";
+
+const CONNECTION_ID: &str = "CONNECTION_ID";
+const PROCESS: &str = "PROCESS";
+const VALUE: &str = "VALUE";
+const UPPER: &str = "UPPER";
+const TYPE: &str = "TYPE";
+const ALIGNMENT: &str = "ALIGNMENT";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("creates a scan session and scans the process for a value")
+        .arg(
+            Arg::with_name(CONNECTION_ID)
+                .help("the connection to be used")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(PROCESS)
+                .help(PROCESS_SELECTOR_HELP)
+                .index(2)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(VALUE)
+                .help("the value to be searched, or the lower bound if --upper is set")
+                .index(3)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(UPPER)
+                .help("searches for values between VALUE and this value (inclusive)")
+                .long("upper")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(TYPE)
+                .help("the type of the value")
+                .long("type")
+                .short("t")
+                .takes_value(true)
+                .possible_values(VALUE_TYPES)
+                .default_value("u32"),
+        )
+        .arg(
+            Arg::with_name(ALIGNMENT)
+                .help("alignment of the scanned addresses (default: the size of the value)")
+                .long("alignment")
+                .short("a")
+                .takes_value(true),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let conn_id = matches.value_of(CONNECTION_ID).unwrap();
+    let process = matches.value_of(PROCESS).unwrap();
+    let upper = matches.value_of(UPPER);
+
+    let result = dispatch_request(
+        conf,
+        CreateScanSessionRequest {
+            conn_id: conn_id.to_string(),
+            process: Some(parse_process_selector(process)),
+            value_type: parse_value_type(matches.value_of(TYPE).unwrap()) as i32,
+            compare: if upper.is_some() {
+                ScanCompare::Range
+            } else {
+                ScanCompare::Equal
+            } as i32,
+            value: matches.value_of(VALUE).unwrap().to_string(),
+            upper: upper.unwrap_or_default().to_string(),
+            alignment: matches
+                .value_of(ALIGNMENT)
+                .map(|a| {
+                    a.parse()
+                        .expect("integer parse failed, alignment must be u32 value")
+                })
+                .unwrap_or_default(),
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => {
+            if let Some(session) = r.session {
+                print_session(&session);
+            }
+        }
+    }
+}
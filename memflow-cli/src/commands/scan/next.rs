@@ -0,0 +1,70 @@
+use super::{parse_compare, print_session, COMPARISONS};
+use crate::commands::util::exit_with_error;
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::NextScanRequest;
+
+pub const COMMAND_STR: &str = "next";
+
+const ID: &str = "ID";
+const COMPARE: &str = "COMPARE";
+const VALUE: &str = "VALUE";
+const UPPER: &str = "UPPER";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("narrows down the results of a scan session")
+        .arg(
+            Arg::with_name(ID)
+                .help("the id of the scan session")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(COMPARE)
+                .help("how the current values are compared")
+                .index(2)
+                .possible_values(COMPARISONS)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(VALUE)
+                .help("the value for 'equal' or the lower bound for 'range'")
+                .index(3)
+                .required_ifs(&[(COMPARE, "equal"), (COMPARE, "range")]),
+        )
+        .arg(
+            Arg::with_name(UPPER)
+                .help("the upper bound for 'range'")
+                .index(4)
+                .required_if(COMPARE, "range"),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let result = dispatch_request(
+        conf,
+        NextScanRequest {
+            id: matches.value_of(ID).unwrap().to_string(),
+            compare: parse_compare(matches.value_of(COMPARE).unwrap()) as i32,
+            value: matches.value_of(VALUE).unwrap_or_default().to_string(),
+            upper: matches.value_of(UPPER).unwrap_or_default().to_string(),
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => {
+            if let Some(session) = r.session {
+                print_session(&session);
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::commands::util::{exit_with_error, parse_u64};
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::ListScanResultsRequest;
+
+pub const COMMAND_STR: &str = "results";
+
+const ID: &str = "ID";
+const OFFSET: &str = "OFFSET";
+const LIMIT: &str = "LIMIT";
+const REFRESH: &str = "REFRESH";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("lists the results of a scan session")
+        .arg(
+            Arg::with_name(ID)
+                .help("the id of the scan session")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name(OFFSET)
+                .help("index of the first result")
+                .long("offset")
+                .takes_value(true)
+                .default_value("0"),
+        )
+        .arg(
+            Arg::with_name(LIMIT)
+                .help("maximum number of results")
+                .long("limit")
+                .short("n")
+                .takes_value(true)
+                .default_value("100"),
+        )
+        .arg(
+            Arg::with_name(REFRESH)
+                .help("shows the current values instead of the values of the last scan")
+                .long("refresh")
+                .short("r"),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let result = dispatch_request(
+        conf,
+        ListScanResultsRequest {
+            id: matches.value_of(ID).unwrap().to_string(),
+            offset: parse_u64(matches.value_of(OFFSET).unwrap())
+                .expect("integer parse failed, offset must be u64 value"),
+            limit: parse_u64(matches.value_of(LIMIT).unwrap())
+                .expect("integer parse failed, limit must be u64 value"),
+            refresh: matches.is_present(REFRESH),
+        },
+    );
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(r) => {
+            for result in r.results.iter() {
+                if result.module.is_empty() {
+                    println!("0x{:x} = {}", result.addr, result.value);
+                } else {
+                    println!(
+                        "0x{:x} ({}+0x{:x}) = {}",
+                        result.addr, result.module, result.offset, result.value
+                    );
+                }
+            }
+            println!("{} of {} results", r.results.len(), r.total);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::commands::util::exit_with_error;
+use crate::Config;
+use memflow_client::dispatch::dispatch_request;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use log::trace;
+
+use memflow_daemon::memflow_rpc::CloseScanSessionRequest;
+
+pub const COMMAND_STR: &str = "rm";
+
+const ID: &str = "ID";
+
+pub fn command_definition<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_STR)
+        .about("closes a scan session")
+        .arg(
+            Arg::with_name(ID)
+                .help("the id of the scan session")
+                .index(1)
+                .required(true),
+        )
+}
+
+pub fn handle_command(conf: &Config, matches: &ArgMatches) {
+    trace!("handling command");
+
+    let id = matches.value_of(ID).unwrap();
+
+    let result = dispatch_request(conf, CloseScanSessionRequest { id: id.to_string() });
+
+    match result {
+        Err(e) => exit_with_error(e),
+        Ok(_) => println!("Scan session closed"),
+    }
+}
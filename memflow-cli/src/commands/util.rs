@@ -0,0 +1,122 @@
+use memflow_client::error::Error;
+use memflow_daemon::memflow_rpc::{process_selector::Selector, ProcessSelector};
+
+use log::error;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Parses a number either as hexadecimal if prefixed with `0x` or as decimal otherwise.
+pub fn parse_u64(value: &str) -> Option<u64> {
+    if let Some(hex) = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        value.parse().ok()
+    }
+}
+
+/// Help text for arguments parsed by [`parse_process_selector`].
+pub const PROCESS_SELECTOR_HELP: &str =
+    "the process to be used: a pid, an exact name, a name pattern containing '*' or '?' or the address of the EPROCESS structure (0x...)";
+
+/// Parses a process selector from the command line.
+///
+/// Numbers are treated as pids, hexadecimal numbers prefixed with `0x` as EPROCESS addresses
+/// and anything else as a process name, or as a name pattern if it contains a wildcard.
+pub fn parse_process_selector(value: &str) -> ProcessSelector {
+    let selector = if let Ok(pid) = value.parse::<u32>() {
+        Selector::Pid(pid)
+    } else if let Some(address) = value.strip_prefix("0x").and_then(|_| parse_u64(value)) {
+        Selector::Address(address)
+    } else if value.contains('*') || value.contains('?') {
+        Selector::NameGlob(value.to_string())
+    } else {
+        Selector::Name(value.to_string())
+    };
+
+    ProcessSelector {
+        selector: Some(selector),
+    }
+}
+
+/// Parses a virtual address which is either absolute or relative to a module, e.g. `ntdll.dll+0x1234`.
+///
+/// A module without an offset has to contain an extension (e.g. `ntdll.dll`)
+/// so mistyped addresses are not looked up as module names.
+///
+/// Returns the module name, which is empty for absolute addresses, and the address or offset.
+pub fn parse_address(value: &str) -> Option<(String, u64)> {
+    if let Some(addr) = parse_u64(value) {
+        return Some((String::new(), addr));
+    }
+
+    let (module, offset) = match value.rfind('+') {
+        Some(idx) => (value[..idx].trim(), parse_u64(value[idx + 1..].trim())?),
+        None if value.contains('.') => (value.trim(), 0),
+        None => return None,
+    };
+
+    if module.is_empty() {
+        None
+    } else {
+        Some((module.to_string(), offset))
+    }
+}
+
+/// Parses a hex string like `90 90 cc`, `9090cc` or `0x90,0x90,0xcc` into bytes.
+pub fn parse_hex(value: &str) -> Option<Vec<u8>> {
+    let digits = value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|s| {
+            s.strip_prefix("0x")
+                .or_else(|| s.strip_prefix("0X"))
+                .unwrap_or(s)
+        })
+        .collect::<String>();
+
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Returns the bytes to be written either from a hex string or from a file.
+pub fn read_input(hex: Option<&str>, file: Option<&str>) -> Result<Vec<u8>, String> {
+    match (hex, file) {
+        (_, Some(file)) => {
+            std::fs::read(file).map_err(|err| format!("unable to read {}: {}", file, err))
+        }
+        (Some(hex), None) => parse_hex(hex).ok_or_else(|| format!("invalid hex string: {}", hex)),
+        (None, None) => Err("no data to be written".to_string()),
+    }
+}
+
+/// Parses a point in time, either as unix timestamp in milliseconds
+/// or relative to now with a unit suffix, e.g. `30s`, `10m`, `2h` or `1d`.
+///
+/// Returns the unix timestamp in milliseconds.
+pub fn parse_since(value: &str) -> Option<u64> {
+    let unit = match value.chars().last()? {
+        's' => 1000,
+        'm' => 60 * 1000,
+        'h' => 60 * 60 * 1000,
+        'd' => 24 * 60 * 60 * 1000,
+        _ => return value.parse().ok(),
+    };
+
+    let ago = value[..value.len() - 1].parse::<u64>().ok()? * unit;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+    Some((now.as_millis() as u64).saturating_sub(ago))
+}
+
+/// Logs the error and exits with the exit code belonging to its kind.
+pub fn exit_with_error(err: Error) -> ! {
+    error!("{}", err);
+    std::process::exit(err.exit_code())
+}
@@ -6,6 +6,7 @@ use clap::{App, Arg};
 
 use log::{debug, LevelFilter};
 
+use memflow_client::config::ClientConfig;
 use memflow_client::dispatch::Config;
 
 #[cfg(not(target_os = "windows"))]
@@ -39,15 +40,19 @@ fn main() {
         )
         .subcommand(commands::connection::command_definition())
         .subcommand(commands::fuse::command_definition())
+        .subcommand(commands::phys::command_definition())
         .subcommand(commands::proc::command_definition())
+        .subcommand(commands::scan::command_definition())
         .subcommand(commands::gdb::command_definition())
+        .subcommand(commands::events::command_definition())
+        .subcommand(commands::audit::command_definition())
         .subcommand(commands::benchmark::command_definition());
 
     let matches = app.clone().get_matches();
 
     let config_path = matches.value_of("config").unwrap();
     let config_str = std::fs::read_to_string(config_path).unwrap();
-    let config: memflow_daemon::Config = serde_json::from_str(&config_str).unwrap();
+    let config: ClientConfig = serde_json::from_str(&config_str).unwrap();
 
     // setup verbosity
     let log_filter = match config
@@ -70,15 +75,15 @@ fn main() {
 
     // we first check the 'host' argument
     // in case 'host' is empty we check the 'config' argument
-    let conf = if let Some(host) = matches.value_of("host") {
-        Config {
-            host: host.to_string(),
-        }
-    } else {
-        debug!("loading host from configuration file: {}", config_path);
-        Config {
-            host: config.socket_addr,
-        }
+    let conf = Config {
+        host: if let Some(host) = matches.value_of("host") {
+            host.to_string()
+        } else {
+            debug!("loading host from configuration file: {}", config_path);
+            config.socket_addr
+        },
+        tls: config.tls,
+        auth_token: config.auth_token,
     };
     debug!("memflow host: {}", conf.host);
 
@@ -89,12 +94,24 @@ fn main() {
         (commands::fuse::COMMAND_STR, Some(subargv)) => {
             commands::fuse::handle_command(&conf, subargv)
         }
+        (commands::phys::COMMAND_STR, Some(subargv)) => {
+            commands::phys::handle_command(&conf, subargv)
+        }
         (commands::proc::COMMAND_STR, Some(subargv)) => {
             commands::proc::handle_command(&conf, subargv)
         }
+        (commands::scan::COMMAND_STR, Some(subargv)) => {
+            commands::scan::handle_command(&conf, subargv)
+        }
         (commands::gdb::COMMAND_STR, Some(subargv)) => {
             commands::gdb::handle_command(&conf, subargv)
         }
+        (commands::events::COMMAND_STR, Some(subargv)) => {
+            commands::events::handle_command(&conf, subargv)
+        }
+        (commands::audit::COMMAND_STR, Some(subargv)) => {
+            commands::audit::handle_command(&conf, subargv)
+        }
         (commands::benchmark::COMMAND_STR, Some(subargv)) => {
             commands::benchmark::handle_command(&conf, subargv)
         }
@@ -0,0 +1,27 @@
+use serde_derive::Deserialize;
+
+/// Settings of the client, usually read from `client.conf`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ClientConfig {
+    pub verbosity: Option<String>,
+    /// Address of the daemon, e.g. `http://127.0.0.1:8000`, `https://127.0.0.1:8000` or `unix:///run/memflow.sock`
+    pub socket_addr: String,
+    /// Required if the daemon has tls enabled, the address has to start with `https://` in that case
+    pub tls: Option<TlsConfig>,
+    /// Token sent with every request
+    pub auth_token: Option<String>,
+}
+
+/// TLS settings of the client.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// Path to the pem encoded ca certificate used to verify the daemon
+    pub ca_cert: Option<String>,
+    /// Path to the pem encoded client certificate, required if the daemon verifies clients
+    pub cert: Option<String>,
+    /// Path to the pem encoded private key of the client certificate
+    pub key: Option<String>,
+    /// Domain name the daemon certificate is verified against, defaults to the host
+    pub domain: Option<String>,
+}
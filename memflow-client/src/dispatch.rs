@@ -1,36 +1,169 @@
+use crate::config::TlsConfig;
+use crate::error::{Error, Result};
 use async_trait::async_trait;
-use memflow_daemon::error::Result;
 
 use memflow_daemon::memflow_rpc::memflow_client::MemflowClient;
 use memflow_daemon::memflow_rpc::{
-    CloseConnectionRequest, CloseConnectionResponse, FuseListRequest, FuseListResponse,
-    FuseMountRequest, FuseMountResponse, GdbAttachRequest, GdbAttachResponse, GdbListRequest,
-    GdbListResponse, ListConnectionsRequest, ListConnectionsResponse, ListProcessesRequest,
-    ListProcessesResponse, NewConnectionRequest, NewConnectionResponse,
-    PhysicalMemoryMetadataRequest, PhysicalMemoryMetadataResponse, ProcessInfoRequest,
-    ProcessInfoResponse, ReadPhysicalMemoryRequest, ReadPhysicalMemoryResponse,
-    ReadVirtualMemoryRequest, ReadVirtualMemoryResponse, WritePhysicalMemoryRequest,
-    WritePhysicalMemoryResponse, WriteVirtualMemoryRequest, WriteVirtualMemoryResponse,
+    AttachOsRequest, AttachOsResponse, CloseConnectionRequest, CloseConnectionResponse,
+    CloseScanSessionRequest, CloseScanSessionResponse, ConnectionCacheRequest,
+    ConnectionCacheResponse, CreateScanSessionRequest, CreateScanSessionResponse,
+    DumpMemoryResponse, DumpPhysicalMemoryRequest, DumpVirtualMemoryRequest, Event,
+    FuseListRequest, FuseListResponse, FuseMountRequest, FuseMountResponse, FuseUnmountRequest,
+    FuseUnmountResponse, GdbAttachRequest, GdbAttachResponse, GdbDetachRequest, GdbDetachResponse,
+    GdbListRequest, GdbListResponse, ListConnectionsRequest, ListConnectionsResponse,
+    ListMemoryRegionsRequest, ListMemoryRegionsResponse, ListProcessesRequest,
+    ListProcessesResponse, ListScanResultsRequest, ListScanResultsResponse,
+    ListScanSessionsRequest, ListScanSessionsResponse, ModuleEvent, NewConnectionRequest,
+    NewConnectionResponse, NextScanRequest, NextScanResponse, PhysicalMemoryMetadataRequest,
+    PhysicalMemoryMetadataResponse, PointerPath, ProcessEvent, ProcessInfoRequest,
+    ProcessInfoResponse, QueryAuditLogRequest, QueryAuditLogResponse, ReadPhysicalMemoryRequest,
+    ReadPhysicalMemoryResponse, ReadVirtualMemoryRequest, ReadVirtualMemoryResponse,
+    ResolvePointerRequest, ResolvePointerResponse, RevertWritesRequest, RevertWritesResponse,
+    ScanPatternRequest, ScanPatternResponse, ScanPointersRequest, TranslateAddressesRequest,
+    TranslateAddressesResponse, WatchEventsRequest, WatchModulesRequest, WatchProcessesRequest,
+    WritePhysicalMemoryRequest, WritePhysicalMemoryResponse, WriteVirtualMemoryRequest,
+    WriteVirtualMemoryResponse,
 };
+use std::str::FromStr;
 use tokio::runtime::Runtime;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::{interceptor::InterceptedService, Interceptor};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity, Uri};
+use tower::service_fn;
 
-pub type Client = MemflowClient<tonic::transport::Channel>;
+/// Metadata key carrying the auth token, must match the daemon.
+const AUTHORIZATION: &str = "authorization";
 
+pub type Client = MemflowClient<InterceptedService<Channel, AuthInterceptor>>;
+
+/// Attaches the auth token to every request.
+#[derive(Clone)]
+pub struct AuthInterceptor {
+    token: Option<MetadataValue<Ascii>>,
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(
+        &mut self,
+        mut request: tonic::Request<()>,
+    ) -> std::result::Result<tonic::Request<()>, tonic::Status> {
+        if let Some(token) = &self.token {
+            request.metadata_mut().insert(AUTHORIZATION, token.clone());
+        }
+        Ok(request)
+    }
+}
+
+/// Stream of chunks returned by DumpPhysicalMemoryRequest and DumpVirtualMemoryRequest.
+/// The stream can only be consumed within the runtime the request has been sent from.
+pub type DumpStream = tonic::Streaming<DumpMemoryResponse>;
+
+/// Stream of events returned by WatchEventsRequest, it only ends if the daemon shuts down.
+/// The stream can only be consumed within the runtime the request has been sent from.
+pub type EventStream = tonic::Streaming<Event>;
+
+/// Stream of process events returned by WatchProcessesRequest.
+/// The stream can only be consumed within the runtime the request has been sent from.
+pub type ProcessEventStream = tonic::Streaming<ProcessEvent>;
+
+/// Stream of module events returned by WatchModulesRequest.
+/// The stream can only be consumed within the runtime the request has been sent from.
+pub type ModuleEventStream = tonic::Streaming<ModuleEvent>;
+
+/// Stream of pointer paths returned by ScanPointersRequest.
+/// The stream can only be consumed within the runtime the request has been sent from.
+pub type PointerPathStream = tonic::Streaming<PointerPath>;
+
+#[derive(Clone, Default)]
 pub struct Config {
     pub host: String,
+    /// Required if the daemon has tls enabled, the host has to start with `https://` in that case
+    pub tls: Option<TlsConfig>,
+    /// Token sent with every request
+    pub auth_token: Option<String>,
 }
 
 /// This returns a Client and a Runtime. The client can only be used within the provided runtime
-pub fn create_client(conf: &Config) -> (Client, Runtime) {
-    let rt = Runtime::new().unwrap();
-    (rt.block_on(create_client_async(conf)), rt)
+pub fn create_client(conf: &Config) -> Result<(Client, Runtime)> {
+    let rt = create_runtime()?;
+    let client = rt.block_on(create_client_async(conf))?;
+    Ok((client, rt))
 }
 
 /// The client can only be used with the same runtime it has been created with
-pub async fn create_client_async(conf: &Config) -> Client {
-    MemflowClient::connect(conf.host.clone())
-        .await
-        .expect("Could not create connection client")
+pub async fn create_client_async(conf: &Config) -> Result<Client> {
+    let token = conf
+        .auth_token
+        .as_ref()
+        .map(|token| {
+            MetadataValue::from_str(&format!("Bearer {}", token))
+                .map_err(|_| Error::Local("invalid auth token".to_string()))
+        })
+        .transpose()?;
+
+    let channel = connect_channel(conf).await?;
+
+    Ok(MemflowClient::with_interceptor(
+        channel,
+        AuthInterceptor { token },
+    ))
+}
+
+fn create_runtime() -> Result<Runtime> {
+    Runtime::new().map_err(|err| Error::Local(format!("unable to create runtime: {}", err)))
+}
+
+async fn connect_channel(conf: &Config) -> Result<Channel> {
+    let socket_path = conf.host.strip_prefix("unix://");
+
+    // the uri is ignored for unix sockets but the endpoint still requires a valid one
+    let uri = match socket_path {
+        Some(_) => "http://[::]:50051".to_string(),
+        None => conf.host.clone(),
+    };
+    let mut endpoint = Channel::from_shared(uri)
+        .map_err(|_| Error::Local(format!("invalid host: {}", conf.host)))?;
+
+    if let Some(tls) = &conf.tls {
+        let mut tls_config = ClientTlsConfig::new();
+        if let Some(ca_cert) = &tls.ca_cert {
+            tls_config = tls_config.ca_certificate(Certificate::from_pem(read_pem(ca_cert)?));
+        }
+        if let (Some(cert), Some(key)) = (&tls.cert, &tls.key) {
+            tls_config = tls_config.identity(Identity::from_pem(read_pem(cert)?, read_pem(key)?));
+        }
+        if let Some(domain) = &tls.domain {
+            tls_config = tls_config.domain_name(domain.clone());
+        }
+        endpoint = endpoint.tls_config(tls_config)?;
+    }
+
+    match socket_path {
+        Some(path) => connect_unix(endpoint, path).await,
+        None => Ok(endpoint.connect().await?),
+    }
+}
+
+#[cfg(unix)]
+async fn connect_unix(endpoint: Endpoint, path: &str) -> Result<Channel> {
+    let path = path.to_string();
+    Ok(endpoint
+        .connect_with_connector(service_fn(move |_: Uri| {
+            tokio::net::UnixStream::connect(path.clone())
+        }))
+        .await?)
+}
+
+#[cfg(not(unix))]
+async fn connect_unix(_endpoint: Endpoint, path: &str) -> Result<Channel> {
+    Err(Error::Local(format!(
+        "unix sockets are not supported on this platform: {}",
+        path
+    )))
+}
+
+fn read_pem(path: &str) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|err| Error::Local(format!("unable to read {}: {}", path, err)))
 }
 
 /// Takes config and a request to send over the wire.
@@ -39,7 +172,7 @@ pub fn dispatch_request<R, S>(conf: &Config, req: R) -> Result<S>
 where
     tonic::Request<R>: DispatchMessage<tonic::Response<S>>,
 {
-    let rt = Runtime::new().unwrap();
+    let rt = create_runtime()?;
     rt.block_on(dispatch_request_async(conf, req))
 }
 
@@ -63,7 +196,7 @@ pub async fn dispatch_request_async<R, S>(conf: &Config, req: R) -> Result<S>
 where
     tonic::Request<R>: DispatchMessage<tonic::Response<S>>,
 {
-    let mut client = create_client_async(conf).await;
+    let mut client = create_client_async(conf).await?;
     dispatch_request_async_client(conf, req, &mut client).await
 }
 
@@ -130,6 +263,43 @@ impl DispatchMessage<tonic::Response<CloseConnectionResponse>>
     }
 }
 
+#[async_trait]
+impl DispatchMessage<tonic::Response<AttachOsResponse>> for tonic::Request<AttachOsRequest> {
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<AttachOsResponse>> {
+        client.attach_os(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<ConnectionCacheResponse>>
+    for tonic::Request<ConnectionCacheRequest>
+{
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<ConnectionCacheResponse>> {
+        client.connection_cache(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<RevertWritesResponse>>
+    for tonic::Request<RevertWritesRequest>
+{
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<RevertWritesResponse>> {
+        client.revert_writes(self).await.map_err(|x| x.into())
+    }
+}
+
 #[async_trait]
 impl DispatchMessage<tonic::Response<ReadPhysicalMemoryResponse>>
     for tonic::Request<ReadPhysicalMemoryRequest>
@@ -178,6 +348,20 @@ impl DispatchMessage<tonic::Response<PhysicalMemoryMetadataResponse>>
     }
 }
 
+#[async_trait]
+impl DispatchMessage<tonic::Response<DumpStream>> for tonic::Request<DumpPhysicalMemoryRequest> {
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<DumpStream>> {
+        client
+            .dump_physical_memory(self)
+            .await
+            .map_err(|x| x.into())
+    }
+}
+
 #[async_trait]
 impl DispatchMessage<tonic::Response<ReadVirtualMemoryResponse>>
     for tonic::Request<ReadVirtualMemoryRequest>
@@ -207,6 +391,17 @@ impl DispatchMessage<tonic::Response<WriteVirtualMemoryResponse>>
     }
 }
 
+#[async_trait]
+impl DispatchMessage<tonic::Response<DumpStream>> for tonic::Request<DumpVirtualMemoryRequest> {
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<DumpStream>> {
+        client.dump_virtual_memory(self).await.map_err(|x| x.into())
+    }
+}
+
 #[async_trait]
 impl DispatchMessage<tonic::Response<ListProcessesResponse>>
     for tonic::Request<ListProcessesRequest>
@@ -231,6 +426,154 @@ impl DispatchMessage<tonic::Response<ProcessInfoResponse>> for tonic::Request<Pr
     }
 }
 
+#[async_trait]
+impl DispatchMessage<tonic::Response<ProcessEventStream>>
+    for tonic::Request<WatchProcessesRequest>
+{
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<ProcessEventStream>> {
+        client.watch_processes(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<ModuleEventStream>> for tonic::Request<WatchModulesRequest> {
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<ModuleEventStream>> {
+        client.watch_modules(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<ScanPatternResponse>> for tonic::Request<ScanPatternRequest> {
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<ScanPatternResponse>> {
+        client.scan_pattern(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<CreateScanSessionResponse>>
+    for tonic::Request<CreateScanSessionRequest>
+{
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<CreateScanSessionResponse>> {
+        client.create_scan_session(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<NextScanResponse>> for tonic::Request<NextScanRequest> {
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<NextScanResponse>> {
+        client.next_scan(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<ListScanSessionsResponse>>
+    for tonic::Request<ListScanSessionsRequest>
+{
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<ListScanSessionsResponse>> {
+        client.list_scan_sessions(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<ListScanResultsResponse>>
+    for tonic::Request<ListScanResultsRequest>
+{
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<ListScanResultsResponse>> {
+        client.list_scan_results(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<CloseScanSessionResponse>>
+    for tonic::Request<CloseScanSessionRequest>
+{
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<CloseScanSessionResponse>> {
+        client.close_scan_session(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<ResolvePointerResponse>>
+    for tonic::Request<ResolvePointerRequest>
+{
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<ResolvePointerResponse>> {
+        client.resolve_pointer(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<PointerPathStream>> for tonic::Request<ScanPointersRequest> {
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<PointerPathStream>> {
+        client.scan_pointers(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<ListMemoryRegionsResponse>>
+    for tonic::Request<ListMemoryRegionsRequest>
+{
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<ListMemoryRegionsResponse>> {
+        client.list_memory_regions(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<TranslateAddressesResponse>>
+    for tonic::Request<TranslateAddressesRequest>
+{
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<TranslateAddressesResponse>> {
+        client.translate_addresses(self).await.map_err(|x| x.into())
+    }
+}
+
 #[async_trait]
 impl DispatchMessage<tonic::Response<FuseMountResponse>> for tonic::Request<FuseMountRequest> {
     async fn dispatch_message(
@@ -253,6 +596,17 @@ impl DispatchMessage<tonic::Response<FuseListResponse>> for tonic::Request<FuseL
     }
 }
 
+#[async_trait]
+impl DispatchMessage<tonic::Response<FuseUnmountResponse>> for tonic::Request<FuseUnmountRequest> {
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<FuseUnmountResponse>> {
+        client.fuse_unmount(self).await.map_err(|x| x.into())
+    }
+}
+
 #[async_trait]
 impl DispatchMessage<tonic::Response<GdbAttachResponse>> for tonic::Request<GdbAttachRequest> {
     async fn dispatch_message(
@@ -274,3 +628,38 @@ impl DispatchMessage<tonic::Response<GdbListResponse>> for tonic::Request<GdbLis
         client.gdb_list(self).await.map_err(|x| x.into())
     }
 }
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<GdbDetachResponse>> for tonic::Request<GdbDetachRequest> {
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<GdbDetachResponse>> {
+        client.gdb_detach(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<QueryAuditLogResponse>>
+    for tonic::Request<QueryAuditLogRequest>
+{
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<QueryAuditLogResponse>> {
+        client.query_audit_log(self).await.map_err(|x| x.into())
+    }
+}
+
+#[async_trait]
+impl DispatchMessage<tonic::Response<EventStream>> for tonic::Request<WatchEventsRequest> {
+    async fn dispatch_message(
+        self,
+        _conf: &Config,
+        client: &mut Client,
+    ) -> Result<tonic::Response<EventStream>> {
+        client.watch_events(self).await.map_err(|x| x.into())
+    }
+}
@@ -0,0 +1,145 @@
+use memflow_daemon::memflow_rpc::ErrorDetails;
+use prost::Message;
+use tonic::Code;
+
+use std::{error, fmt, result};
+
+/// Errors returned by the daemon or while talking to it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// A connection, process, module or file was not found
+    NotFound(ErrorInfo),
+    /// The request contains invalid arguments
+    InvalidArgument(ErrorInfo),
+    /// The object to be created already exists
+    AlreadyExists(ErrorInfo),
+    /// The connection is not in the state required for the operation
+    FailedPrecondition(ErrorInfo),
+    /// The memory could not be accessed, e.g. the page is not mapped
+    OutOfRange(ErrorInfo),
+    /// The client is not allowed to perform the operation
+    PermissionDenied(ErrorInfo),
+    /// The client did not provide valid credentials
+    Unauthenticated(ErrorInfo),
+    /// The daemon or the target is not available
+    Unavailable(ErrorInfo),
+    /// A limit of the daemon has been reached
+    ResourceExhausted(ErrorInfo),
+    /// Any other error reported by the daemon
+    Internal(ErrorInfo),
+    /// Local errors like invalid client configs or files which could not be written
+    Local(String),
+}
+
+/// The machine-readable kind and the message of a daemon error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ErrorInfo {
+    /// Stable identifier of the error, e.g. "not_found"
+    pub kind: String,
+    pub message: String,
+}
+
+impl Error {
+    /// Returns the exit code the cli uses for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::NotFound(_) => 2,
+            Error::InvalidArgument(_) => 3,
+            Error::AlreadyExists(_) => 4,
+            Error::FailedPrecondition(_) => 5,
+            Error::OutOfRange(_) => 6,
+            Error::PermissionDenied(_) => 7,
+            Error::Unauthenticated(_) => 8,
+            Error::Unavailable(_) => 9,
+            Error::Internal(_) => 10,
+            Error::Local(_) => 11,
+            Error::ResourceExhausted(_) => 12,
+        }
+    }
+
+    /// Returns the kind of the error as reported by the daemon.
+    pub fn kind(&self) -> &str {
+        match self {
+            Error::NotFound(info)
+            | Error::InvalidArgument(info)
+            | Error::AlreadyExists(info)
+            | Error::FailedPrecondition(info)
+            | Error::OutOfRange(info)
+            | Error::PermissionDenied(info)
+            | Error::Unauthenticated(info)
+            | Error::Unavailable(info)
+            | Error::ResourceExhausted(info)
+            | Error::Internal(info) => &info.kind,
+            Error::Local(_) => "local",
+        }
+    }
+
+    /// Returns the message of the error.
+    pub fn message(&self) -> &str {
+        match self {
+            Error::NotFound(info)
+            | Error::InvalidArgument(info)
+            | Error::AlreadyExists(info)
+            | Error::FailedPrecondition(info)
+            | Error::OutOfRange(info)
+            | Error::PermissionDenied(info)
+            | Error::Unauthenticated(info)
+            | Error::Unavailable(info)
+            | Error::ResourceExhausted(info)
+            | Error::Internal(info) => &info.message,
+            Error::Local(msg) => msg,
+        }
+    }
+}
+
+/// Convert from tonic::Status to error
+impl From<tonic::Status> for Error {
+    fn from(status: tonic::Status) -> Self {
+        // older daemons and tonic itself do not attach any details
+        let info = ErrorDetails::decode(status.details())
+            .ok()
+            .filter(|details| !details.kind.is_empty())
+            .map(|details| ErrorInfo {
+                kind: details.kind,
+                message: details.message,
+            })
+            .unwrap_or_else(|| ErrorInfo {
+                kind: format!("{:?}", status.code()).to_lowercase(),
+                message: status.message().to_string(),
+            });
+
+        match status.code() {
+            Code::NotFound => Error::NotFound(info),
+            Code::InvalidArgument => Error::InvalidArgument(info),
+            Code::AlreadyExists => Error::AlreadyExists(info),
+            Code::FailedPrecondition => Error::FailedPrecondition(info),
+            Code::OutOfRange => Error::OutOfRange(info),
+            Code::PermissionDenied => Error::PermissionDenied(info),
+            Code::Unauthenticated => Error::Unauthenticated(info),
+            Code::Unavailable => Error::Unavailable(info),
+            Code::ResourceExhausted => Error::ResourceExhausted(info),
+            _ => Error::Internal(info),
+        }
+    }
+}
+
+/// Convert from tonic::transport::Error to error
+impl From<tonic::transport::Error> for Error {
+    fn from(error: tonic::transport::Error) -> Self {
+        Error::Unavailable(ErrorInfo {
+            kind: "transport".to_string(),
+            message: error.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl error::Error for Error {}
+
+/// Specialized `Result` type for client errors.
+pub type Result<T> = result::Result<T, Error>;
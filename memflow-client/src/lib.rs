@@ -1 +1,4 @@
+pub mod config;
 pub mod dispatch;
+pub mod error;
+pub mod partial;
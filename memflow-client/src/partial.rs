@@ -0,0 +1,72 @@
+use memflow_daemon::memflow_rpc::{
+    ReadPhysicalMemoryEntryResponse, ReadStatus, ReadVirtualMemoryEntryResponse,
+};
+use memflow_daemon::pages::{is_page_valid, page_index, valid_ranges};
+
+/// Gives access to the valid parts of a read entry which could only be read partially.
+pub trait PartialRead {
+    /// Returns the data of the entry. Unreadable parts are zero-filled.
+    fn data(&self) -> &[u8];
+
+    /// Returns the status of the read.
+    fn read_status(&self) -> ReadStatus;
+
+    /// Returns the bitmap of valid pages for partial reads.
+    fn valid_pages(&self) -> &[u8];
+
+    /// Returns true if the entire entry has been read.
+    fn is_complete(&self) -> bool {
+        self.read_status() == ReadStatus::Ok
+    }
+
+    /// Returns the `(address, length)` ranges which contain valid data
+    /// given the address the entry has been read from.
+    fn valid_ranges(&self, addr: u64) -> Vec<(u64, u64)> {
+        match self.read_status() {
+            ReadStatus::Ok if !self.data().is_empty() => vec![(addr, self.data().len() as u64)],
+            ReadStatus::Partial => valid_ranges(addr, self.data().len(), self.valid_pages()),
+            _ => vec![],
+        }
+    }
+
+    /// Returns true if the byte at the given offset of the entry has been read successfully.
+    fn is_valid(&self, addr: u64, offset: usize) -> bool {
+        if offset >= self.data().len() {
+            return false;
+        }
+
+        match self.read_status() {
+            ReadStatus::Ok => true,
+            ReadStatus::Partial => is_page_valid(self.valid_pages(), page_index(addr, offset)),
+            _ => false,
+        }
+    }
+}
+
+impl PartialRead for ReadPhysicalMemoryEntryResponse {
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn read_status(&self) -> ReadStatus {
+        self.status()
+    }
+
+    fn valid_pages(&self) -> &[u8] {
+        &self.valid_pages
+    }
+}
+
+impl PartialRead for ReadVirtualMemoryEntryResponse {
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn read_status(&self) -> ReadStatus {
+        self.status()
+    }
+
+    fn valid_pages(&self) -> &[u8] {
+        &self.valid_pages
+    }
+}
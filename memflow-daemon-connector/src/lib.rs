@@ -1,6 +1,8 @@
 use memflow_client;
+use memflow_client::config::TlsConfig;
+use memflow_client::partial::PartialRead;
 
-use log::error;
+use log::{debug, error};
 
 use memflow::{
     ConnectorArgs, Error, PhysicalMemory, PhysicalMemoryMetadata, PhysicalReadData,
@@ -10,7 +12,6 @@ use memflow_derive::connector;
 use tokio::runtime::Runtime;
 
 pub struct DaemonConnector {
-    addr: String,
     conn_id: String,
 
     runtime: Runtime,
@@ -21,16 +22,18 @@ pub struct DaemonConnector {
 }
 
 impl DaemonConnector {
-    pub fn new(addr: &str, conn_id: &str) -> Result<Self> {
-        let conf = memflow_client::dispatch::Config { host: addr.into() };
-
+    pub fn new(conf: memflow_client::dispatch::Config, conn_id: &str) -> Result<Self> {
         let rt = Runtime::new().map_err(|e| {
             error!("{}", e);
             Error::Other("unable to instantiate tokio runtime")
         })?;
 
-        let mut client: memflow_client::dispatch::Client =
-            rt.block_on(memflow_client::dispatch::create_client_async(&conf));
+        let mut client: memflow_client::dispatch::Client = rt
+            .block_on(memflow_client::dispatch::create_client_async(&conf))
+            .map_err(|e| {
+                error!("{}", e);
+                Error::Connector("unable to connect to the daemon")
+            })?;
 
         let metadata = rt
             .block_on(memflow_client::dispatch::dispatch_request_async_client(
@@ -40,12 +43,14 @@ impl DaemonConnector {
                 },
                 &mut client,
             ))
-            .expect("Failed to get memory metadata")
+            .map_err(|e| {
+                error!("{}", e);
+                Error::Connector("unable to query the physical memory metadata")
+            })?
             .metadata
-            .expect("Received no metadata");
+            .ok_or_else(|| Error::Connector("received no physical memory metadata"))?;
 
         Ok(Self {
-            addr: addr.to_string(),
             conn_id: conn_id.to_string(),
 
             runtime: rt,
@@ -59,7 +64,7 @@ impl DaemonConnector {
 
 impl Clone for DaemonConnector {
     fn clone(&self) -> Self {
-        DaemonConnector::new(&self.addr, &self.conn_id).unwrap()
+        DaemonConnector::new(self.conf.clone(), &self.conn_id).unwrap()
     }
 }
 
@@ -87,10 +92,37 @@ impl PhysicalMemory for DaemonConnector {
             ))
             .map_err(|_| Error::Other("Transfer error"))?;
 
+        if response.reads.len() != data.len() {
+            return Err(Error::Other(
+                "the daemon returned an invalid number of reads",
+            ));
+        }
+
+        // unreadable pages are zero-filled by the daemon, the readable parts are still copied
+        // but the read fails so callers do not mistake the zeroes for memory contents
+        let mut complete = true;
         for (data_out, read_in) in data.iter_mut().zip(response.reads.iter()) {
+            if read_in.data.len() != data_out.1.len() {
+                return Err(Error::Other(
+                    "the daemon returned a read of an invalid length",
+                ));
+            }
             data_out.1.copy_from_slice(&read_in.data[..]);
+            if !read_in.is_complete() {
+                debug!(
+                    "partial read at {:x}, valid ranges: {:x?}",
+                    data_out.0.as_u64(),
+                    read_in.valid_ranges(data_out.0.as_u64())
+                );
+                complete = false;
+            }
+        }
+
+        if complete {
+            Ok(())
+        } else {
+            Err(Error::PhysicalMemory("unable to read physical memory"))
         }
-        Ok(())
     }
 
     fn phys_write_raw_list(&mut self, data: &[PhysicalWriteData]) -> Result<()> {
@@ -128,6 +160,9 @@ impl PhysicalMemory for DaemonConnector {
 }
 
 /// Creates a new Qemu Procfs Connector instance.
+///
+/// Besides `host` and `id` the connector accepts `token` for authentication
+/// and `ca_cert`, `cert`, `key` and `domain` for tls connections.
 #[connector(name = "daemon")]
 pub fn create_connector(args: &ConnectorArgs) -> Result<DaemonConnector> {
     let addr = args
@@ -137,5 +172,26 @@ pub fn create_connector(args: &ConnectorArgs) -> Result<DaemonConnector> {
     let conn_id = args
         .get("id")
         .ok_or_else(|| Error::Connector("id argument is missing"))?;
-    DaemonConnector::new(addr, conn_id)
+
+    // tls is enabled as soon as any of the tls arguments is set
+    let tls = if ["ca_cert", "cert", "key", "domain"]
+        .iter()
+        .any(|arg| args.get(arg).is_some())
+    {
+        Some(TlsConfig {
+            ca_cert: args.get("ca_cert").map(String::from),
+            cert: args.get("cert").map(String::from),
+            key: args.get("key").map(String::from),
+            domain: args.get("domain").map(String::from),
+        })
+    } else {
+        None
+    };
+
+    let conf = memflow_client::dispatch::Config {
+        host: addr.to_string(),
+        tls,
+        auth_token: args.get("token").map(String::from),
+    };
+    DaemonConnector::new(conf, conn_id)
 }
@@ -0,0 +1,179 @@
+use crate::auth::Peer;
+use crate::error::{Error, Result};
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lazy_static::lazy_static;
+use log::{error, info};
+use memflow_daemon::config::AuditLogConfig;
+use serde_derive::{Deserialize, Serialize};
+use tonic::Request;
+
+lazy_static! {
+    static ref AUDIT_LOG: Mutex<Option<AuditLog>> = Mutex::new(None);
+}
+
+struct AuditLog {
+    path: String,
+    file: File,
+    record_data: bool,
+}
+
+/// A single line of the audit log.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Unix timestamp in milliseconds
+    pub timestamp: u64,
+    /// "rpc", "fuse", "gdb" or "revert"
+    pub source: String,
+    pub peer: String,
+    pub conn_id: String,
+    /// Not set for physical writes
+    pub pid: Option<u32>,
+    pub addr: u64,
+    pub len: u64,
+    /// Hex encoded bytes before the write
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old: Option<String>,
+    /// Hex encoded bytes which have been written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new: Option<String>,
+}
+
+/// Describes a single memory write which is about to be recorded.
+pub struct AuditWrite<'a> {
+    pub source: &'a str,
+    pub peer: &'a str,
+    pub conn_id: &'a str,
+    pub pid: Option<u32>,
+    pub addr: u64,
+    pub old: Option<&'a [u8]>,
+    pub new: &'a [u8],
+}
+
+/// Opens the audit log, new entries are always appended to the existing file.
+pub fn init(conf: &AuditLogConfig) -> Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&conf.path)
+        .map_err(|err| Error::Other(format!("unable to open {}: {}", conf.path, err)))?;
+
+    info!("recording memory writes in {}", conf.path);
+    *AUDIT_LOG.lock().unwrap() = Some(AuditLog {
+        path: conf.path.clone(),
+        file,
+        record_data: conf.record_data,
+    });
+    Ok(())
+}
+
+/// Returns true if the previous and the written bytes should be recorded.
+///
+/// Callers only need to read the previous bytes if this returns true.
+pub fn record_data() -> bool {
+    AUDIT_LOG
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|log| log.record_data)
+        .unwrap_or_default()
+}
+
+/// Appends the write to the audit log if it is enabled.
+///
+/// Failing to record a write is logged but does not fail the write itself.
+pub fn record(write: AuditWrite) {
+    let mut log = AUDIT_LOG.lock().unwrap();
+    let log = match log.as_mut() {
+        Some(log) => log,
+        None => return,
+    };
+
+    let entry = AuditEntry {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default(),
+        source: write.source.to_string(),
+        peer: write.peer.to_string(),
+        conn_id: write.conn_id.to_string(),
+        pid: write.pid,
+        addr: write.addr,
+        len: write.new.len() as u64,
+        old: write.old.filter(|_| log.record_data).map(to_hex),
+        new: Some(write.new).filter(|_| log.record_data).map(to_hex),
+    };
+
+    let line = match serde_json::to_string(&entry) {
+        Ok(line) => line,
+        Err(err) => {
+            error!("unable to serialize audit entry: {}", err);
+            return;
+        }
+    };
+
+    if let Err(err) = writeln!(log.file, "{}", line).and_then(|_| log.file.flush()) {
+        error!("unable to write audit log {}: {}", log.path, err);
+    }
+}
+
+/// Reads the latest `limit` entries matching the filter from the audit log.
+///
+/// Only the matching entries are kept while reading so the whole log never has to fit into memory.
+pub fn entries<F>(filter: F, limit: usize) -> Result<Vec<AuditEntry>>
+where
+    F: Fn(&AuditEntry) -> bool,
+{
+    let path = match AUDIT_LOG.lock().unwrap().as_ref() {
+        Some(log) => log.path.clone(),
+        None => {
+            return Err(Error::FailedPrecondition(
+                "the audit log is not enabled".to_string(),
+            ))
+        }
+    };
+
+    let file = File::open(&path)
+        .map_err(|err| Error::Other(format!("unable to open {}: {}", path, err)))?;
+
+    let mut entries = VecDeque::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|err| Error::Other(format!("unable to read {}: {}", path, err)))?;
+        match serde_json::from_str(&line) {
+            Ok(entry) if filter(&entry) => {
+                if entries.len() == limit {
+                    entries.pop_front();
+                }
+                entries.push_back(entry);
+            }
+            Ok(_) => {}
+            Err(err) => error!("skipping invalid audit entry in {}: {}", path, err),
+        }
+    }
+    Ok(entries.into())
+}
+
+/// Describes the client which sent the request.
+pub fn peer<T>(request: &Request<T>) -> String {
+    request
+        .extensions()
+        .get::<Peer>()
+        .map(|peer| peer.to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .filter_map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,277 @@
+use crate::error::{Error, Result};
+
+use crate::unix::UnixConnectInfo;
+use futures::future::{self, BoxFuture};
+use log::warn;
+use memflow_daemon::config::{PolicyConfig, Role, TlsConfig, TokenBinding, UidBinding};
+use tonic::body::BoxBody;
+use tonic::transport::server::{TcpConnectInfo, TlsConnectInfo};
+use tonic::transport::{Certificate, Identity, NamedService, ServerTlsConfig};
+use tonic::Status;
+use tower::Service;
+
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Header carrying the token in the form `Bearer <token>`.
+pub const AUTHORIZATION: &str = "authorization";
+
+/// Builds the tls config of the server from the pem files in the daemon config.
+pub fn server_tls_config(conf: &TlsConfig) -> Result<ServerTlsConfig> {
+    let cert = read_pem(conf.cert.as_ref(), "cert")?;
+    let key = read_pem(conf.key.as_ref(), "key")?;
+
+    let mut tls = ServerTlsConfig::new().identity(Identity::from_pem(cert, key));
+    if let Some(ca_cert) = &conf.ca_cert {
+        // only accept clients with a certificate signed by this ca
+        tls = tls.client_ca_root(Certificate::from_pem(read_pem(Some(ca_cert), "ca_cert")?));
+    }
+
+    Ok(tls)
+}
+
+/// Compares two tokens without returning early so the comparison time does not leak
+/// how many leading bytes of a guessed token are correct.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn read_pem(path: Option<&String>, name: &str) -> Result<Vec<u8>> {
+    let path = path.ok_or_else(|| Error::Other(format!("tls {} is missing", name)))?;
+    std::fs::read(path).map_err(|err| Error::Other(format!("unable to read {}: {}", path, err)))
+}
+
+/// Resolves the role of a client from its credentials.
+pub struct Policy {
+    tokens: Vec<TokenBinding>,
+    uids: Vec<UidBinding>,
+    client_cert_role: Option<Role>,
+    default_role: Option<Role>,
+}
+
+impl Policy {
+    /// Creates the policy from `auth_tokens` and the `policy` section of the daemon config.
+    ///
+    /// Without a policy section every client is an admin, provided it sent one of the
+    /// `auth_tokens` if any are configured.
+    pub fn new(auth_tokens: &[String], policy: Option<&PolicyConfig>) -> Self {
+        let mut tokens = auth_tokens
+            .iter()
+            .map(|token| TokenBinding {
+                token: token.clone(),
+                role: Role::Admin,
+            })
+            .collect::<Vec<_>>();
+
+        match policy {
+            Some(policy) => {
+                tokens.extend(policy.tokens.iter().cloned());
+                Self {
+                    tokens,
+                    uids: policy.uids.clone(),
+                    client_cert_role: policy.client_cert_role,
+                    default_role: policy.default_role,
+                }
+            }
+            None => Self {
+                default_role: if tokens.is_empty() {
+                    Some(Role::Admin)
+                } else {
+                    None
+                },
+                tokens,
+                uids: vec![],
+                client_cert_role: None,
+            },
+        }
+    }
+
+    /// Returns the highest role granted to a client with the given token.
+    fn resolve(&self, token: Option<&str>, peer: &Peer) -> Option<Role> {
+        let token_role = token.and_then(|token| {
+            self.tokens
+                .iter()
+                .filter(|binding| constant_time_eq(binding.token.as_bytes(), token.as_bytes()))
+                .map(|binding| binding.role)
+                .max()
+        });
+
+        let uid_role = peer.uid.and_then(|uid| {
+            self.uids
+                .iter()
+                .filter(|binding| binding.uid == uid)
+                .map(|binding| binding.role)
+                .max()
+        });
+
+        let cert_role = self.client_cert_role.filter(|_| peer.client_cert);
+
+        vec![token_role, uid_role, cert_role, self.default_role]
+            .into_iter()
+            .flatten()
+            .max()
+    }
+}
+
+/// Returns the role required to call the rpc with the given grpc path,
+/// e.g. `/memflow_rpc.Memflow/ReadVirtualMemory`.
+///
+/// Unknown rpcs require the admin role so new rpcs are never exposed by accident.
+pub fn required_role(path: &str) -> Role {
+    match path.rsplit('/').next().unwrap_or_default() {
+        "ListConnections"
+        | "ConnectionCache"
+        | "ReadPhysicalMemory"
+        | "PhysicalMemoryMetadata"
+        | "DumpPhysicalMemory"
+        | "ReadVirtualMemory"
+        | "DumpVirtualMemory"
+        | "ListProcesses"
+        | "ProcessInfo"
+        | "WatchProcesses"
+        | "WatchModules"
+        | "ScanPattern"
+        | "CreateScanSession"
+        | "NextScan"
+        | "ListScanSessions"
+        | "ListScanResults"
+        | "CloseScanSession"
+        | "ResolvePointer"
+        | "ScanPointers"
+        | "ListMemoryRegions"
+        | "TranslateAddresses"
+        | "FuseList"
+        | "GdbList"
+        | "WatchEvents" => Role::ReadOnly,
+        "RevertWrites" | "WritePhysicalMemory" | "WriteVirtualMemory" => Role::ReadWrite,
+        _ => Role::Admin,
+    }
+}
+
+/// The sender of a request, attached to the extensions of each authorized request.
+#[derive(Clone, Debug, Default)]
+pub struct Peer {
+    /// Only set for clients connected via the unix socket
+    pub uid: Option<u32>,
+    /// Only set for clients connected via tcp
+    pub addr: Option<SocketAddr>,
+    /// The client presented a certificate signed by `tls.ca_cert`
+    pub client_cert: bool,
+}
+
+impl Peer {
+    fn from_extensions(extensions: &http::Extensions) -> Self {
+        let has_certs = |certs: Option<Arc<Vec<Certificate>>>| {
+            certs.map(|certs| !certs.is_empty()).unwrap_or_default()
+        };
+
+        if let Some(info) = extensions.get::<UnixConnectInfo>() {
+            Self {
+                uid: info.uid,
+                ..Default::default()
+            }
+        } else if let Some(info) = extensions.get::<TlsConnectInfo<UnixConnectInfo>>() {
+            Self {
+                uid: info.get_ref().uid,
+                client_cert: has_certs(info.peer_certs()),
+                ..Default::default()
+            }
+        } else if let Some(info) = extensions.get::<TcpConnectInfo>() {
+            Self {
+                addr: info.remote_addr(),
+                ..Default::default()
+            }
+        } else if let Some(info) = extensions.get::<TlsConnectInfo<TcpConnectInfo>>() {
+            Self {
+                addr: info.get_ref().remote_addr(),
+                client_cert: has_certs(info.peer_certs()),
+                ..Default::default()
+            }
+        } else {
+            Self::default()
+        }
+    }
+}
+
+impl fmt::Display for Peer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.uid, self.addr) {
+            (Some(uid), _) => write!(f, "unix uid={}", uid),
+            (None, Some(addr)) => write!(f, "tcp {}", addr),
+            (None, None) => f.write_str("unknown"),
+        }
+    }
+}
+
+/// Wraps the grpc service and rejects all requests whose sender lacks the role
+/// required by the called rpc, see [`required_role`].
+#[derive(Clone)]
+pub struct Authorize<S> {
+    inner: S,
+    policy: Arc<Policy>,
+}
+
+impl<S> Authorize<S> {
+    pub fn new(inner: S, policy: Policy) -> Self {
+        Self {
+            inner,
+            policy: Arc::new(policy),
+        }
+    }
+}
+
+impl<S: NamedService> NamedService for Authorize<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S, B> Service<http::Request<B>> for Authorize<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, core::result::Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<core::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        let peer = Peer::from_extensions(request.extensions());
+        let (role, has_token) = {
+            let token = request.headers().get(AUTHORIZATION).map(|value| {
+                value
+                    .to_str()
+                    .ok()
+                    .and_then(|value| value.strip_prefix("Bearer "))
+            });
+            (self.policy.resolve(token.flatten(), &peer), token.is_some())
+        };
+        let required = required_role(request.uri().path());
+
+        let status = match role {
+            Some(role) if role >= required => {
+                request.extensions_mut().insert(peer);
+                // the ready service has to be used for this request, the clone takes its place
+                let clone = self.inner.clone();
+                let mut inner = std::mem::replace(&mut self.inner, clone);
+                return Box::pin(inner.call(request));
+            }
+            Some(_) => Status::permission_denied(format!(
+                "the {} role is required for this operation",
+                required.as_str()
+            )),
+            None if has_token => {
+                warn!("rejected request with invalid token from {}", peer);
+                Status::unauthenticated("invalid token")
+            }
+            None => Status::unauthenticated("missing credentials"),
+        };
+        Box::pin(future::ready(Ok(status.to_http())))
+    }
+}
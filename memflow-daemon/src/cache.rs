@@ -0,0 +1,235 @@
+use crate::error::{Error, Result};
+use crate::memflow_rpc;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use memflow::architecture::ArchitectureObj;
+use memflow::*;
+
+/// Upper limits of the cache options of a connection.
+const MAX_PAGE_CACHE_SIZE: usize = 0x4000_0000;
+const MAX_TLB_SIZE: usize = 0x10_0000;
+
+/// Cache configuration of a connection.
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    /// Size of the page cache in bytes
+    pub page_cache_size: usize,
+    pub page_cache_validity: Duration,
+    /// Number of entries in the translation lookaside buffer
+    pub tlb_size: usize,
+    pub tlb_validity: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            page_cache_size: size::mb(2),
+            page_cache_validity: Duration::from_secs(1),
+            tlb_size: 2048,
+            tlb_validity: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Converts the rpc cache options into a `CacheConfig`.
+/// Zero values will be replaced by their defaults.
+impl From<&memflow_rpc::CacheOptions> for CacheConfig {
+    fn from(opts: &memflow_rpc::CacheOptions) -> Self {
+        let default = Self::default();
+        Self {
+            enabled: !opts.disabled,
+            page_cache_size: match opts.page_cache_size {
+                0 => default.page_cache_size,
+                size => size as usize,
+            },
+            page_cache_validity: match opts.page_cache_validity_ms {
+                0 => default.page_cache_validity,
+                ms => Duration::from_millis(ms),
+            },
+            tlb_size: match opts.tlb_size {
+                0 => default.tlb_size,
+                size => size as usize,
+            },
+            tlb_validity: match opts.tlb_validity_ms {
+                0 => default.tlb_validity,
+                ms => Duration::from_millis(ms),
+            },
+        }
+    }
+}
+
+impl From<&CacheConfig> for memflow_rpc::CacheOptions {
+    fn from(config: &CacheConfig) -> Self {
+        Self {
+            disabled: !config.enabled,
+            page_cache_size: config.page_cache_size as u64,
+            page_cache_validity_ms: config.page_cache_validity.as_millis() as u64,
+            tlb_size: config.tlb_size as u64,
+            tlb_validity_ms: config.tlb_validity.as_millis() as u64,
+        }
+    }
+}
+
+impl CacheConfig {
+    /// Wraps the given memory in a page cache with pages of `page_size` bytes
+    pub fn build_page_cache<T: PhysicalMemory>(
+        &self,
+        mem: T,
+        page_size: usize,
+    ) -> Result<CachedMemoryAccess<'static, T, TimedCacheValidator>> {
+        if self.page_cache_size < page_size || self.page_cache_size > MAX_PAGE_CACHE_SIZE {
+            return Err(Error::InvalidArgument(format!(
+                "the page cache size has to be between 0x{:x} and 0x{:x} bytes",
+                page_size, MAX_PAGE_CACHE_SIZE
+            )));
+        }
+
+        CachedMemoryAccess::builder(mem)
+            .page_size(page_size)
+            .cache_size(self.page_cache_size)
+            .validator(TimedCacheValidator::new(self.page_cache_validity))
+            .build()
+            .map_err(|err| {
+                Error::InvalidArgument(format!("invalid page cache options: {}", err.to_str()))
+            })
+    }
+
+    /// Creates a new cached virtual address translator
+    pub fn build_vat_cache(
+        &self,
+        arch: ArchitectureObj,
+    ) -> Result<CachedVirtualTranslate<DirectTranslate, TimedCacheValidator>> {
+        if self.tlb_size > MAX_TLB_SIZE {
+            return Err(Error::InvalidArgument(format!(
+                "the tlb size has to be at most {} entries",
+                MAX_TLB_SIZE
+            )));
+        }
+
+        CachedVirtualTranslate::builder(DirectTranslate::new())
+            .arch(arch)
+            .entries(self.tlb_size)
+            .validator(TimedCacheValidator::new(self.tlb_validity))
+            .build()
+            .map_err(|err| Error::InvalidArgument(format!("invalid tlb options: {}", err.to_str())))
+    }
+}
+
+/// Memory access counters of a connection.
+///
+/// Reads are counted once when they are requested from the os layer
+/// and once when they actually reach the connector.
+/// The difference between both is the amount of data served by the cache.
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    reads: AtomicU64,
+    read_bytes: AtomicU64,
+    writes: AtomicU64,
+    write_bytes: AtomicU64,
+    connector_reads: AtomicU64,
+    connector_read_bytes: AtomicU64,
+}
+
+impl From<&CacheStats> for memflow_rpc::CacheStatistics {
+    fn from(stats: &CacheStats) -> Self {
+        let read_bytes = stats.read_bytes.load(Ordering::Relaxed);
+        let connector_read_bytes = stats.connector_read_bytes.load(Ordering::Relaxed);
+        Self {
+            reads: stats.reads.load(Ordering::Relaxed),
+            read_bytes,
+            writes: stats.writes.load(Ordering::Relaxed),
+            write_bytes: stats.write_bytes.load(Ordering::Relaxed),
+            connector_reads: stats.connector_reads.load(Ordering::Relaxed),
+            connector_read_bytes,
+            hit_bytes: read_bytes.saturating_sub(connector_read_bytes),
+        }
+    }
+}
+
+/// Combined cache configuration and statistics of a connection.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionCache {
+    pub config: CacheConfig,
+    pub stats: Arc<CacheStats>,
+}
+
+impl ConnectionCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            stats: Arc::new(CacheStats::default()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum StatsLevel {
+    Request,
+    Connector,
+}
+
+/// Physical memory wrapper that records all accesses into `CacheStats`.
+#[derive(Clone)]
+pub struct StatsMemory<T> {
+    mem: T,
+    stats: Arc<CacheStats>,
+    level: StatsLevel,
+}
+
+impl<T: PhysicalMemory> StatsMemory<T> {
+    /// Records all accesses as requests from the os layer
+    pub fn requests(mem: T, stats: Arc<CacheStats>) -> Self {
+        Self {
+            mem,
+            stats,
+            level: StatsLevel::Request,
+        }
+    }
+
+    /// Records all accesses as accesses that reached the connector
+    pub fn connector(mem: T, stats: Arc<CacheStats>) -> Self {
+        Self {
+            mem,
+            stats,
+            level: StatsLevel::Connector,
+        }
+    }
+}
+
+impl<T: PhysicalMemory> PhysicalMemory for StatsMemory<T> {
+    fn phys_read_raw_list(&mut self, data: &mut [PhysicalReadData]) -> memflow::Result<()> {
+        let bytes = data.iter().map(|d| d.1.len() as u64).sum::<u64>();
+        let (reads, read_bytes) = match self.level {
+            StatsLevel::Request => (&self.stats.reads, &self.stats.read_bytes),
+            StatsLevel::Connector => (
+                &self.stats.connector_reads,
+                &self.stats.connector_read_bytes,
+            ),
+        };
+        reads.fetch_add(data.len() as u64, Ordering::Relaxed);
+        read_bytes.fetch_add(bytes, Ordering::Relaxed);
+
+        self.mem.phys_read_raw_list(data)
+    }
+
+    fn phys_write_raw_list(&mut self, data: &[PhysicalWriteData]) -> memflow::Result<()> {
+        if let StatsLevel::Request = self.level {
+            let bytes = data.iter().map(|d| d.1.len() as u64).sum::<u64>();
+            self.stats
+                .writes
+                .fetch_add(data.len() as u64, Ordering::Relaxed);
+            self.stats.write_bytes.fetch_add(bytes, Ordering::Relaxed);
+        }
+
+        self.mem.phys_write_raw_list(data)
+    }
+
+    fn metadata(&self) -> PhysicalMemoryMetadata {
+        self.mem.metadata()
+    }
+}
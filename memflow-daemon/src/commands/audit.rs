@@ -0,0 +1,66 @@
+use crate::audit::{entries, from_hex};
+use crate::error::Result;
+use crate::state::STATE;
+
+use log::info;
+
+use crate::memflow_rpc::{AuditLogEntry, QueryAuditLogRequest, QueryAuditLogResponse};
+
+use std::cmp::min;
+
+/// Default number of entries returned by [`query`].
+const DEFAULT_RESULT_LIMIT: u64 = 100;
+
+/// Maximum number of entries returned by [`query`].
+const MAX_RESULT_LIMIT: u64 = 10_000;
+
+pub async fn query(msg: &QueryAuditLogRequest) -> Result<QueryAuditLogResponse> {
+    // entries are recorded with the connection id, resolve aliases of open connections
+    let conn_id = if msg.conn_id.is_empty() {
+        None
+    } else {
+        let state = STATE.lock().await;
+        Some(
+            state
+                .connection(&msg.conn_id)
+                .map(|conn| conn.id.clone())
+                .unwrap_or_else(|| msg.conn_id.clone()),
+        )
+    };
+
+    let limit = if msg.limit == 0 {
+        DEFAULT_RESULT_LIMIT
+    } else {
+        min(msg.limit, MAX_RESULT_LIMIT)
+    };
+
+    let entries = tokio::task::block_in_place(|| {
+        entries(
+            |e| {
+                conn_id.as_ref().map(|id| &e.conn_id == id).unwrap_or(true)
+                    && (msg.pid == 0 || e.pid == Some(msg.pid))
+                    && e.timestamp >= msg.since
+            },
+            limit as usize,
+        )
+    })?;
+
+    info!("querying audit log: {} entries", entries.len());
+
+    Ok(QueryAuditLogResponse {
+        entries: entries
+            .into_iter()
+            .map(|e| AuditLogEntry {
+                timestamp: e.timestamp,
+                source: e.source,
+                peer: e.peer,
+                conn_id: e.conn_id,
+                pid: e.pid.unwrap_or_default(),
+                addr: e.addr,
+                len: e.len,
+                old_data: e.old.as_deref().map(from_hex).unwrap_or_default(),
+                new_data: e.new.as_deref().map(from_hex).unwrap_or_default(),
+            })
+            .collect(),
+    })
+}
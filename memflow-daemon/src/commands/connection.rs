@@ -1,78 +1,128 @@
+use super::process::ensure_running;
+use crate::audit::{self, AuditWrite};
+use crate::cache::{CacheConfig, ConnectionCache};
 use crate::error::{Error, Result};
-use crate::state::{KernelHandle, STATE};
+use crate::journal::JournalEntry;
+use crate::os::create_os;
+use crate::state::{lock_connection, ConnectionTarget, KernelHandle, STATE};
 
 use log::{error, info};
-use memflow::{ConnectorArgs, ConnectorInstance, ConnectorInventory};
+use memflow::{ConnectorArgs, ConnectorInstance, ConnectorInventory, PartialResultExt};
+use tokio::task::block_in_place;
 
 use crate::memflow_rpc::{
-    CloseConnectionRequest, CloseConnectionResponse, ConnectionDescription, ListConnectionsRequest,
-    ListConnectionsResponse, NewConnectionRequest, NewConnectionResponse,
+    AttachOsRequest, AttachOsResponse, CloseConnectionRequest, CloseConnectionResponse,
+    ConnectionCacheRequest, ConnectionCacheResponse, ConnectionDescription, ListConnectionsRequest,
+    ListConnectionsResponse, NewConnectionRequest, NewConnectionResponse, RevertWritesRequest,
+    RevertWritesResponse,
 };
 
+/// Connector arguments whose values are never logged or returned to clients.
+const SECRET_ARGS: &[&str] = &["token"];
+
+/// Replaces the values of all secret arguments (e.g. the token of the daemon connector).
+fn redact_args(args: &str) -> String {
+    args.split(',')
+        .map(|arg| {
+            let key = arg.splitn(2, '=').next().unwrap_or_default();
+            if arg.contains('=') && SECRET_ARGS.contains(&key.trim()) {
+                format!("{}=***", key)
+            } else {
+                arg.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 fn create_connector(msg: &NewConnectionRequest) -> Result<ConnectorInstance> {
     let args = match &msg.args {
         args if args == "" => ConnectorArgs::default(),
         args => ConnectorArgs::parse(&args)
-            .map_err(|_| Error::Connector("unable to parse connector string".into()))?,
+            .map_err(|_| Error::InvalidArgument("unable to parse connector string".into()))?,
     };
 
     let inventory = unsafe { ConnectorInventory::scan() };
     unsafe { inventory.create_connector(&msg.name, &args) }.map_err(Error::from)
 }
 
+/// Initializes the os layer of a connector.
+///
+/// A failed kernel scan is reported as unavailable as the target might still be booting.
+fn init_os(
+    name: &str,
+    connector: ConnectorInstance,
+    cache: &ConnectionCache,
+) -> Result<KernelHandle> {
+    block_in_place(|| create_os(name, connector, cache)).map_err(|err| match err {
+        Error::Core(_) | Error::Win32(_) => {
+            Error::Unavailable(format!("unable to initialize the os: {}", err))
+        }
+        err => err,
+    })
+}
+
 pub async fn new<'a>(msg: &NewConnectionRequest) -> Result<NewConnectionResponse> {
-    match create_connector(msg) {
+    let args = redact_args(&msg.args);
+    match block_in_place(|| create_connector(msg)) {
         Ok(conn) => {
-            // TODO: add os argument
             // TODO: redirect log to client
-            // TODO: add cache options
 
             info!("connector created");
 
-            // initialize kernel
-            let kernel = memflow_win32::Kernel::builder(conn)
-                .build_default_caches()
-                .build()?;
+            let cache = ConnectionCache::new(
+                msg.cache
+                    .as_ref()
+                    .map(CacheConfig::from)
+                    .unwrap_or_default(),
+            );
 
-            info!("found win32 kernel");
+            // initialize os
+            let kernel = if msg.os == "none" {
+                info!("skipping os initialization");
+                None
+            } else {
+                let kernel = init_os(&msg.os, conn.clone(), &cache)?;
+                info!("initialized {} os", kernel.name());
+                Some(kernel)
+            };
 
             let mut state = STATE.lock().await;
 
             match state.connection_add(
                 &msg.name,
-                if msg.args == "" {
-                    None
-                } else {
-                    Some(msg.args.clone())
-                },
+                if args == "" { None } else { Some(args.clone()) },
                 if msg.alias == "" {
                     None
                 } else {
                     Some(msg.alias.clone())
                 },
-                KernelHandle::Win32(kernel),
+                conn,
+                cache,
+                kernel,
+                msg.journal,
             ) {
                 Ok(id) => {
-                    info!("connection created: {} | {} | {:?}", id, msg.name, msg.args);
+                    info!("connection created: {} | {} | {:?}", id, msg.name, args);
                     Ok(NewConnectionResponse { conn_id: id })
                 }
                 Err(err) => {
-                    let err_msg = format!(
+                    error!(
                         "could not create connector: {} | {:?} ({})",
-                        msg.name, msg.args, err
+                        msg.name, args, err
                     );
-                    error!("{}", err_msg);
-                    Err(Error::Connector(err_msg))
+                    Err(err)
                 }
             }
         }
+        Err(Error::InvalidArgument(err)) => Err(Error::InvalidArgument(err)),
         Err(err) => {
             let err_msg = format!(
                 "could not create connector: {} | {:?} ({})",
-                msg.name, msg.args, err
+                msg.name, args, err
             );
             error!("{}", err_msg);
-            Err(Error::Connector(err_msg))
+            Err(Error::Unavailable(err_msg))
         }
     }
 }
@@ -100,6 +150,7 @@ pub async fn ls(_msg: &ListConnectionsRequest) -> Result<ListConnectionsResponse
                     .map(|a| a.to_string())
                     .unwrap_or_default(),
                 refcount: c.1.refcount as u64,
+                os: c.1.os.clone().unwrap_or_default(),
             };
             connections.push(con);
         }
@@ -119,9 +170,111 @@ pub async fn rm(msg: &CloseConnectionRequest) -> Result<CloseConnectionResponse>
             Ok(CloseConnectionResponse {})
         }
         Err(err) => {
-            let err_msg = format!("unable to remove connection {}: {}", msg.conn_id, err);
-            error!("{}", err_msg);
-            Err(Error::Connector(err_msg))
+            error!("unable to remove connection {}: {}", msg.conn_id, err);
+            Err(err)
+        }
+    }
+}
+
+pub async fn attach_os(msg: &AttachOsRequest) -> Result<AttachOsResponse> {
+    // only this connection is locked while the os is initialized as the kernel scan might take a while
+    let mut conn = lock_connection(&msg.conn_id).await?;
+    if conn.kernel.is_some() {
+        return Err(Error::FailedPrecondition(format!(
+            "connection {} already has an os attached",
+            msg.conn_id
+        )));
+    }
+
+    let kernel = init_os(&msg.os, conn.connector.clone(), &conn.cache)?;
+    let os = kernel.name().to_string();
+    conn.kernel = Some(kernel);
+
+    // the connection has to be released before the state is locked, see `lock_connection`
+    drop(conn);
+
+    let mut state = STATE.lock().await;
+    if let Some(conn) = state.connection_mut(&msg.conn_id) {
+        conn.os = Some(os.clone());
+    }
+
+    info!("attached {} os to connection {}", os, msg.conn_id);
+    Ok(AttachOsResponse {})
+}
+
+pub async fn cache(msg: &ConnectionCacheRequest) -> Result<ConnectionCacheResponse> {
+    let mut conn = lock_connection(&msg.conn_id).await?;
+    if msg.flush {
+        if let Some(kernel) = conn.kernel.as_mut() {
+            kernel.flush_caches();
+        }
+        conn.physical.flush_caches();
+        info!("flushed caches of connection {}", msg.conn_id);
+    }
+
+    Ok(ConnectionCacheResponse {
+        options: Some((&conn.cache.config).into()),
+        stats: Some((&*conn.cache.stats).into()),
+    })
+}
+
+pub async fn revert(msg: &RevertWritesRequest, peer: &str) -> Result<RevertWritesResponse> {
+    let mut conn = lock_connection(&msg.conn_id).await?;
+    let entries = match conn.journal.as_mut() {
+        Some(journal) => journal.take_since(msg.since),
+        None => {
+            return Err(Error::FailedPrecondition(format!(
+                "journaling is not enabled for connection {}",
+                msg.conn_id
+            )))
+        }
+    };
+
+    let (mut reverted, mut failed) = (0, 0);
+    block_in_place(|| {
+        for entry in entries.iter() {
+            match revert_entry(&mut conn, entry) {
+                Ok(_) => {
+                    reverted += 1;
+                    audit::record(AuditWrite {
+                        source: "revert",
+                        peer,
+                        conn_id: &conn.id,
+                        pid: entry.pid(),
+                        addr: entry.addr,
+                        old: None,
+                        new: &entry.data,
+                    });
+                }
+                Err(err) => {
+                    failed += 1;
+                    error!("unable to revert write at {:x}: {}", entry.addr, err);
+                }
+            }
+        }
+    });
+
+    info!(
+        "reverted {} writes on connection {} ({} failed)",
+        reverted, msg.conn_id, failed
+    );
+    Ok(RevertWritesResponse { reverted, failed })
+}
+
+fn revert_entry(conn: &mut ConnectionTarget, entry: &JournalEntry) -> Result<()> {
+    match &entry.proc_info {
+        Some(proc_info) => {
+            // the pid might have been reused by another process
+            let kernel = conn.kernel_mut()?;
+            ensure_running(kernel, proc_info)?;
+            kernel
+                .virt_mem(proc_info)?
+                .virt_write_raw(entry.addr.into(), &entry.data)
+                .data_part()?;
         }
+        None => conn
+            .phys_mem()
+            .phys_write_raw(entry.addr.into(), &entry.data)?,
     }
+    Ok(())
 }
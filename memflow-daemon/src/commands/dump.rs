@@ -0,0 +1,209 @@
+use super::process::{select_process, selector_or_pid};
+use super::virt_mem::base_address;
+use crate::error::{Error, Result};
+use crate::state::{ConnectionTarget, STATE};
+
+use log::{error, info};
+use memflow::types::size;
+use memflow::{PhysicalMemory, VirtualMemory};
+use memflow_daemon::pages::{read_pages, split_pages};
+
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::block_in_place;
+
+use crate::memflow_rpc::{
+    DumpMemoryRange, DumpMemoryResponse, DumpPhysicalMemoryRequest, DumpVirtualMemoryRequest,
+};
+
+/// Number of chunks which are read ahead of the client.
+const DUMP_QUEUE_SIZE: usize = 4;
+
+/// Upper bound for the requested chunk size, larger requests are clamped.
+const MAX_CHUNK_SIZE: u64 = 0x100_0000;
+
+pub type DumpReceiver = mpsc::Receiver<Result<DumpMemoryResponse>>;
+
+pub async fn dump_physical(msg: &DumpPhysicalMemoryRequest) -> Result<DumpReceiver> {
+    let target = STATE.lock().await.connection_target(&msg.conn_id)?;
+
+    let total_len = if msg.len == 0 {
+        let size = target.lock().await.phys_metadata().size as u64;
+        size.checked_sub(msg.addr).ok_or_else(|| {
+            Error::InvalidArgument(format!("address {:x} is out of bounds", msg.addr))
+        })?
+    } else {
+        msg.len
+    };
+    end_address(msg.addr, total_len)?;
+
+    info!(
+        "dumping {:x} bytes of physical memory at {:x} from connection {}",
+        total_len, msg.addr, msg.conn_id
+    );
+
+    Ok(spawn_dump(
+        target,
+        msg.addr,
+        total_len,
+        chunk_size(msg.chunk_size),
+        |conn, addr, buf| {
+            let phys_mem = conn.phys_mem();
+            Ok(read_chunk(addr, buf, |addr, buf| {
+                phys_mem.phys_read_raw_into(addr.into(), buf).is_ok()
+            }))
+        },
+    ))
+}
+
+pub async fn dump_virtual(msg: &DumpVirtualMemoryRequest) -> Result<DumpReceiver> {
+    if msg.len == 0 {
+        return Err(Error::InvalidArgument(
+            "a length is required for virtual memory dumps".to_string(),
+        ));
+    }
+
+    let target = STATE.lock().await.connection_target(&msg.conn_id)?;
+
+    let (proc_info, offset) = {
+        let mut conn = target.lock().await;
+        block_in_place(|| -> Result<_> {
+            let kernel = conn.kernel_mut()?;
+            let proc_info = select_process(kernel, &selector_or_pid(&msg.process, msg.pid))?;
+            let offset = base_address(kernel, &proc_info, msg.base_offsets, &msg.module)?;
+            Ok((proc_info, offset))
+        })?
+    };
+    let addr = offset.checked_add(msg.addr).ok_or_else(|| {
+        Error::InvalidArgument(format!(
+            "address {:x} overflows the base address {:x}",
+            msg.addr, offset
+        ))
+    })?;
+    end_address(addr, msg.len)?;
+
+    info!(
+        "dumping {:x} bytes of virtual memory at {:x} of process {} ({}) from connection {}",
+        msg.len, addr, proc_info.name, proc_info.pid, msg.conn_id
+    );
+
+    Ok(spawn_dump(
+        target,
+        addr,
+        msg.len,
+        chunk_size(msg.chunk_size),
+        move |conn, addr, buf| {
+            let mut virt_mem = conn.kernel_mut()?.virt_mem(&proc_info)?;
+            Ok(read_chunk(addr, buf, |addr, buf| {
+                virt_mem.virt_read_raw_into(addr.into(), buf).is_ok()
+            }))
+        },
+    ))
+}
+
+fn chunk_size(requested: u64) -> u64 {
+    if requested == 0 {
+        size::mb(1) as u64
+    } else {
+        requested.min(MAX_CHUNK_SIZE)
+    }
+}
+
+/// Ensures the dumped range does not wrap around the address space.
+fn end_address(addr: u64, len: u64) -> Result<u64> {
+    addr.checked_add(len).ok_or_else(|| {
+        Error::InvalidArgument(format!(
+            "range of {:x} bytes at {:x} overflows the address space",
+            len, addr
+        ))
+    })
+}
+
+/// Streams `total_len` bytes starting at `addr` in chunks of `chunk_size` bytes.
+///
+/// The connection is only locked while a single chunk is read so other requests
+/// on the same connection are not blocked for the entire dump.
+fn spawn_dump<F>(
+    target: Arc<Mutex<ConnectionTarget>>,
+    addr: u64,
+    total_len: u64,
+    chunk_size: u64,
+    mut read: F,
+) -> DumpReceiver
+where
+    F: FnMut(&mut ConnectionTarget, u64, &mut [u8]) -> Result<Vec<DumpMemoryRange>>
+        + Send
+        + 'static,
+{
+    let (tx, rx) = mpsc::channel(DUMP_QUEUE_SIZE);
+
+    tokio::spawn(async move {
+        let mut offset = 0;
+        while offset < total_len {
+            let len = std::cmp::min(chunk_size, total_len - offset);
+            let mut data = vec![0u8; len as usize];
+
+            let unreadable = {
+                let mut conn = target.lock().await;
+                block_in_place(|| read(&mut *conn, addr + offset, &mut data))
+            };
+
+            let chunk = unreadable.map(|unreadable| DumpMemoryResponse {
+                offset,
+                addr: addr + offset,
+                total_len,
+                data,
+                unreadable,
+            });
+
+            let failed = chunk.is_err();
+            if let Err(err) = &chunk {
+                error!("memory dump at {:x} failed: {}", addr + offset, err);
+            }
+
+            if tx.send(chunk).await.is_err() {
+                info!("memory dump at {:x} cancelled by client", addr);
+                break;
+            }
+
+            if failed {
+                break;
+            }
+
+            offset += len;
+        }
+    });
+
+    rx
+}
+
+/// Reads a single chunk via `read`.
+///
+/// If the chunk cannot be read at once it is read page by page instead.
+/// Pages which still fail are zero-filled and returned as unreadable ranges.
+fn read_chunk<F>(addr: u64, buf: &mut [u8], mut read: F) -> Vec<DumpMemoryRange>
+where
+    F: FnMut(u64, &mut [u8]) -> bool,
+{
+    if read(addr, buf) {
+        return vec![];
+    }
+
+    let pages = read_pages(addr, buf, read);
+
+    let mut unreadable: Vec<DumpMemoryRange> = vec![];
+    for ((page_addr, _, page_len), _) in split_pages(addr, buf.len())
+        .zip(pages.iter())
+        .filter(|(_, valid)| !**valid)
+    {
+        match unreadable.last_mut() {
+            Some(last) if last.addr + last.len == page_addr => last.len += page_len as u64,
+            _ => unreadable.push(DumpMemoryRange {
+                addr: page_addr,
+                len: page_len as u64,
+            }),
+        }
+    }
+
+    unreadable
+}
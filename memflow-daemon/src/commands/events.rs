@@ -0,0 +1,68 @@
+use crate::error::Result;
+use crate::events;
+use crate::state::STATE;
+
+use log::info;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+
+use crate::memflow_rpc::{Event, EventKind, WatchEventsRequest};
+
+pub type EventReceiver = mpsc::Receiver<Result<Event>>;
+
+pub async fn watch(msg: &WatchEventsRequest) -> Result<EventReceiver> {
+    // events are emitted with the connection id, resolve aliases of open connections
+    let conn_id = if msg.conn_id.is_empty() {
+        None
+    } else {
+        let state = STATE.lock().await;
+        Some(
+            state
+                .connection(&msg.conn_id)
+                .map(|conn| conn.id.clone())
+                .unwrap_or_else(|| msg.conn_id.clone()),
+        )
+    };
+
+    info!("client subscribed to events");
+
+    let mut events = events::subscribe();
+    let (tx, rx) = mpsc::channel(1);
+    tokio::spawn(async move {
+        loop {
+            // stop as soon as the client disconnects, even if no events are emitted
+            let received = tokio::select! {
+                _ = tx.closed() => break,
+                received = events.recv() => received,
+            };
+
+            let event = match received {
+                Ok(event) => event,
+                Err(RecvError::Lagged(count)) => events::new_event(
+                    EventKind::Error,
+                    "",
+                    "",
+                    &format!(
+                        "{} events have been dropped as the client is too slow",
+                        count
+                    ),
+                ),
+                Err(RecvError::Closed) => break,
+            };
+
+            if let Some(conn_id) = &conn_id {
+                if !event.conn_id.is_empty() && &event.conn_id != conn_id {
+                    continue;
+                }
+            }
+
+            // the client disconnected
+            if tx.send(Ok(event)).await.is_err() {
+                break;
+            }
+        }
+        info!("client unsubscribed from events");
+    });
+
+    Ok(rx)
+}
@@ -1,79 +1,125 @@
 mod filesystem;
 use filesystem::VirtualMemoryFileSystem;
-use log::info;
+use log::{error, info};
 
 use crate::error::{Error, Result};
-use crate::state::{new_uuid, STATE};
+use crate::events;
+use crate::state::{lock_connection, new_uuid, STATE};
 
 use crate::memflow_rpc::{
     FuseListRequest, FuseListResponse, FuseMount, FuseMountRequest, FuseMountResponse,
+    FuseUnmountRequest, FuseUnmountResponse,
 };
 
 use std::ffi::OsStr;
 use std::path::Path;
+use std::process::Command;
+use tokio::task::block_in_place;
 
 pub async fn mount(msg: &FuseMountRequest) -> Result<FuseMountResponse> {
-    let mut state = STATE.lock().await;
-
     let is_empty = Path::new(&msg.mount_point)
         .read_dir()
-        .map_err(|_| Error::Other("mount point not found".to_string()))?
+        .map_err(|_| Error::NotFound("mount point not found".to_string()))?
         .next()
         .is_none();
     if is_empty {
         // find connection and spawn filesystem thread
-        if let Some(conn) = state.connection_mut(&msg.conn_id) {
-            let kernel = conn.kernel.clone();
-            let id = new_uuid();
-
-            info!("filesystem with id {} mounted at {}", id, &msg.mount_point);
-            info!("please use 'umount' or 'fusermount -u' to unmount the filesystem");
-
-            let msg_clone = msg.clone();
-            std::thread::spawn(move || {
-                let opts = [
-                    "-o",
-                    &format!(
-                        "auto_unmount,allow_other,uid={},gid={}",
-                        msg_clone.uid, msg_clone.gid
-                    ),
-                ];
-                let mntopts = opts.iter().map(|o| o.as_ref()).collect::<Vec<&OsStr>>();
-
-                // the filesystem will add itself into the global scope
-                let vmfs = VirtualMemoryFileSystem::new(
-                    &id,
-                    &msg_clone.conn_id,
-                    &msg_clone.mount_point,
-                    kernel,
-                    msg_clone.uid,
-                    msg_clone.gid,
-                );
-
-                // blocks until the fs is umounted
-                fuse_mt::mount(
-                    fuse_mt::FuseMT::new(vmfs, 8),
-                    &msg_clone.mount_point,
-                    &mntopts,
-                )
-                .unwrap();
-            });
-
-            Ok(FuseMountResponse {})
-        } else {
-            Err(Error::Connector(format!(
-                "no connection with id {} found",
-                msg.conn_id
-            )))
-        }
+        let (conn_id, kernel) = {
+            let conn = lock_connection(&msg.conn_id).await?;
+            (conn.id.clone(), conn.kernel_or_physical())
+        };
+        let id = new_uuid();
+
+        info!("filesystem with id {} mounted at {}", id, &msg.mount_point);
+        info!("please use 'memflow fuse umount' to unmount the filesystem");
+
+        let msg_clone = msg.clone();
+        let id_clone = id.clone();
+        std::thread::spawn(move || {
+            let id = id_clone;
+            let opts = [
+                "-o",
+                &format!(
+                    "auto_unmount,allow_other,uid={},gid={}",
+                    msg_clone.uid, msg_clone.gid
+                ),
+            ];
+            let mntopts = opts.iter().map(|o| o.as_ref()).collect::<Vec<&OsStr>>();
+
+            // the filesystem will add itself into the global scope
+            let vmfs = VirtualMemoryFileSystem::new(
+                &id,
+                &conn_id,
+                &msg_clone.mount_point,
+                kernel,
+                msg_clone.uid,
+                msg_clone.gid,
+            );
+
+            // blocks until the fs is umounted
+            if let Err(err) = fuse_mt::mount(
+                fuse_mt::FuseMT::new(vmfs, 8),
+                &msg_clone.mount_point,
+                &mntopts,
+            ) {
+                let msg = format!("unable to mount {}: {}", msg_clone.mount_point, err);
+                error!("{}", msg);
+                events::emit_error(&conn_id, &id, &msg);
+            }
+        });
+
+        Ok(FuseMountResponse { id })
     } else {
-        Err(Error::Other(format!(
+        Err(Error::FailedPrecondition(format!(
             "mount point {} is not empty",
             msg.mount_point
         )))
     }
 }
 
+/// Unmounts the file system with the given id or mount point.
+///
+/// The fuse thread exits once the kernel has released the mount.
+pub async fn unmount(msg: &FuseUnmountRequest) -> Result<FuseUnmountResponse> {
+    let (id, mount_point) = {
+        let state = STATE.lock().await;
+        state
+            .file_systems
+            .values()
+            .find(|fs| fs.id == msg.id || fs.mount_point == msg.id)
+            .map(|fs| (fs.id.clone(), fs.mount_point.clone()))
+            .ok_or_else(|| Error::NotFound(format!("no file system with id {} found", msg.id)))?
+    };
+
+    // the state must not be locked here as the fuse thread removes itself on unmount
+    let output = block_in_place(|| {
+        if cfg!(target_os = "linux") {
+            Command::new("fusermount")
+                .arg("-u")
+                .arg(&mount_point)
+                .output()
+        } else {
+            Command::new("umount").arg(&mount_point).output()
+        }
+    })
+    .map_err(|err| Error::Other(format!("unable to unmount {}: {}", mount_point, err)))?;
+
+    if !output.status.success() {
+        return Err(Error::FailedPrecondition(format!(
+            "unable to unmount {}: {}",
+            mount_point,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    info!("filesystem with id {} unmounted from {}", id, mount_point);
+
+    // the fuse thread might still be shutting down
+    STATE.lock().await.file_system_remove(&id);
+
+    Ok(FuseUnmountResponse {})
+}
+
 pub async fn ls(_msg: &FuseListRequest) -> Result<FuseListResponse> {
     let state = STATE.lock().await;
 
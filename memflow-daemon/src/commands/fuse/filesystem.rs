@@ -1,7 +1,10 @@
 mod scopes;
 use scopes::ConnectionScope;
 
+use crate::audit::{self, AuditWrite};
 use crate::error::{Error, Result};
+use crate::events;
+use crate::memflow_rpc::EventKind;
 use crate::state::{state_lock_sync, FileSystemHandle, KernelHandle};
 
 use std::cell::RefCell;
@@ -15,8 +18,6 @@ use log::info;
 use fuse_mt::*;
 use time::*;
 
-use memflow::mem::phys_mem::PhysicalMemory;
-
 pub type ChildrenList = Vec<Arc<Box<dyn FileSystemEntry>>>;
 
 /// Trait describing an entry into the virtual filesystem.
@@ -143,6 +144,12 @@ pub trait FileSystemFileHandler {
     fn write(&mut self, _offset: u64, _data: Vec<u8>) -> Result<usize> {
         Err(Error::Other("unable to write to file".to_string()))
     }
+
+    /// Maps an offset in the file to the pid and the address which is written to.
+    /// Files without a pid map to physical memory.
+    fn address(&self, offset: u64) -> (Option<u32>, u64) {
+        (None, offset)
+    }
 }
 
 /// This reader provides a basic implementation of a `FileSystemFileHandler`.
@@ -245,9 +252,7 @@ impl VirtualMemoryFileSystem {
         uid: u32,
         gid: u32,
     ) -> Self {
-        let readonly = match &kernel {
-            KernelHandle::Win32(kernel) => kernel.phys_mem.metadata().readonly,
-        };
+        let readonly = kernel.phys_metadata().readonly;
 
         Self {
             id: id.to_string(),
@@ -283,11 +288,17 @@ impl FilesystemMT for VirtualMemoryFileSystem {
         // grab state and insert the reference
         let mut state = state_lock_sync();
         if let Some(conn) = state.connection_mut(&self.conn_id) {
-            conn.refcount += 1;
+            conn.add_ref();
             state.file_systems.insert(
                 self.id.clone(),
                 FileSystemHandle::new(&self.id, &self.conn_id, &self.mount_point),
             );
+            events::emit(
+                EventKind::FuseMounted,
+                &self.conn_id,
+                &self.id,
+                &self.mount_point,
+            );
         }
         Ok(())
     }
@@ -626,7 +637,7 @@ impl FilesystemMT for VirtualMemoryFileSystem {
     /// Return the number of bytes written.
     fn write(
         &self,
-        _req: RequestInfo,
+        req: RequestInfo,
         _path: &Path,
         fh: u64,
         offset: u64,
@@ -637,7 +648,24 @@ impl FilesystemMT for VirtualMemoryFileSystem {
         if !self.readonly {
             if let Ok(opened_files) = self.opened_files.read() {
                 if let Some(file) = opened_files.get(fh) {
-                    if let Ok(bytes) = file.lock().unwrap().write(offset, data) {
+                    let mut file = file.lock().unwrap();
+                    let old = if audit::record_data() {
+                        file.read(offset, data.len() as u32).ok()
+                    } else {
+                        None
+                    };
+                    let new = data.clone();
+                    if let Ok(bytes) = file.write(offset, data) {
+                        let (pid, addr) = file.address(offset);
+                        audit::record(AuditWrite {
+                            source: "fuse",
+                            peer: &format!("fuse uid={}", req.uid),
+                            conn_id: &self.conn_id,
+                            pid,
+                            addr,
+                            old: old.as_deref(),
+                            new: &new[..bytes],
+                        });
                         Ok(bytes as u32)
                     } else {
                         Err(libc::EIO)
@@ -911,17 +939,11 @@ impl Drop for VirtualMemoryFileSystem {
     fn drop(&mut self) {
         // grab state and remove the reference
         let mut state = state_lock_sync();
-        if state.file_systems.contains_key(&self.id) {
+        if state.file_system_remove(&self.id).is_some() {
             info!(
-                "closing virtual filesystem and removing reference from connection {}",
+                "closed virtual filesystem and removed reference from connection {}",
                 self.conn_id
             );
-
-            if let Some(conn) = state.connection_mut(&self.conn_id) {
-                conn.refcount -= 1;
-            }
-
-            state.file_systems.remove(&self.id);
         }
     }
 }
@@ -12,7 +12,7 @@ use crate::state::KernelHandle;
 
 use std::sync::{Arc, Mutex};
 
-use memflow_win32::{Win32ModuleInfo, Win32Process, Win32ProcessInfo};
+use memflow_win32::{Win32ModuleInfo, Win32ProcessInfo};
 
 pub struct ConnectionScope {
     kernel: Arc<Mutex<KernelHandle>>,
@@ -79,20 +79,13 @@ impl FileSystemEntry for DriverRootFolder {
             let mut result = Vec::new();
 
             if let Ok(mut kernel) = self.kernel.lock() {
-                match &mut *kernel {
-                    KernelHandle::Win32(kernel) => {
-                        if let Ok(mut kernel_proc) = kernel.kernel_process() {
-                            if let Ok(modules) = kernel_proc.module_list() {
-                                for mi in modules.into_iter() {
-                                    result.push(Box::new(ModuleFolder::new(
-                                        self.kernel.clone(),
-                                        kernel_proc.proc_info.clone(),
-                                        mi,
-                                    ))
-                                        as Box<dyn FileSystemEntry>);
-                                }
-                            }
-                        }
+                if let Ok((kernel_proc_info, modules)) = kernel.kernel_module_list() {
+                    for mi in modules.into_iter() {
+                        result.push(Box::new(ModuleFolder::new(
+                            self.kernel.clone(),
+                            kernel_proc_info.clone(),
+                            mi,
+                        )) as Box<dyn FileSystemEntry>);
                     }
                 }
             }
@@ -131,14 +124,10 @@ impl FileSystemEntry for ProcessRootFolder {
             let mut result = Vec::new();
 
             if let Ok(mut kernel) = self.kernel.lock() {
-                match &mut *kernel {
-                    KernelHandle::Win32(kernel) => {
-                        if let Ok(processes) = kernel.process_info_list() {
-                            for pi in processes.into_iter() {
-                                result.push(Box::new(ProcessFolder::new(self.kernel.clone(), pi))
-                                    as Box<dyn FileSystemEntry>);
-                            }
-                        }
+                if let Ok(processes) = kernel.process_info_list() {
+                    for pi in processes.into_iter() {
+                        result.push(Box::new(ProcessFolder::new(self.kernel.clone(), pi))
+                            as Box<dyn FileSystemEntry>);
                     }
                 }
             }
@@ -224,19 +213,13 @@ impl FileSystemEntry for ModuleRootFolder {
             let mut result = Vec::new();
 
             if let Ok(mut kernel) = self.kernel.lock() {
-                match &mut *kernel {
-                    KernelHandle::Win32(kernel) => {
-                        let mut process = Win32Process::with_kernel_ref(kernel, self.pi.clone());
-                        if let Ok(modules) = process.module_list() {
-                            for mi in modules.into_iter() {
-                                result.push(Box::new(ModuleFolder::new(
-                                    self.kernel.clone(),
-                                    self.pi.clone(),
-                                    mi,
-                                ))
-                                    as Box<dyn FileSystemEntry>);
-                            }
-                        }
+                if let Ok(modules) = kernel.module_list(&self.pi) {
+                    for mi in modules.into_iter() {
+                        result.push(Box::new(ModuleFolder::new(
+                            self.kernel.clone(),
+                            self.pi.clone(),
+                            mi,
+                        )) as Box<dyn FileSystemEntry>);
                     }
                 }
             }
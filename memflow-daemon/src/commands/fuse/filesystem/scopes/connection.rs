@@ -15,9 +15,7 @@ pub struct PhysicalDumpFile {
 impl PhysicalDumpFile {
     pub fn new(kernel: Arc<Mutex<KernelHandle>>) -> Self {
         let phys_size = if let Ok(kernel) = kernel.lock() {
-            match &*kernel {
-                KernelHandle::Win32(kernel) => kernel.phys_mem.metadata().size,
-            }
+            kernel.phys_metadata().size
         } else {
             0
         };
@@ -65,26 +63,20 @@ impl PhysicalDumpReader {
 
 impl FileSystemFileHandler for PhysicalDumpReader {
     fn read(&mut self, offset: u64, size: u32) -> Result<Vec<u8>> {
-        match &mut self.kernel {
-            KernelHandle::Win32(kernel) => {
-                let phys_size = kernel.phys_mem.metadata().size;
-                let real_size = std::cmp::min(size as usize, phys_size - offset as usize);
+        let phys_size = self.kernel.phys_metadata().size;
+        let real_size = std::cmp::min(size as usize, phys_size - offset as usize);
 
-                kernel
-                    .phys_mem
-                    .phys_read_raw((offset as u64).into(), real_size)
-                    .map_err(Error::from)
-            }
-        }
+        self.kernel
+            .phys_mem()
+            .phys_read_raw((offset as u64).into(), real_size)
+            .map_err(Error::from)
     }
 
     fn write(&mut self, offset: u64, data: Vec<u8>) -> Result<usize> {
-        match &mut self.kernel {
-            KernelHandle::Win32(kernel) => kernel
-                .phys_mem
-                .phys_write_raw((offset as u64).into(), &data)
-                .map_err(Error::from)
-                .map(|_| data.len()),
-        }
+        self.kernel
+            .phys_mem()
+            .phys_write_raw((offset as u64).into(), &data)
+            .map_err(Error::from)
+            .map(|_| data.len())
     }
 }
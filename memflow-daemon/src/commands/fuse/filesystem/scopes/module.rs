@@ -2,7 +2,7 @@ use super::super::{
     ChildrenList, FileSystemChildren, FileSystemEntry, FileSystemFileHandler, StaticFileReader,
 };
 use crate::error::{Error, Result};
-use crate::state::{CachedWin32Process, KernelHandle};
+use crate::state::KernelHandle;
 
 use std::sync::{Arc, Mutex};
 
@@ -47,12 +47,12 @@ impl FileSystemEntry for ModuleDumpFile {
 
     fn open(&self) -> Result<Box<dyn FileSystemFileHandler>> {
         if let Ok(kernel) = self.kernel.lock() {
-            match &*kernel {
-                KernelHandle::Win32(kernel) => {
-                    let process = Win32Process::with_kernel(kernel.clone(), self.pi.clone());
-                    Ok(Box::new(ModuleDumpReader::new(process, self.mi.clone())))
-                }
-            }
+            let virt_mem = kernel.clone().into_virt_mem(self.pi.clone())?;
+            Ok(Box::new(ModuleDumpReader::new(
+                virt_mem,
+                self.pi.pid,
+                self.mi.clone(),
+            )))
         } else {
             Err(Error::Other("unable to lock kernel".to_string()))
         }
@@ -60,13 +60,14 @@ impl FileSystemEntry for ModuleDumpFile {
 }
 
 struct ModuleDumpReader {
-    process: CachedWin32Process,
+    virt_mem: Box<dyn VirtualMemory>,
+    pid: u32,
     mi: Win32ModuleInfo,
 }
 
 impl ModuleDumpReader {
-    pub fn new(process: CachedWin32Process, mi: Win32ModuleInfo) -> Self {
-        Self { process, mi }
+    pub fn new(virt_mem: Box<dyn VirtualMemory>, pid: u32, mi: Win32ModuleInfo) -> Self {
+        Self { virt_mem, pid, mi }
     }
 }
 
@@ -75,8 +76,7 @@ impl FileSystemFileHandler for ModuleDumpReader {
         let mod_size = self.mi.size;
         let real_size = std::cmp::min(size as usize, mod_size - offset as usize);
 
-        self.process
-            .virt_mem
+        self.virt_mem
             .virt_read_raw(self.mi.base + offset as usize, real_size)
             .data_part()
             .map_err(Error::from)
@@ -86,8 +86,7 @@ impl FileSystemFileHandler for ModuleDumpReader {
         let mod_size = self.mi.size;
         let real_size = std::cmp::min(data.len(), mod_size - offset as usize);
         if real_size > 0 {
-            self.process
-                .virt_mem
+            self.virt_mem
                 .virt_write_raw(self.mi.base + offset as usize, &data[0..real_size])
                 .data_part()
                 .map_err(Error::from)?;
@@ -98,6 +97,10 @@ impl FileSystemFileHandler for ModuleDumpReader {
             ))
         }
     }
+
+    fn address(&self, offset: u64) -> (Option<u32>, u64) {
+        (Some(self.pid), self.mi.base.as_u64() + offset)
+    }
 }
 
 /// Generates a virtual folder which contains PE header, imports and exports.
@@ -180,17 +183,12 @@ impl ModulePeHeaderFile {
         let mut kernel = kernel
             .lock()
             .map_err(|_| Error::Other("unable to acquire kernel lock".to_string()))?;
-        match &mut *kernel {
-            KernelHandle::Win32(kernel) => {
-                let mut process = Win32Process::with_kernel_ref(kernel, pi);
-                let image = process
-                    .virt_mem
-                    .virt_read_raw(mi.base, mi.size)
-                    .data_part()?;
-                let pe = PeView::from_bytes(&image).map_err(Error::PE)?;
-                serde_json::to_string_pretty(&pe).map_err(|_| Error::Serialize)
-            }
-        }
+        let image = kernel
+            .virt_mem(&pi)?
+            .virt_read_raw(mi.base, mi.size)
+            .data_part()?;
+        let pe = PeView::from_bytes(&image).map_err(Error::PE)?;
+        serde_json::to_string_pretty(&pe).map_err(|_| Error::Serialize)
     }
 }
 
@@ -239,38 +237,33 @@ impl ModulePeImportsFile {
         let mut kernel = kernel
             .lock()
             .map_err(|_| Error::Other("unable to acquire kernel lock".to_string()))?;
-        match &mut *kernel {
-            KernelHandle::Win32(kernel) => {
-                let mut process = Win32Process::with_kernel_ref(kernel, pi);
-                let image = process
-                    .virt_mem
-                    .virt_read_raw(mi.base, mi.size)
-                    .data_part()?;
-                let pe = PeView::from_bytes(&image).map_err(Error::PE)?;
-
-                let imports = pe.imports().map_err(Error::PE)?;
-                let mut out = String::new();
-                for desc in imports {
-                    let dll_name = desc.dll_name().map_err(Error::PE)?;
-                    let iat = desc.iat().map_err(Error::PE)?;
-                    let int = desc.int().map_err(Error::PE)?;
-
-                    for (_va, import) in Iterator::zip(iat, int) {
-                        if let Ok(import) = import {
-                            match import {
-                                Import::ByName { hint: _, name } => {
-                                    out.push_str(&format!("{}!{}\n", dll_name, name,));
-                                }
-                                Import::ByOrdinal { ord: _ } => {
-                                    // TODO:
-                                }
-                            }
+        let image = kernel
+            .virt_mem(&pi)?
+            .virt_read_raw(mi.base, mi.size)
+            .data_part()?;
+        let pe = PeView::from_bytes(&image).map_err(Error::PE)?;
+
+        let imports = pe.imports().map_err(Error::PE)?;
+        let mut out = String::new();
+        for desc in imports {
+            let dll_name = desc.dll_name().map_err(Error::PE)?;
+            let iat = desc.iat().map_err(Error::PE)?;
+            let int = desc.int().map_err(Error::PE)?;
+
+            for (_va, import) in Iterator::zip(iat, int) {
+                if let Ok(import) = import {
+                    match import {
+                        Import::ByName { hint: _, name } => {
+                            out.push_str(&format!("{}!{}\n", dll_name, name,));
+                        }
+                        Import::ByOrdinal { ord: _ } => {
+                            // TODO:
                         }
                     }
                 }
-                Ok(out)
             }
         }
+        Ok(out)
     }
 }
 
@@ -319,39 +312,34 @@ impl ModulePeExportsFile {
         let mut kernel = kernel
             .lock()
             .map_err(|_| Error::Other("unable to acquire kernel lock".to_string()))?;
-        match &mut *kernel {
-            KernelHandle::Win32(kernel) => {
-                let mut process = Win32Process::with_kernel_ref(kernel, pi);
-                let image = process
-                    .virt_mem
-                    .virt_read_raw(mi.base, mi.size)
-                    .data_part()?;
-                let pe = PeView::from_bytes(&image).map_err(Error::PE)?;
-
-                let exports = pe.exports().map_err(Error::PE)?;
-                let mut out = String::new();
-                for (&name_rva, function_rva) in exports
-                    .by()
-                    .map_err(Error::PE)?
-                    .names()
-                    .iter()
-                    .zip(exports.by().map_err(Error::PE)?.functions())
-                {
-                    if let Ok(name_it) = pe.derva_c_str(name_rva) {
-                        if let Ok(name_str) = std::str::from_utf8(name_it.as_ref()) {
-                            out.push_str(&format!(
-                                "{} = {}!0x{:x} (0x{:x})\n",
-                                name_str,
-                                mi.name,
-                                function_rva,
-                                mi.base + function_rva,
-                            ));
-                        }
-                    }
+        let image = kernel
+            .virt_mem(&pi)?
+            .virt_read_raw(mi.base, mi.size)
+            .data_part()?;
+        let pe = PeView::from_bytes(&image).map_err(Error::PE)?;
+
+        let exports = pe.exports().map_err(Error::PE)?;
+        let mut out = String::new();
+        for (&name_rva, function_rva) in exports
+            .by()
+            .map_err(Error::PE)?
+            .names()
+            .iter()
+            .zip(exports.by().map_err(Error::PE)?.functions())
+        {
+            if let Ok(name_it) = pe.derva_c_str(name_rva) {
+                if let Ok(name_str) = std::str::from_utf8(name_it.as_ref()) {
+                    out.push_str(&format!(
+                        "{} = {}!0x{:x} (0x{:x})\n",
+                        name_str,
+                        mi.name,
+                        function_rva,
+                        mi.base + function_rva,
+                    ));
                 }
-                Ok(out)
             }
         }
+        Ok(out)
     }
 }
 
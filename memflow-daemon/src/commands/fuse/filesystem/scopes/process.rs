@@ -105,61 +105,54 @@ impl FileSystemEntry for ProcessMiniDump {
                 .lock()
                 .map_err(|_| Error::Other("Poisoned lock".to_string()))?;
 
-            match &mut *kernel {
-                KernelHandle::Win32(kernel) => {
-                    let (major, minor, build) = kernel.kernel_info.kernel_winver.as_tuple();
-
-                    let mut process =
-                        Win32Process::with_kernel_ref(kernel, self.process_info.clone());
-                    let maps = process.virt_mem.virt_page_map(0);
-
-                    let mut ret = vec![];
-                    let mut cursor = std::io::Cursor::new(&mut ret);
-                    let mut minidump = Minidump::default();
-
-                    let mut module_list = ModuleListStream::default();
-
-                    for i in process.module_list()? {
-                        module_list.add_module(MinidumpModule {
-                            base_of_image: i.base.as_u64(),
-                            size_of_image: i.size as _,
-                            checksum: 0,
-                            time_date_stamp: 0,
-                            name: i.name,
-                        });
-                    }
-
-                    minidump
-                        .directory
-                        .push(Box::new(SystemInfoStream::with_arch_and_version(
-                            9, major, minor, build,
-                        )));
-                    minidump.directory.push(Box::new(module_list));
-
-                    let mut memory_list = Memory64ListStream::default();
-
-                    for (addr, size) in maps {
-                        let mut buf = vec![0; size];
-                        process
-                            .virt_mem
-                            .virt_read_raw_into(addr, &mut buf)
-                            .data_part()?;
-                        memory_list.list.push(MemoryDescriptor {
-                            start_of_memory: addr.as_u64(),
-                            buf,
-                        });
-                    }
-
-                    minidump.directory.push(Box::new(memory_list));
-                    minidump
-                        .write_all(&mut cursor)
-                        .map_err(|_| Error::Other("Failed to write minidump".to_string()))?;
-
-                    *locked_cache = Some(ret.clone());
-
-                    Ok(Box::new(StaticFileReader::from_vec(ret)))
-                }
+            let (major, minor, build) = kernel.version().unwrap_or_default();
+            let modules = kernel.module_list(&self.process_info)?;
+
+            let mut virt_mem = kernel.virt_mem(&self.process_info)?;
+            let maps = virt_mem.virt_page_map(0);
+
+            let mut ret = vec![];
+            let mut cursor = std::io::Cursor::new(&mut ret);
+            let mut minidump = Minidump::default();
+
+            let mut module_list = ModuleListStream::default();
+
+            for i in modules {
+                module_list.add_module(MinidumpModule {
+                    base_of_image: i.base.as_u64(),
+                    size_of_image: i.size as _,
+                    checksum: 0,
+                    time_date_stamp: 0,
+                    name: i.name,
+                });
             }
+
+            minidump
+                .directory
+                .push(Box::new(SystemInfoStream::with_arch_and_version(
+                    9, major, minor, build,
+                )));
+            minidump.directory.push(Box::new(module_list));
+
+            let mut memory_list = Memory64ListStream::default();
+
+            for (addr, size) in maps {
+                let mut buf = vec![0; size];
+                virt_mem.virt_read_raw_into(addr, &mut buf).data_part()?;
+                memory_list.list.push(MemoryDescriptor {
+                    start_of_memory: addr.as_u64(),
+                    buf,
+                });
+            }
+
+            minidump.directory.push(Box::new(memory_list));
+            minidump
+                .write_all(&mut cursor)
+                .map_err(|_| Error::Other("Failed to write minidump".to_string()))?;
+
+            *locked_cache = Some(ret.clone());
+
+            Ok(Box::new(StaticFileReader::from_vec(ret)))
         }
     }
 }
@@ -219,48 +212,42 @@ impl FileSystemEntry for ProcessMemoryMaps {
                 .lock()
                 .map_err(|_| Error::Other("Poisoned lock".to_string()))?;
 
-            match &mut *kernel {
-                KernelHandle::Win32(kernel) => {
-                    let mut process =
-                        Win32Process::with_kernel_ref(kernel, self.process_info.clone());
-                    let maps = process.virt_mem.virt_translation_map();
-                    let module_list = process.module_list()?;
-
-                    let ret: String = maps
-                        .into_iter()
-                        .map(|(vaddr, size, paddr)| {
-                            let module = module_list
-                                .iter()
-                                .find(|m| m.base <= vaddr && m.base + m.size > vaddr);
-                            let perms = format!(
-                                "r{}{}",
-                                if paddr.page_type().contains(PageType::WRITEABLE) {
-                                    'w'
-                                } else {
-                                    '-'
-                                },
-                                if !paddr.page_type().contains(PageType::NOEXEC) {
-                                    'x'
-                                } else {
-                                    '-'
-                                }
-                            );
-                            format!(
-                                "{:x}-{:x} {} {:9x} {}\n",
-                                vaddr,
-                                vaddr + size,
-                                perms,
-                                paddr,
-                                module.map(|m| m.name.clone()).unwrap_or_default()
-                            )
-                        })
-                        .collect();
-
-                    *locked_cache = Some(ret.clone());
-
-                    Ok(Box::new(StaticFileReader::from_string(ret)))
-                }
-            }
+            let module_list = kernel.module_list(&self.process_info)?;
+            let maps = kernel.virt_mem(&self.process_info)?.virt_translation_map();
+
+            let ret: String = maps
+                .into_iter()
+                .map(|(vaddr, size, paddr)| {
+                    let module = module_list
+                        .iter()
+                        .find(|m| m.base <= vaddr && m.base + m.size > vaddr);
+                    let perms = format!(
+                        "r{}{}",
+                        if paddr.page_type().contains(PageType::WRITEABLE) {
+                            'w'
+                        } else {
+                            '-'
+                        },
+                        if !paddr.page_type().contains(PageType::NOEXEC) {
+                            'x'
+                        } else {
+                            '-'
+                        }
+                    );
+                    format!(
+                        "{:x}-{:x} {} {:9x} {}\n",
+                        vaddr,
+                        vaddr + size,
+                        perms,
+                        paddr,
+                        module.map(|m| m.name.clone()).unwrap_or_default()
+                    )
+                })
+                .collect();
+
+            *locked_cache = Some(ret.clone());
+
+            Ok(Box::new(StaticFileReader::from_string(ret)))
         }
     }
 }
@@ -1,44 +1,77 @@
 mod stub;
 
+use super::process::{select_process, selector_or_pid};
 use crate::error::{Error, Result};
-use crate::state::{new_uuid, STATE};
-use log::info;
+use crate::events;
+use crate::state::{lock_connection, new_uuid, GdbStubControl, GdbStubHandle, STATE};
+use log::{error, info};
+use std::sync::Arc;
+use tokio::task::block_in_place;
 
 use crate::memflow_rpc::{
-    GdbAttachRequest, GdbAttachResponse, GdbListRequest, GdbListResponse, GdbStub,
+    GdbAttachRequest, GdbAttachResponse, GdbDetachRequest, GdbDetachResponse, GdbListRequest,
+    GdbListResponse, GdbStub,
 };
 
 pub async fn attach(msg: &GdbAttachRequest) -> Result<GdbAttachResponse> {
-    let mut state = STATE.lock().await;
-
     // find connection and spawn gdb thread
-    if let Some(conn) = state.connection_mut(&msg.conn_id) {
-        let kernel = conn.kernel.clone();
-        let id = new_uuid();
-
-        info!("gdb stub with id {} spawned at address {}", id, &msg.addr);
-        info!("the gdb stub will automatically be closed on disconnect");
-
-        let msg_clone = msg.clone();
-        let id_clone = id.clone();
-        std::thread::spawn(move || {
-            stub::spawn_gdb_stub(
-                &id_clone,
-                &msg_clone.conn_id,
-                msg_clone.pid,
-                &msg_clone.addr,
-                kernel,
-            )
-            .unwrap();
-        });
-
-        Ok(GdbAttachResponse { id: id })
-    } else {
-        Err(Error::Connector(format!(
-            "no connection with id {} found",
-            msg.conn_id
-        )))
+    let (conn_id, kernel, proc_info) = {
+        let mut conn = lock_connection(&msg.conn_id).await?;
+        let conn_id = conn.id.clone();
+
+        // ensure the process exists before spawning the stub
+        let kernel = conn.kernel_mut()?;
+        let proc_info =
+            block_in_place(|| select_process(kernel, &selector_or_pid(&msg.process, msg.pid)))?;
+        (conn_id, kernel.clone(), proc_info)
+    };
+
+    let id = new_uuid();
+    let control = Arc::new(GdbStubControl::default());
+
+    // register the stub before spawning it so it can be detached right away
+    {
+        let mut state = STATE.lock().await;
+        state.gdb_stub_add(GdbStubHandle::new(
+            &id,
+            &conn_id,
+            &msg.addr,
+            control.clone(),
+        ))?;
     }
+
+    info!(
+        "gdb stub with id {} for process {} ({}) spawned at address {}",
+        id, proc_info.name, proc_info.pid, &msg.addr
+    );
+    info!("the gdb stub will automatically be closed on disconnect");
+
+    let addr = msg.addr.clone();
+    let id_clone = id.clone();
+    std::thread::spawn(move || {
+        if let Err(err) =
+            stub::spawn_gdb_stub(&id_clone, &conn_id, proc_info, &addr, kernel, control)
+        {
+            error!("gdb stub {} failed: {}", id_clone, err);
+            events::emit_error(&conn_id, &id_clone, &err.to_string());
+        }
+    });
+
+    Ok(GdbAttachResponse { id: id })
+}
+
+/// Stops the gdb stub and disconnects the attached debugger.
+pub async fn detach(msg: &GdbDetachRequest) -> Result<GdbDetachResponse> {
+    let mut state = STATE.lock().await;
+    let stub = state
+        .gdb_stub_remove(&msg.id)
+        .ok_or_else(|| Error::NotFound(format!("no gdb stub with id {} found", msg.id)))?;
+
+    // the stub thread exits on its own once the debugger has been disconnected
+    stub.control.stop();
+
+    info!("gdb stub with id {} detached", msg.id);
+    Ok(GdbDetachResponse {})
 }
 
 pub async fn ls(_msg: &GdbListRequest) -> Result<GdbListResponse> {
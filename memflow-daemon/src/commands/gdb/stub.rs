@@ -1,9 +1,15 @@
+use crate::audit::{self, AuditWrite};
 use crate::error::{Error, Result};
-use crate::state::{state_lock_sync, CachedWin32Process, GdbStubHandle, KernelHandle};
+use crate::events;
+use crate::memflow_rpc::EventKind;
+use crate::state::{state_lock_sync, GdbStubControl, KernelHandle};
 
-use std::net::{TcpListener, TcpStream};
+use std::io::ErrorKind;
+use std::net::{Shutdown, TcpListener, TcpStream};
 #[cfg(unix)]
 use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::Arc;
+use std::time::Duration;
 
 use log::{error, info};
 use url::Url;
@@ -14,24 +20,59 @@ use gdbstub::{
 };
 
 use memflow::*;
+use memflow_win32::Win32ProcessInfo;
+
+/// Interval in which a waiting stub checks if it has been stopped.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Calls the non-blocking `accept` function until a debugger connects.
+///
+/// Returns `None` if the stub has been stopped before a debugger connected.
+fn accept_until_stopped<T>(
+    control: &GdbStubControl,
+    mut accept: impl FnMut() -> std::io::Result<T>,
+) -> Result<Option<T>> {
+    loop {
+        match accept() {
+            Ok(accepted) => return Ok(Some(accepted)),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                if control.is_stopped() {
+                    return Ok(None);
+                }
+                std::thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+            Err(e) => {
+                error!("{}", e);
+                return Err(Error::IO);
+            }
+        }
+    }
+}
 
-fn wait_for_tcp(sockaddr: &str) -> Result<TcpStream> {
+fn wait_for_tcp(sockaddr: &str, control: &GdbStubControl) -> Result<Option<(TcpStream, String)>> {
     info!("started tcp gdb stub on {:?}", sockaddr);
     let sock = TcpListener::bind(sockaddr).map_err(|e| {
         error!("{}", e);
         Error::IO
     })?;
+    sock.set_nonblocking(true).map_err(|_| Error::IO)?;
 
-    let (stream, addr) = sock.accept().map_err(|e| {
-        error!("{}", e);
-        Error::IO
-    })?;
+    let (stream, addr) = match accept_until_stopped(control, || sock.accept())? {
+        Some(accepted) => accepted,
+        None => return Ok(None),
+    };
+    stream.set_nonblocking(false).map_err(|_| Error::IO)?;
     info!("debugger connected from {}", addr);
-    Ok(stream)
+
+    let disconnect = stream.try_clone().map_err(|_| Error::IO)?;
+    control.set_disconnect(Box::new(move || {
+        disconnect.shutdown(Shutdown::Both).ok();
+    }));
+    Ok(Some((stream, format!("tcp {}", addr))))
 }
 
 #[cfg(unix)]
-fn wait_for_uds(path: &str) -> Result<UnixStream> {
+fn wait_for_uds(path: &str, control: &GdbStubControl) -> Result<Option<UnixStream>> {
     match std::fs::remove_file(path) {
         Ok(_) => {}
         Err(e) => match e.kind() {
@@ -48,131 +89,148 @@ fn wait_for_uds(path: &str) -> Result<UnixStream> {
         error!("{}", e);
         Error::IO
     })?;
+    sock.set_nonblocking(true).map_err(|_| Error::IO)?;
 
-    let (stream, addr) = sock.accept().map_err(|e| {
-        error!("{}", e);
-        Error::IO
-    })?;
+    let (stream, addr) = match accept_until_stopped(control, || sock.accept())? {
+        Some(accepted) => accepted,
+        None => return Ok(None),
+    };
+    stream.set_nonblocking(false).map_err(|_| Error::IO)?;
     info!("debugger connected from {:?}", addr);
-    Ok(stream)
-}
 
-fn gdb_stub_init(id: &str, conn_id: &str, addr: &str) -> Result<()> {
-    let mut state = state_lock_sync();
-    if let Some(conn) = state.connection_mut(conn_id) {
-        conn.refcount += 1;
-        state
-            .gdb_stubs
-            .insert(id.to_string(), GdbStubHandle::new(id, conn_id, addr));
-    }
-    Ok(())
+    let disconnect = stream.try_clone().map_err(|_| Error::IO)?;
+    control.set_disconnect(Box::new(move || {
+        disconnect.shutdown(Shutdown::Both).ok();
+    }));
+    Ok(Some(stream))
 }
 
 fn gdb_stub_drop(id: &str, conn_id: &str) -> Result<()> {
     let mut state = state_lock_sync();
-    if state.gdb_stubs.contains_key(id) {
+    if state.gdb_stub_remove(id).is_some() {
         info!(
-            "closing gdb stub and removing reference from connection {}",
+            "closed gdb stub and removed reference from connection {}",
             conn_id
         );
-
-        if let Some(conn) = state.connection_mut(conn_id) {
-            conn.refcount -= 1;
-        }
-
-        state.gdb_stubs.remove(id);
     }
     Ok(())
 }
 
-fn gdb_wait_for_connection(addr: &str, mut stub: GdbStubx64) -> Result<()> {
-    let url = Url::parse(addr).map_err(|_| Error::Other("invalid url".to_string()))?;
+fn gdb_wait_for_connection(
+    id: &str,
+    addr: &str,
+    mut stub: GdbStubx64,
+    control: &GdbStubControl,
+) -> Result<()> {
+    let url = Url::parse(addr).map_err(|_| Error::InvalidArgument("invalid url".to_string()))?;
     let connection: Box<dyn Connection<Error = std::io::Error>> = match url.scheme() {
         "tcp" => {
             if let Some(host_str) = url.host_str() {
-                Box::new(wait_for_tcp(&format!(
-                    "{}:{}",
-                    host_str,
-                    url.port().unwrap_or(8000)
-                ))?)
+                let sockaddr = format!("{}:{}", host_str, url.port().unwrap_or(8000));
+                let (stream, peer) = match wait_for_tcp(&sockaddr, control)? {
+                    Some(accepted) => accepted,
+                    None => {
+                        info!("gdb stub {} detached before a debugger connected", id);
+                        return Ok(());
+                    }
+                };
+                stub.peer = peer;
+                Box::new(stream)
             } else {
-                return Err(Error::Other("invalid tcp host".to_string()));
+                return Err(Error::InvalidArgument("invalid tcp host".to_string()));
             }
         }
         #[cfg(not(target_os = "windows"))]
-        "unix" => Box::new(wait_for_uds(url.path())?),
+        "unix" => {
+            let stream = match wait_for_uds(url.path(), control)? {
+                Some(stream) => stream,
+                None => {
+                    info!("gdb stub {} detached before a debugger connected", id);
+                    return Ok(());
+                }
+            };
+            stub.peer = format!("unix {}", url.path());
+            Box::new(stream)
+        }
         _ => {
-            return Err(Error::Other(
+            return Err(Error::InvalidArgument(
                 "only tcp and unix (not on Windows) urls are supported".to_string(),
             ))
         }
     };
 
+    events::emit(EventKind::GdbClientAttached, &stub.conn_id, id, &stub.peer);
+
     // hook-up debugger
     let mut debugger = GdbStub::new(connection);
-    match debugger.run(&mut stub).unwrap() {
-        DisconnectReason::Disconnect => {
-            info!("client disconnected");
-        }
-        DisconnectReason::TargetHalted => info!("target halted"),
-        DisconnectReason::Kill => {
-            info!("gdb sent a kill command");
+    let reason = match debugger.run(&mut stub) {
+        Ok(DisconnectReason::Disconnect) => "client disconnected",
+        Ok(DisconnectReason::TargetHalted) => "target halted",
+        Ok(DisconnectReason::Kill) => "gdb sent a kill command",
+        // the connection has been shut down by a detach request
+        Err(_) if control.is_stopped() => "gdb stub detached",
+        Err(err) => {
+            let msg = format!("gdb stub failed: {:?}", err);
+            events::emit(EventKind::GdbClientDetached, &stub.conn_id, id, &msg);
+            return Err(Error::Other(msg));
         }
     };
+    info!("{}", reason);
+    events::emit(EventKind::GdbClientDetached, &stub.conn_id, id, reason);
 
     Ok(())
 }
 
-/// Creates a new gdb stub and blocks until the user disconnects.
-/// This function will also add and remove this gdb stub from/into the global state.
+/// Creates a new gdb stub and blocks until the user disconnects or the stub is detached.
+/// The gdb stub has to be added to the global state beforehand, it is removed once this function returns.
 pub fn spawn_gdb_stub(
     id: &str,
     conn_id: &str,
-    pid: PID,
+    proc_info: Win32ProcessInfo,
     addr: &str,
     kernel: KernelHandle,
+    control: Arc<GdbStubControl>,
 ) -> Result<()> {
     // TODO: generic stubs per architecture
-    let stub = GdbStubx64::new(kernel, pid).unwrap();
-
-    // add to global state
-    gdb_stub_init(id, conn_id, addr)?;
-
-    // we do not fail here to ensure stub_drop is called
-    if let Err(e) = gdb_wait_for_connection(addr, stub) {
-        error!("{}", e);
-    }
+    let result = GdbStubx64::new(kernel, conn_id, proc_info)
+        .and_then(|stub| gdb_wait_for_connection(id, addr, stub, &control));
 
+    // the stub is always removed from the global state, even if it failed
     gdb_stub_drop(id, conn_id)?;
-    Ok(())
+    result
 }
 
 /// Implementation of the Virtual Memory GDB Stub
 pub struct GdbStubx64 {
-    process: CachedWin32Process,
+    virt_mem: Box<dyn VirtualMemory>,
+    conn_id: String,
+    pid: u32,
+    /// Address of the connected debugger, used for the audit log
+    peer: String,
     //eip: Address,
 }
 
 impl GdbStubx64 {
-    pub fn new(kernel: KernelHandle, pid: PID) -> Result<Self> {
-        match kernel {
-            KernelHandle::Win32(kernel) => {
-                let process = kernel.into_process_pid(pid).map_err(Error::from)?;
-
-                // get first module
-
-                // get eip
-                /*
-                let image = process
-                    .virt_mem
-                    .virt_read_raw(mi.base, mi.size)
-                    .data_part()?;
-                let pe = PeView::from_bytes(&image).map_err(Error::PE)?;
-                */
-
-                Ok(Self { process })
-            }
-        }
+    pub fn new(kernel: KernelHandle, conn_id: &str, proc_info: Win32ProcessInfo) -> Result<Self> {
+        let pid = proc_info.pid;
+        let virt_mem = kernel.into_virt_mem(proc_info)?;
+
+        // get first module
+
+        // get eip
+        /*
+        let image = virt_mem
+            .virt_read_raw(mi.base, mi.size)
+            .data_part()?;
+        let pe = PeView::from_bytes(&image).map_err(Error::PE)?;
+        */
+
+        Ok(Self {
+            virt_mem,
+            conn_id: conn_id.to_string(),
+            pid,
+            peer: "unknown".to_string(),
+        })
     }
 }
 
@@ -205,7 +263,6 @@ impl Target for GdbStubx64 {
         push_byte: &mut dyn FnMut(u8),
     ) -> Result<()> {
         let buf = self
-            .process
             .virt_mem
             .virt_read_raw(addr.start.into(), (addr.end - addr.start) as usize)
             .data_part()
@@ -219,11 +276,29 @@ impl Target for GdbStubx64 {
     }
 
     fn write_addrs(&mut self, start_addr: u64, data: &[u8]) -> Result<()> {
-        self.process
-            .virt_mem
+        let old = if audit::record_data() {
+            self.virt_mem
+                .virt_read_raw(start_addr.into(), data.len())
+                .data_part()
+                .ok()
+        } else {
+            None
+        };
+
+        self.virt_mem
             .virt_write_raw(start_addr.into(), data)
             .data_part()
             .map_err(Error::from)?;
+
+        audit::record(AuditWrite {
+            source: "gdb",
+            peer: &self.peer,
+            conn_id: &self.conn_id,
+            pid: Some(self.pid),
+            addr: start_addr,
+            old: old.as_deref(),
+            new: data,
+        });
         Ok(())
     }
 
@@ -1,6 +1,14 @@
+pub mod audit;
 pub mod connection;
+pub mod dump;
+pub mod events;
 pub mod fuse;
 pub mod gdb;
 pub mod phys_mem;
+pub mod pointer;
 pub mod process;
+pub mod regions;
+pub mod scan;
+pub mod scanner;
+pub mod translate;
 pub mod virt_mem;
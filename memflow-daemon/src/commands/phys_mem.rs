@@ -1,96 +1,139 @@
-use crate::error::{Error, Result};
-use crate::state::{KernelHandle, STATE};
+use crate::audit::{self, AuditWrite};
+use crate::error::Result;
+use crate::state::lock_connection;
 
 use memflow::{PhysicalMemory, PhysicalReadData, PhysicalWriteData};
+use memflow_daemon::pages::{pack_bitmap, read_pages};
 
 use crate::memflow_rpc::{
     PhysicalMemoryMetadata, PhysicalMemoryMetadataRequest, PhysicalMemoryMetadataResponse,
     ReadPhysicalMemoryEntryResponse, ReadPhysicalMemoryRequest, ReadPhysicalMemoryResponse,
-    WritePhysicalMemoryRequest, WritePhysicalMemoryResponse,
+    ReadStatus, WritePhysicalMemoryRequest, WritePhysicalMemoryResponse,
 };
 
-pub async fn read(msg: &ReadPhysicalMemoryRequest) -> Result<ReadPhysicalMemoryResponse> {
-    let mut state = STATE.lock().await;
-    if let Some(conn) = state.connection_mut(&msg.conn_id) {
-        match &mut conn.kernel {
-            KernelHandle::Win32(kernel) => {
-                // create [PhysicalReadData]
-                let mut result_reads = Vec::new();
-                let mut read_data = Vec::new();
-                for read in msg.reads.iter() {
-                    result_reads.push(ReadPhysicalMemoryEntryResponse {
-                        data: vec![0u8; read.len as usize],
-                    });
-                }
-
-                for read in msg.reads.iter().zip(result_reads.iter_mut()) {
-                    read_data.push(PhysicalReadData(read.0.addr.into(), &mut read.1.data[..]));
-                }
-
-                kernel
-                    .phys_mem
-                    .phys_read_raw_list(&mut read_data.as_mut_slice())?;
-
-                Ok(ReadPhysicalMemoryResponse {
-                    reads: result_reads,
-                })
-            }
-        }
+use tokio::task::block_in_place;
+
+/// Converts the result of a page-wise read into the status and the bitmap of a read entry.
+pub fn read_status(pages: &[bool]) -> (ReadStatus, Vec<u8>) {
+    if pages.iter().all(|valid| *valid) {
+        (ReadStatus::Ok, vec![])
+    } else if pages.iter().any(|valid| *valid) {
+        (ReadStatus::Partial, pack_bitmap(pages))
     } else {
-        Err(Error::Connector(format!(
-            "no connection with id {} found",
-            msg.conn_id
-        )))
+        (ReadStatus::Failed, vec![])
     }
 }
 
-pub async fn write(msg: &WritePhysicalMemoryRequest) -> Result<WritePhysicalMemoryResponse> {
-    let mut state = STATE.lock().await;
-    if let Some(conn) = state.connection_mut(&msg.conn_id) {
-        match &mut conn.kernel {
-            KernelHandle::Win32(kernel) => {
-                // create [PhysicalWriteData]
-                let mut write_data = Vec::new();
-                for write in msg.writes.iter() {
-                    write_data.push(PhysicalWriteData(write.addr.into(), &write.data.as_slice()));
-                }
-
-                kernel
-                    .phys_mem
-                    .phys_write_raw_list(&write_data.as_slice())?;
-
-                Ok(WritePhysicalMemoryResponse {})
+pub async fn read(msg: &ReadPhysicalMemoryRequest) -> Result<ReadPhysicalMemoryResponse> {
+    let mut conn = lock_connection(&msg.conn_id).await?;
+
+    // create [PhysicalReadData]
+    let mut result_reads = Vec::new();
+    for read in msg.reads.iter() {
+        result_reads.push(ReadPhysicalMemoryEntryResponse {
+            data: vec![0u8; read.len as usize],
+            ..Default::default()
+        });
+    }
+
+    block_in_place(|| {
+        let phys_mem = conn.phys_mem();
+
+        let mut read_data = Vec::new();
+        for read in msg.reads.iter().zip(result_reads.iter_mut()) {
+            read_data.push(PhysicalReadData(read.0.addr.into(), &mut read.1.data[..]));
+        }
+
+        if phys_mem
+            .phys_read_raw_list(&mut read_data.as_mut_slice())
+            .is_err()
+        {
+            // retry each entry page by page so a single unreadable page does not discard the entire batch
+            for (read, entry) in msg.reads.iter().zip(result_reads.iter_mut()) {
+                let pages = read_pages(read.addr, &mut entry.data, |addr, buf| {
+                    phys_mem.phys_read_raw_into(addr.into(), buf).is_ok()
+                });
+                let (status, valid_pages) = read_status(&pages);
+                entry.set_status(status);
+                entry.valid_pages = valid_pages;
             }
         }
-    } else {
-        Err(Error::Connector(format!(
-            "no connection with id {} found",
-            msg.conn_id
-        )))
+    });
+
+    Ok(ReadPhysicalMemoryResponse {
+        reads: result_reads,
+    })
+}
+
+pub async fn write(
+    msg: &WritePhysicalMemoryRequest,
+    peer: &str,
+) -> Result<WritePhysicalMemoryResponse> {
+    let mut conn = lock_connection(&msg.conn_id).await?;
+
+    // create [PhysicalWriteData]
+    let mut write_data = Vec::new();
+    for write in msg.writes.iter() {
+        write_data.push(PhysicalWriteData(write.addr.into(), &write.data.as_slice()));
     }
+
+    block_in_place(|| -> Result<()> {
+        // the original bytes are required for the journal, the audit log records them if possible
+        let journal = conn.journal.is_some();
+        let old_data = if journal || audit::record_data() {
+            msg.writes
+                .iter()
+                .map(|write| {
+                    let mut buf = vec![0u8; write.data.len()];
+                    match conn
+                        .phys_mem()
+                        .phys_read_raw_into(write.addr.into(), &mut buf)
+                    {
+                        Ok(_) => Ok(Some(buf)),
+                        Err(err) if journal => Err(err.into()),
+                        Err(_) => Ok(None),
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            vec![None; msg.writes.len()]
+        };
+
+        conn.phys_mem()
+            .phys_write_raw_list(&write_data.as_slice())?;
+
+        for (write, old) in msg.writes.iter().zip(old_data.into_iter()) {
+            audit::record(AuditWrite {
+                source: "rpc",
+                peer,
+                conn_id: &conn.id,
+                pid: None,
+                addr: write.addr,
+                old: old.as_deref(),
+                new: &write.data,
+            });
+
+            if let (Some(journal), Some(old)) = (conn.journal.as_mut(), old) {
+                journal.record(None, write.addr, old);
+            }
+        }
+
+        Ok(())
+    })?;
+
+    Ok(WritePhysicalMemoryResponse {})
 }
 
 pub async fn metadata(
     msg: &PhysicalMemoryMetadataRequest,
 ) -> Result<PhysicalMemoryMetadataResponse> {
-    let mut state = STATE.lock().await;
-    if let Some(conn) = state.connection_mut(&msg.conn_id) {
-        match &mut conn.kernel {
-            KernelHandle::Win32(kernel) => {
-                let metadata = kernel.phys_mem.metadata();
-
-                Ok(PhysicalMemoryMetadataResponse {
-                    metadata: Some(PhysicalMemoryMetadata {
-                        size: metadata.size as u64,
-                        readonly: metadata.readonly,
-                    }),
-                })
-            }
-        }
-    } else {
-        Err(Error::Connector(format!(
-            "no connection with id {} found",
-            msg.conn_id
-        )))
-    }
+    let conn = lock_connection(&msg.conn_id).await?;
+    let metadata = conn.phys_metadata();
+
+    Ok(PhysicalMemoryMetadataResponse {
+        metadata: Some(PhysicalMemoryMetadata {
+            size: metadata.size as u64,
+            readonly: metadata.readonly,
+        }),
+    })
 }
@@ -0,0 +1,579 @@
+use log::info;
+
+use super::process::select_process;
+use super::scan::{find_module, module_at, scan_range};
+use super::scanner::user_regions;
+use crate::error::{Error, Result};
+use crate::state::lock_connection;
+
+use memflow::VirtualMemory;
+use memflow_win32::win32::{Win32ModuleInfo, Win32ProcessInfo};
+
+use crate::memflow_rpc::{
+    PointerHop, PointerPath, ResolvePointerRequest, ResolvePointerResponse, ScanPointersRequest,
+};
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use tokio::sync::mpsc;
+use tokio::task::block_in_place;
+
+/// Default values of a [`ScanPointersRequest`].
+const DEFAULT_MAX_DEPTH: u32 = 3;
+const DEFAULT_MAX_OFFSET: u64 = 0x1000;
+const DEFAULT_MAX_RESULTS: u32 = 100;
+
+/// Upper limits of a [`ScanPointersRequest`].
+const MAX_DEPTH: u32 = 8;
+const MAX_OFFSET: u64 = 0x10000;
+const MAX_RESULTS: u32 = 10_000;
+
+/// Maximum number of pointers collected by a pointer scan.
+const MAX_POINTER_MAP_SIZE: usize = 10_000_000;
+
+/// Maximum number of pointers followed per level of a pointer scan.
+const MAX_LEVEL_NODES: usize = 1_000_000;
+
+/// Number of pointer paths which are queued for the client.
+const POINTER_SCAN_QUEUE_SIZE: usize = 64;
+
+/// Upper limits of a pointer path to keep the recursive parser and evaluator within the stack.
+const MAX_PATH_LEN: usize = 1024;
+const MAX_PATH_DEPTH: usize = 64;
+const MAX_PATH_TERMS: usize = 256;
+
+pub type PointerPathReceiver = mpsc::Receiver<Result<PointerPath>>;
+
+/// A parsed pointer path.
+#[derive(Debug, PartialEq)]
+enum PointerExpr {
+    Number(u64),
+    Module(String),
+    Deref(Box<PointerExpr>),
+    Add(Box<PointerExpr>, Box<PointerExpr>),
+    Sub(Box<PointerExpr>, Box<PointerExpr>),
+}
+
+/// Recursive descent parser for pointer paths like `[[game.exe+0x1A2B]+0x10]+0x8`.
+struct PathParser<'a> {
+    path: &'a str,
+    pos: usize,
+    depth: usize,
+    terms: usize,
+}
+
+impl<'a> PathParser<'a> {
+    fn parse(path: &'a str) -> Result<PointerExpr> {
+        if path.len() > MAX_PATH_LEN {
+            return Err(Error::InvalidArgument(format!(
+                "the pointer path exceeds {} characters",
+                MAX_PATH_LEN
+            )));
+        }
+
+        let mut parser = Self {
+            path,
+            pos: 0,
+            depth: 0,
+            terms: 0,
+        };
+        let expr = parser.expr()?;
+        parser.skip_whitespace();
+        if parser.pos != path.len() {
+            return Err(parser.error());
+        }
+        Ok(expr)
+    }
+
+    fn error(&self) -> Error {
+        Error::InvalidArgument(format!(
+            "invalid pointer path {} at position {}",
+            self.path, self.pos
+        ))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.path[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn expr(&mut self) -> Result<PointerExpr> {
+        let mut lhs = self.term()?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('+') => {
+                    self.pos += 1;
+                    lhs = PointerExpr::Add(Box::new(lhs), Box::new(self.term()?));
+                }
+                Some('-') => {
+                    self.pos += 1;
+                    lhs = PointerExpr::Sub(Box::new(lhs), Box::new(self.term()?));
+                }
+                _ => return Ok(lhs),
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<PointerExpr> {
+        self.terms += 1;
+        if self.terms > MAX_PATH_TERMS {
+            return Err(Error::InvalidArgument(format!(
+                "the pointer path exceeds {} terms",
+                MAX_PATH_TERMS
+            )));
+        }
+
+        self.skip_whitespace();
+        if self.peek() == Some('[') {
+            self.depth += 1;
+            if self.depth > MAX_PATH_DEPTH {
+                return Err(Error::InvalidArgument(format!(
+                    "the pointer path exceeds {} nested dereferences",
+                    MAX_PATH_DEPTH
+                )));
+            }
+
+            self.pos += 1;
+            let expr = self.expr()?;
+            self.skip_whitespace();
+            if self.peek() != Some(']') {
+                return Err(self.error());
+            }
+            self.pos += 1;
+            self.depth -= 1;
+            return Ok(PointerExpr::Deref(Box::new(expr)));
+        }
+
+        // module names may contain a '-' unless it is followed by a number
+        let rest = &self.path[self.pos..];
+        let len = rest
+            .char_indices()
+            .find(|(i, c)| {
+                c.is_whitespace()
+                    || matches!(c, '+' | '[' | ']')
+                    || (*c == '-'
+                        && rest[i + 1..]
+                            .chars()
+                            .next()
+                            .map(|c| c.is_ascii_digit())
+                            .unwrap_or(true))
+            })
+            .map(|(i, _)| i)
+            .unwrap_or_else(|| rest.len());
+        if len == 0 {
+            return Err(self.error());
+        }
+
+        let token = &rest[..len];
+        self.pos += len;
+        Ok(match parse_number(token) {
+            Some(num) => PointerExpr::Number(num),
+            None => PointerExpr::Module(token.to_string()),
+        })
+    }
+}
+
+fn parse_number(value: &str) -> Option<u64> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// Returns the size of a pointer of the process in bytes.
+fn pointer_width(proc_info: &Win32ProcessInfo) -> usize {
+    proc_info.proc_arch.bits() as usize / 8
+}
+
+fn read_pointer(virt_mem: &mut dyn VirtualMemory, addr: u64, width: usize) -> Option<u64> {
+    let mut buf = [0u8; 8];
+    virt_mem
+        .virt_read_raw_into(addr.into(), &mut buf[..width])
+        .ok()
+        .map(|_| u64::from_le_bytes(buf))
+}
+
+struct Evaluator<'a> {
+    virt_mem: &'a mut dyn VirtualMemory,
+    modules: &'a [Win32ModuleInfo],
+    width: usize,
+    hops: Vec<PointerHop>,
+}
+
+impl Evaluator<'_> {
+    /// Evaluates the expression, returns `None` once a pointer could not be read.
+    fn eval(&mut self, expr: &PointerExpr) -> Result<Option<u64>> {
+        Ok(match expr {
+            PointerExpr::Number(num) => Some(*num),
+            PointerExpr::Module(name) => Some(find_module(self.modules, name)?.base.as_u64()),
+            PointerExpr::Deref(inner) => match self.eval(inner)? {
+                Some(addr) => {
+                    let value = read_pointer(self.virt_mem, addr, self.width);
+                    self.hops.push(PointerHop {
+                        addr,
+                        value: value.unwrap_or_default(),
+                        readable: value.is_some(),
+                    });
+                    value
+                }
+                None => None,
+            },
+            PointerExpr::Add(lhs, rhs) => match (self.eval(lhs)?, self.eval(rhs)?) {
+                (Some(lhs), Some(rhs)) => Some(lhs.wrapping_add(rhs)),
+                _ => None,
+            },
+            PointerExpr::Sub(lhs, rhs) => match (self.eval(lhs)?, self.eval(rhs)?) {
+                (Some(lhs), Some(rhs)) => Some(lhs.wrapping_sub(rhs)),
+                _ => None,
+            },
+        })
+    }
+}
+
+/// Evaluates a pointer path and returns each dereferenced pointer.
+pub async fn resolve(msg: &ResolvePointerRequest) -> Result<ResolvePointerResponse> {
+    let expr = PathParser::parse(&msg.path)?;
+
+    let mut conn = lock_connection(&msg.conn_id).await?;
+    let kernel = conn.kernel_mut()?;
+
+    block_in_place(|| {
+        let proc_info = select_process(kernel, &msg.process)?;
+        let modules = kernel.module_list(&proc_info)?;
+        let mut virt_mem = kernel.virt_mem(&proc_info)?;
+
+        let mut evaluator = Evaluator {
+            virt_mem: virt_mem.as_mut(),
+            modules: &modules,
+            width: pointer_width(&proc_info),
+            hops: vec![],
+        };
+        let addr = evaluator.eval(&expr)?;
+
+        Ok(ResolvePointerResponse {
+            addr: addr.unwrap_or_default(),
+            hops: evaluator.hops,
+            valid: addr.is_some(),
+        })
+    })
+}
+
+/// Returns true if the address is part of one of the sorted regions.
+fn is_mapped(regions: &[(u64, u64)], addr: u64) -> bool {
+    let idx = match regions.binary_search_by(|(start, _)| start.cmp(&addr)) {
+        Ok(idx) => idx,
+        Err(0) => return false,
+        Err(idx) => idx - 1,
+    };
+    addr < regions[idx].0 + regions[idx].1
+}
+
+/// Collects all aligned pointers of the process which point into mapped memory.
+///
+/// Returns `(value, addr)` pairs sorted by the value
+/// or an error if the process contains more than [`MAX_POINTER_MAP_SIZE`] pointers.
+fn pointer_map(
+    virt_mem: &mut dyn VirtualMemory,
+    proc_info: &Win32ProcessInfo,
+) -> Result<Vec<(u64, u64)>> {
+    let width = pointer_width(proc_info);
+    let regions = user_regions(virt_mem, proc_info);
+
+    let mut map = vec![];
+    for (addr, len) in regions.iter() {
+        let complete = scan_range(virt_mem, *addr, *len, 0, |chunk| {
+            for offset in (0..chunk.owned.saturating_sub(width - 1)).step_by(width) {
+                if !chunk.is_readable(offset, width) {
+                    continue;
+                }
+
+                let mut buf = [0u8; 8];
+                buf[..width].copy_from_slice(&chunk.data[offset..offset + width]);
+                let value = u64::from_le_bytes(buf);
+                if is_mapped(&regions, value) {
+                    if map.len() >= MAX_POINTER_MAP_SIZE {
+                        return false;
+                    }
+                    map.push((value, chunk.addr + offset as u64));
+                }
+            }
+            true
+        })?;
+        if !complete {
+            return Err(Error::ResourceExhausted(format!(
+                "the process contains more than {} pointers",
+                MAX_POINTER_MAP_SIZE
+            )));
+        }
+    }
+
+    map.sort_unstable();
+    Ok(map)
+}
+
+/// An address the pointer scan searches pointers to.
+struct PointerNode {
+    addr: u64,
+    /// Offsets from this node to the target
+    offsets: Vec<u64>,
+}
+
+fn format_path(module: &str, module_offset: u64, offsets: &[u64]) -> String {
+    let mut path = format!("{}+0x{:x}", module, module_offset);
+    for offset in offsets.iter() {
+        path = format!("[{}]+0x{:x}", path, offset);
+    }
+    path
+}
+
+/// Finds all pointers to the given nodes.
+///
+/// Returns at most `max_paths` paths starting in a module and the nodes of the next level.
+/// No nodes are returned once `max_paths` has been reached.
+fn next_level(
+    map: &[(u64, u64)],
+    modules: &[Win32ModuleInfo],
+    nodes: &[PointerNode],
+    max_offset: u64,
+    max_paths: usize,
+    visited: &mut HashSet<u64>,
+    last: bool,
+) -> (Vec<PointerPath>, Vec<PointerNode>) {
+    let mut paths = vec![];
+    let mut next = vec![];
+    for node in nodes.iter() {
+        let lower = node.addr.saturating_sub(max_offset);
+        // the index of the first pointer which is not below `lower`
+        let start = map
+            .binary_search_by(|(value, _)| {
+                if *value < lower {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            })
+            .unwrap_or_else(|idx| idx);
+
+        for (value, addr) in map[start..]
+            .iter()
+            .take_while(|(value, _)| *value <= node.addr)
+        {
+            let mut offsets = vec![node.addr - value];
+            offsets.extend_from_slice(&node.offsets);
+
+            if let Some(module) = module_at(modules, *addr) {
+                let module_offset = addr - module.base.as_u64();
+                paths.push(PointerPath {
+                    module: module.name.clone(),
+                    module_offset,
+                    path: format_path(&module.name, module_offset, &offsets),
+                    offsets,
+                });
+                if paths.len() >= max_paths {
+                    return (paths, vec![]);
+                }
+            } else if !last && next.len() < MAX_LEVEL_NODES && visited.insert(*addr) {
+                next.push(PointerNode {
+                    addr: *addr,
+                    offsets,
+                });
+            }
+        }
+    }
+    (paths, next)
+}
+
+/// Streams pointer paths starting in a module which lead to the target address.
+pub async fn scan(msg: &ScanPointersRequest) -> Result<PointerPathReceiver> {
+    let max_depth = if msg.max_depth == 0 {
+        DEFAULT_MAX_DEPTH
+    } else {
+        msg.max_depth.min(MAX_DEPTH)
+    };
+    let max_offset = if msg.max_offset == 0 {
+        DEFAULT_MAX_OFFSET
+    } else {
+        msg.max_offset.min(MAX_OFFSET)
+    };
+    let max_results = if msg.max_results == 0 {
+        DEFAULT_MAX_RESULTS
+    } else {
+        msg.max_results.min(MAX_RESULTS)
+    } as usize;
+
+    // the memory is only read once, the connection is not locked while searching paths
+    let (modules, map, target) = {
+        let mut conn = lock_connection(&msg.conn_id).await?;
+        let kernel = conn.kernel_mut()?;
+        block_in_place(|| -> Result<_> {
+            let proc_info = select_process(kernel, &msg.process)?;
+            let modules = kernel.module_list(&proc_info)?;
+            let target = if msg.module.is_empty() {
+                msg.target
+            } else {
+                find_module(&modules, &msg.module)?
+                    .base
+                    .as_u64()
+                    .checked_add(msg.target)
+                    .ok_or_else(|| Error::InvalidArgument("target out of range".to_string()))?
+            };
+            let mut virt_mem = kernel.virt_mem(&proc_info)?;
+            let map = pointer_map(virt_mem.as_mut(), &proc_info)?;
+
+            info!(
+                "scanning {} pointers of process {} ({}) for paths to {:x}",
+                map.len(),
+                proc_info.name,
+                proc_info.pid,
+                target
+            );
+            Ok((modules, map, target))
+        })?
+    };
+
+    let (tx, rx) = mpsc::channel(POINTER_SCAN_QUEUE_SIZE);
+    tokio::spawn(async move {
+        let mut nodes = vec![PointerNode {
+            addr: target,
+            offsets: vec![],
+        }];
+        let mut visited = HashSet::new();
+        visited.insert(target);
+
+        let mut found = 0;
+        for depth in 1..=max_depth {
+            let (paths, next) = block_in_place(|| {
+                next_level(
+                    &map,
+                    &modules,
+                    &nodes,
+                    max_offset,
+                    max_results - found,
+                    &mut visited,
+                    depth == max_depth,
+                )
+            });
+
+            for path in paths.into_iter() {
+                if tx.send(Ok(path)).await.is_err() {
+                    return;
+                }
+                found += 1;
+            }
+
+            if found == max_results || next.is_empty() {
+                break;
+            }
+            nodes = next;
+        }
+    });
+
+    Ok(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(num: u64) -> Box<PointerExpr> {
+        Box::new(PointerExpr::Number(num))
+    }
+
+    fn module(name: &str) -> Box<PointerExpr> {
+        Box::new(PointerExpr::Module(name.to_string()))
+    }
+
+    fn deref(expr: PointerExpr) -> Box<PointerExpr> {
+        Box::new(PointerExpr::Deref(Box::new(expr)))
+    }
+
+    #[test]
+    fn parse_nested_path() {
+        let inner = PointerExpr::Add(module("game.exe"), num(0x1a2b));
+        let outer = PointerExpr::Add(deref(inner), num(0x10));
+        assert_eq!(
+            PathParser::parse("[[game.exe+0x1A2B]+0x10]+0x8").unwrap(),
+            PointerExpr::Add(deref(outer), num(0x8))
+        );
+    }
+
+    #[test]
+    fn parse_decimal_and_sub() {
+        assert_eq!(
+            PathParser::parse("[0x1000]-16").unwrap(),
+            PointerExpr::Sub(deref(PointerExpr::Number(0x1000)), num(16))
+        );
+    }
+
+    #[test]
+    fn parse_module_with_dash() {
+        assert_eq!(
+            PathParser::parse("my-game.exe+0x10").unwrap(),
+            PointerExpr::Add(module("my-game.exe"), num(0x10))
+        );
+        assert_eq!(
+            PathParser::parse("game-1").unwrap(),
+            PointerExpr::Sub(module("game"), num(1))
+        );
+    }
+
+    #[test]
+    fn parse_whitespace() {
+        assert_eq!(
+            PathParser::parse(" [ 0x10 ] + 8 ").unwrap(),
+            PointerExpr::Add(deref(PointerExpr::Number(0x10)), num(8))
+        );
+    }
+
+    #[test]
+    fn parse_multi_byte_whitespace() {
+        assert_eq!(
+            PathParser::parse("\u{a0}[0x10]\u{a0}+\u{2003}8\u{a0}").unwrap(),
+            PointerExpr::Add(deref(PointerExpr::Number(0x10)), num(8))
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for path in ["", "[0x10", "0x10]", "0x10 +", "[]", "0x10 0x20", "+0x10"].iter() {
+            assert!(PathParser::parse(path).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn parse_nesting_limit() {
+        let nested = |depth| format!("{}0x10{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(PathParser::parse(&nested(MAX_PATH_DEPTH)).is_ok());
+        assert!(PathParser::parse(&nested(MAX_PATH_DEPTH + 1)).is_err());
+    }
+
+    #[test]
+    fn parse_term_limit() {
+        let chain = |terms| vec!["0"; terms].join("+");
+        assert!(PathParser::parse(&chain(MAX_PATH_TERMS)).is_ok());
+        assert!(PathParser::parse(&chain(MAX_PATH_TERMS + 1)).is_err());
+    }
+
+    #[test]
+    fn parse_length_limit() {
+        let path = format!("0x{}", "0".repeat(MAX_PATH_LEN));
+        assert!(PathParser::parse(&path).is_err());
+    }
+
+    #[test]
+    fn format_nested_path() {
+        assert_eq!(
+            format_path("game.exe", 0x1a2b, &[0x10, 0x8]),
+            "[[game.exe+0x1a2b]+0x10]+0x8"
+        );
+    }
+}
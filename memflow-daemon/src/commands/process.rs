@@ -2,14 +2,141 @@ use log::info;
 
 use crate::error::{Error, Result};
 
-use crate::state::KernelHandle;
-use crate::state::STATE;
+use crate::state::{lock_connection, KernelHandle};
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::task::block_in_place;
 
 use crate::memflow_rpc::{
-    ListProcessesRequest, ListProcessesResponse, ProcessInfoRequest, ProcessInfoResponse,
-    Win32ModuleInfo, Win32ProcessInfo,
+    process_selector::Selector, ListProcessesRequest, ListProcessesResponse, ModuleEvent,
+    ModuleEventKind, ProcessEvent, ProcessEventKind, ProcessInfoRequest, ProcessInfoResponse,
+    ProcessSelector, WatchModulesRequest, WatchProcessesRequest, Win32ModuleInfo, Win32ProcessInfo,
 };
 
+/// Exit status of processes which are still running (STILL_ACTIVE).
+const EXIT_STATUS_STILL_ACTIVE: i32 = 259;
+
+/// Maximum length of the process name stored in the EPROCESS structure.
+const IMAGE_FILE_NAME_LENGTH: usize = 15;
+
+/// Default interval in which the process and module lists are polled by [`watch`] and [`watch_modules`].
+const WATCH_INTERVAL_MS: u64 = 1000;
+
+/// Shorter intervals are raised to this so a watch cannot keep the connection busy.
+const MIN_WATCH_INTERVAL_MS: u64 = 100;
+
+/// Number of process and module events which are queued for the client.
+const WATCH_QUEUE_SIZE: usize = 64;
+
+/// Returns the selector of a request which still accepts the deprecated `pid` field.
+///
+/// The pid is only used if no selector is set, a pid of 0 selects no process.
+pub fn selector_or_pid(selector: &Option<ProcessSelector>, pid: u32) -> Option<ProcessSelector> {
+    match selector {
+        Some(selector) => Some(selector.clone()),
+        None if pid != 0 => Some(ProcessSelector {
+            selector: Some(Selector::Pid(pid)),
+        }),
+        None => None,
+    }
+}
+
+/// Finds the process referenced by the given selector.
+pub fn select_process(
+    kernel: &mut KernelHandle,
+    selector: &Option<ProcessSelector>,
+) -> Result<memflow_win32::win32::Win32ProcessInfo> {
+    match selector.as_ref().and_then(|s| s.selector.as_ref()) {
+        Some(Selector::Pid(pid)) => kernel.process_info_pid(*pid),
+        Some(Selector::Name(name)) => {
+            // the name in the EPROCESS structure is truncated
+            let name = name
+                .chars()
+                .take(IMAGE_FILE_NAME_LENGTH)
+                .collect::<String>()
+                .to_lowercase();
+            find_unique_process(kernel, &format!("name {}", name), |p| {
+                p.name.to_lowercase() == name
+            })
+        }
+        Some(Selector::NameGlob(pattern)) => {
+            let pattern = pattern.to_lowercase();
+            find_unique_process(kernel, &format!("pattern {}", pattern), |p| {
+                glob_match(&pattern, &p.name.to_lowercase())
+            })
+        }
+        Some(Selector::Address(address)) => kernel
+            .process_info_list()?
+            .into_iter()
+            .find(|p| p.address.as_u64() == *address)
+            .ok_or_else(|| Error::NotFound(format!("no process found at address {:x}", address))),
+        None => Err(Error::InvalidArgument("no process selected".to_string())),
+    }
+}
+
+/// Returns the only running process matching the filter.
+fn find_unique_process<F>(
+    kernel: &mut KernelHandle,
+    description: &str,
+    filter: F,
+) -> Result<memflow_win32::win32::Win32ProcessInfo>
+where
+    F: Fn(&memflow_win32::win32::Win32ProcessInfo) -> bool,
+{
+    let mut processes = kernel
+        .process_info_list()?
+        .into_iter()
+        .filter(|p| p.exit_status == EXIT_STATUS_STILL_ACTIVE && filter(p))
+        .collect::<Vec<_>>();
+
+    match processes.len() {
+        0 => Err(Error::NotFound(format!(
+            "no running process matches {}",
+            description
+        ))),
+        1 => Ok(processes.remove(0)),
+        n => Err(Error::InvalidArgument(format!(
+            "{} processes match {}, please select a single process by pid: {}",
+            n,
+            description,
+            processes
+                .iter()
+                .map(|p| format!("{} ({})", p.name, p.pid))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+/// Matches the name against a pattern containing `*` and `?` wildcards.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((bp, bn)) = backtrack {
+            // let the last '*' consume one more character
+            p = bp + 1;
+            n = bn + 1;
+            backtrack = Some((bp, bn + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 fn conv_win32_module(module: &memflow_win32::win32::Win32ModuleInfo) -> Win32ModuleInfo {
     Win32ModuleInfo {
         peb_entry: module.peb_entry.as_u64(),
@@ -47,70 +174,296 @@ fn conv_win32_process(proc_info: &memflow_win32::win32::Win32ProcessInfo) -> Win
 }
 
 pub async fn process_info(msg: &ProcessInfoRequest) -> Result<ProcessInfoResponse> {
-    let mut state = STATE.lock().await;
+    let mut conn = lock_connection(&msg.conn_id).await?;
+    let kernel = conn.kernel_mut()?;
+
+    let (proc_info, module_list) = block_in_place(|| -> Result<_> {
+        let proc_info = select_process(kernel, &selector_or_pid(&msg.process, msg.pid))?;
+        let module_list = kernel.module_list(&proc_info)?;
+        Ok((proc_info, module_list))
+    })?;
+
+    let modules = module_list
+        .into_iter()
+        .map(|x| conv_win32_module(&x))
+        .collect();
+
+    let response = ProcessInfoResponse {
+        process: Some(conv_win32_process(&proc_info)),
+        modules: modules,
+    };
+    Ok(response)
+}
 
-    if let Some(conn) = state.connection_mut(&msg.conn_id) {
-        match &mut conn.kernel {
-            KernelHandle::Win32(kernel) => {
-                let mut proc = kernel.process_pid(msg.pid)?;
-                let module_list = proc.module_list()?;
-
-                let modules = module_list
-                    .into_iter()
-                    .map(|x| conv_win32_module(&x))
-                    .collect();
-
-                let response = ProcessInfoResponse {
-                    process: Some(conv_win32_process(&proc.proc_info)),
-                    modules: modules,
-                };
-                Ok(response)
-            }
-        }
+pub async fn ls(msg: &ListProcessesRequest) -> Result<ListProcessesResponse> {
+    let mut conn = lock_connection(&msg.conn_id).await?;
+    let kernel = conn.kernel_mut()?;
+
+    if let Ok(processes) = block_in_place(|| kernel.process_info_list()) {
+        info!(
+            "listing processes for connection {}: {} processes\n",
+            msg.conn_id,
+            processes.len(),
+        );
+
+        let response = ListProcessesResponse {
+            processes: processes
+                .into_iter()
+                .map(|x| conv_win32_process(&x))
+                .collect(),
+        };
+        Ok(response)
     } else {
         Err(Error::Connector(format!(
-            "no connection with id {} found",
+            "could not get processes on connection {}",
             msg.conn_id
         )))
     }
 }
 
-pub async fn ls(msg: &ListProcessesRequest) -> Result<ListProcessesResponse> {
-    let mut state = STATE.lock().await;
+pub type ProcessEventReceiver = mpsc::Receiver<Result<ProcessEvent>>;
+
+/// Polls the process list of the connection and reports created and exited processes.
+///
+/// Processes are identified by their pid and EPROCESS address as pids are reused.
+/// The watch ends once the client disconnects or the connection is closed.
+pub async fn watch(msg: &WatchProcessesRequest) -> Result<ProcessEventReceiver> {
+    // fail early if the connection cannot list processes
+    lock_connection(&msg.conn_id).await?.kernel()?;
+
+    let interval = watch_interval(msg.interval_ms);
+    let filters = msg
+        .names
+        .iter()
+        .map(|name| name.to_lowercase())
+        .collect::<Vec<_>>();
+
+    info!(
+        "watching processes of connection {} every {:?}",
+        msg.conn_id, interval
+    );
+
+    let msg = msg.clone();
+    let (tx, rx) = mpsc::channel(WATCH_QUEUE_SIZE);
+    tokio::spawn(async move {
+        let mut running: Option<HashMap<(u32, u64), memflow_win32::win32::Win32ProcessInfo>> = None;
+        loop {
+            let processes = match list_processes(&msg.conn_id).await {
+                Ok(processes) => processes,
+                Err(err) => {
+                    tx.send(Err(err)).await.ok();
+                    break;
+                }
+            };
+            let timestamp = timestamp_ms();
+
+            // exited processes might still be in the list with their exit status set
+            let all = processes
+                .into_iter()
+                .map(|p| ((p.pid, p.address.as_u64()), p))
+                .collect::<HashMap<_, _>>();
+            let current = all
+                .iter()
+                .filter(|(_, p)| {
+                    p.exit_status == EXIT_STATUS_STILL_ACTIVE && name_matches(&filters, &p.name)
+                })
+                .map(|(key, p)| (*key, p.clone()))
+                .collect::<HashMap<_, _>>();
+
+            let mut events = vec![];
+            match &running {
+                Some(previous) => {
+                    for (key, p) in current.iter() {
+                        if !previous.contains_key(key) {
+                            events.push((ProcessEventKind::Created, p));
+                        }
+                    }
+                    for (key, p) in previous.iter() {
+                        if !current.contains_key(key) {
+                            events.push((ProcessEventKind::Exited, all.get(key).unwrap_or(p)));
+                        }
+                    }
+                }
+                None if msg.initial => {
+                    events.extend(current.values().map(|p| (ProcessEventKind::Created, p)));
+                }
+                None => {}
+            }
+            events.sort_by_key(|(_, p)| p.pid);
 
-    if let Some(conn) = state.connection_mut(&msg.conn_id) {
-        match &mut conn.kernel {
-            KernelHandle::Win32(kernel) => {
-                if let Ok(processes) = kernel.process_info_list() {
-                    info!(
-                        "listing processes for connection {}: {} processes\n",
-                        msg.conn_id,
-                        processes.len(),
-                    );
-
-                    let response = ListProcessesResponse {
-                        processes: processes
-                            .into_iter()
-                            .map(|x| conv_win32_process(&x))
-                            .collect(),
-                    };
-                    Ok(response)
-                } else {
-                    Err(Error::Connector(format!(
-                        "could not get processes on connection {}",
-                        msg.conn_id
-                    )))
+            for (kind, p) in events.into_iter() {
+                let event = ProcessEvent {
+                    timestamp,
+                    kind: kind as i32,
+                    process: Some(conv_win32_process(p)),
+                };
+                if tx.send(Ok(event)).await.is_err() {
+                    return;
                 }
             }
+            running = Some(current);
+
+            tokio::select! {
+                _ = tx.closed() => break,
+                _ = tokio::time::sleep(interval) => {}
+            }
         }
-    } else {
-        Err(Error::Connector(format!(
-            "no connection with id {} found",
-            msg.conn_id
-        )))
+        info!("stopped watching processes of connection {}", msg.conn_id);
+    });
+
+    Ok(rx)
+}
+
+pub type ModuleEventReceiver = mpsc::Receiver<Result<ModuleEvent>>;
+
+/// Polls the module list of a process and reports loaded and unloaded modules.
+///
+/// The watch ends once the client disconnects, the connection is closed or the process exits.
+pub async fn watch_modules(msg: &WatchModulesRequest) -> Result<ModuleEventReceiver> {
+    let proc_info = {
+        let mut conn = lock_connection(&msg.conn_id).await?;
+        let kernel = conn.kernel_mut()?;
+        block_in_place(|| select_process(kernel, &msg.process))?
+    };
+    let interval = watch_interval(msg.interval_ms);
+
+    info!(
+        "watching modules of process {} ({}) every {:?}",
+        proc_info.name, proc_info.pid, interval
+    );
+
+    let msg = msg.clone();
+    let (tx, rx) = mpsc::channel(WATCH_QUEUE_SIZE);
+    tokio::spawn(async move {
+        let mut loaded: Option<HashMap<(u64, String), memflow_win32::win32::Win32ModuleInfo>> =
+            None;
+        loop {
+            let modules = match list_modules(&msg.conn_id, &proc_info).await {
+                Ok(modules) => modules,
+                Err(err) => {
+                    tx.send(Err(err)).await.ok();
+                    break;
+                }
+            };
+            let timestamp = timestamp_ms();
+
+            let current = modules
+                .into_iter()
+                .map(|m| ((m.base.as_u64(), m.name.clone()), m))
+                .collect::<HashMap<_, _>>();
+
+            let mut events = vec![];
+            match &loaded {
+                Some(previous) => {
+                    for (key, m) in current.iter() {
+                        if !previous.contains_key(key) {
+                            events.push((ModuleEventKind::Loaded, m));
+                        }
+                    }
+                    for (key, m) in previous.iter() {
+                        if !current.contains_key(key) {
+                            events.push((ModuleEventKind::Unloaded, m));
+                        }
+                    }
+                }
+                None if msg.initial => {
+                    events.extend(current.values().map(|m| (ModuleEventKind::Loaded, m)));
+                }
+                None => {}
+            }
+            events.sort_by_key(|(_, m)| m.base.as_u64());
+
+            for (kind, m) in events.into_iter() {
+                let event = ModuleEvent {
+                    timestamp,
+                    kind: kind as i32,
+                    module: Some(conv_win32_module(m)),
+                };
+                if tx.send(Ok(event)).await.is_err() {
+                    return;
+                }
+            }
+            loaded = Some(current);
+
+            tokio::select! {
+                _ = tx.closed() => break,
+                _ = tokio::time::sleep(interval) => {}
+            }
+        }
+        info!(
+            "stopped watching modules of process {} ({})",
+            proc_info.name, proc_info.pid
+        );
+    });
+
+    Ok(rx)
+}
+
+/// Lists the modules of the process if it is still running.
+async fn list_modules(
+    conn_id: &str,
+    proc_info: &memflow_win32::win32::Win32ProcessInfo,
+) -> Result<Vec<memflow_win32::win32::Win32ModuleInfo>> {
+    let mut conn = lock_connection(conn_id).await?;
+    let kernel = conn.kernel_mut()?;
+    block_in_place(|| {
+        ensure_running(kernel, proc_info)?;
+        kernel.module_list(proc_info)
+    })
+}
+
+/// Ensures the process has not exited since `proc_info` has been retrieved.
+pub fn ensure_running(
+    kernel: &mut KernelHandle,
+    proc_info: &memflow_win32::win32::Win32ProcessInfo,
+) -> Result<()> {
+    // the pid might have been reused by another process
+    match kernel.process_info_pid(proc_info.pid).ok() {
+        Some(p) if p.address == proc_info.address && p.exit_status == EXIT_STATUS_STILL_ACTIVE => {
+            Ok(())
+        }
+        _ => Err(Error::NotFound(format!(
+            "process {} ({}) has exited",
+            proc_info.name, proc_info.pid
+        ))),
     }
 }
 
+fn watch_interval(interval_ms: u64) -> Duration {
+    Duration::from_millis(if interval_ms == 0 {
+        WATCH_INTERVAL_MS
+    } else {
+        interval_ms.max(MIN_WATCH_INTERVAL_MS)
+    })
+}
+
+fn timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+async fn list_processes(conn_id: &str) -> Result<Vec<memflow_win32::win32::Win32ProcessInfo>> {
+    let mut conn = lock_connection(conn_id).await?;
+    let kernel = conn.kernel_mut()?;
+    block_in_place(|| kernel.process_info_list())
+}
+
+/// Checks the name against the lowercase names and name patterns of a [`WatchProcessesRequest`].
+fn name_matches(filters: &[String], name: &str) -> bool {
+    let name = name.to_lowercase();
+    filters.is_empty()
+        || filters.iter().any(|filter| {
+            if filter.contains('*') || filter.contains('?') {
+                glob_match(filter, &name)
+            } else {
+                // the name in the EPROCESS structure is truncated
+                filter.chars().take(IMAGE_FILE_NAME_LENGTH).eq(name.chars())
+            }
+        })
+}
+
 /*
 pub async fn open<S: Sink<response::Message> + Unpin>(
     frame: &mut S,
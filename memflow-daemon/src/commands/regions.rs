@@ -0,0 +1,116 @@
+use log::info;
+
+use super::process::select_process;
+use super::scanner::user_space_end;
+use crate::error::{Error, Result};
+use crate::state::lock_connection;
+
+use memflow::types::PageType;
+use memflow::VirtualMemory;
+
+use crate::memflow_rpc::{
+    ListMemoryRegionsRequest, ListMemoryRegionsResponse, MemoryRegion, MemoryRegionKind,
+};
+
+use tokio::task::block_in_place;
+
+/// The permissions a region has to have to be listed.
+#[derive(Default)]
+struct ProtectionFilter {
+    write: bool,
+    execute: bool,
+}
+
+impl ProtectionFilter {
+    /// Parses a filter like `rw` or `r-x`, mapped pages are always readable.
+    fn parse(protection: &str) -> Result<Self> {
+        let mut filter = Self::default();
+        for c in protection.chars() {
+            match c {
+                'r' | '-' => {}
+                'w' => filter.write = true,
+                'x' => filter.execute = true,
+                _ => {
+                    return Err(Error::InvalidArgument(format!(
+                        "invalid protection: {}",
+                        protection
+                    )))
+                }
+            }
+        }
+        Ok(filter)
+    }
+
+    fn matches(&self, region: &MemoryRegion) -> bool {
+        (!self.write || region.writable) && (!self.execute || region.executable)
+    }
+}
+
+/// Lists the memory regions of a process.
+pub async fn ls(msg: &ListMemoryRegionsRequest) -> Result<ListMemoryRegionsResponse> {
+    let filter = ProtectionFilter::parse(&msg.protection)?;
+
+    let mut conn = lock_connection(&msg.conn_id).await?;
+    let kernel = conn.kernel_mut()?;
+
+    block_in_place(|| {
+        let proc_info = select_process(kernel, &msg.process)?;
+        let modules = kernel.module_list(&proc_info)?;
+        let mut maps = kernel.virt_mem(&proc_info)?.virt_translation_map();
+        maps.sort_by_key(|(vaddr, _, _)| *vaddr);
+
+        let end = user_space_end(&proc_info);
+        let mut regions: Vec<MemoryRegion> = vec![];
+        for (vaddr, size, paddr) in maps.into_iter() {
+            let start = vaddr.as_u64();
+            if start >= end && !msg.kernel {
+                break;
+            }
+
+            let page_type = paddr.page_type();
+            let module = modules
+                .iter()
+                .find(|m| m.base <= vaddr && m.base + m.size > vaddr);
+            let kind = if module.is_some() {
+                MemoryRegionKind::Image
+            } else if start >= end {
+                MemoryRegionKind::Kernel
+            } else {
+                MemoryRegionKind::Private
+            };
+            let region = MemoryRegion {
+                start,
+                size: size as u64,
+                writable: page_type.contains(PageType::WRITEABLE),
+                executable: !page_type.contains(PageType::NOEXEC),
+                kind: kind as i32,
+                module: module.map(|m| m.name.clone()).unwrap_or_default(),
+            };
+
+            // merge adjacent pages which only differ in their address
+            match regions.last_mut() {
+                Some(last)
+                    if last.start + last.size == region.start
+                        && last.writable == region.writable
+                        && last.executable == region.executable
+                        && last.kind == region.kind
+                        && last.module == region.module =>
+                {
+                    last.size += region.size
+                }
+                _ => regions.push(region),
+            }
+        }
+
+        regions.retain(|region| filter.matches(region));
+
+        info!(
+            "listed {} memory regions of process {} ({})",
+            regions.len(),
+            proc_info.name,
+            proc_info.pid
+        );
+
+        Ok(ListMemoryRegionsResponse { regions })
+    })
+}
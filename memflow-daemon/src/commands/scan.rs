@@ -0,0 +1,395 @@
+use log::info;
+
+use super::process::select_process;
+use crate::error::{Error, Result};
+use crate::state::lock_connection;
+
+use memflow::VirtualMemory;
+use memflow_daemon::pages::{page_index, read_pages, split_pages};
+use memflow_win32::win32::Win32ModuleInfo;
+
+use crate::memflow_rpc::{PatternMatch, ScanPatternRequest, ScanPatternResponse};
+
+use std::cmp::min;
+use tokio::task::block_in_place;
+
+/// Size of the chunks in which memory is read while scanning.
+const SCAN_CHUNK_SIZE: u64 = 0x10_0000;
+
+/// Default number of matches returned by [`pattern`].
+const DEFAULT_MAX_MATCHES: usize = 1000;
+
+/// A byte pattern, `None` matches any byte.
+pub struct Pattern {
+    bytes: Vec<Option<u8>>,
+}
+
+impl Pattern {
+    /// Parses an IDA-style pattern like `48 8B 05 ?? ?? ?? ?? 48 85 C0`.
+    ///
+    /// If `mask` is not empty it has to contain a `x` or a `?` for each byte of the pattern
+    /// and overrides the wildcards of the pattern.
+    pub fn parse(pattern: &str, mask: &str) -> Result<Self> {
+        let invalid = || Error::InvalidArgument(format!("invalid pattern: {}", pattern));
+
+        let mut bytes = vec![];
+        for token in pattern.split_whitespace() {
+            if token == "?" {
+                bytes.push(None);
+                continue;
+            }
+
+            // tokens may contain multiple bytes, e.g. `488B05????`
+            if token.len() % 2 != 0 {
+                return Err(invalid());
+            }
+            for i in (0..token.len()).step_by(2) {
+                match token.get(i..i + 2).ok_or_else(invalid)? {
+                    "??" => bytes.push(None),
+                    byte => bytes.push(Some(u8::from_str_radix(byte, 16).map_err(|_| invalid())?)),
+                }
+            }
+        }
+
+        if !mask.is_empty() {
+            if mask.len() != bytes.len() {
+                return Err(Error::InvalidArgument(format!(
+                    "the mask has {} characters but the pattern has {} bytes",
+                    mask.len(),
+                    bytes.len()
+                )));
+            }
+
+            for (byte, m) in bytes.iter_mut().zip(mask.chars()) {
+                match m {
+                    'x' | 'X' if byte.is_none() => {
+                        return Err(Error::InvalidArgument(
+                            "the mask requires an exact match for a wildcard byte".to_string(),
+                        ))
+                    }
+                    'x' | 'X' => {}
+                    '?' => *byte = None,
+                    _ => return Err(Error::InvalidArgument(format!("invalid mask: {}", mask))),
+                }
+            }
+        }
+
+        if bytes.iter().all(Option::is_none) {
+            return Err(Error::InvalidArgument(
+                "the pattern has to contain at least one byte which is not a wildcard".to_string(),
+            ));
+        }
+
+        Ok(Self { bytes })
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns the offsets of all matches in `data`.
+    pub fn find_all<'a>(&'a self, data: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        (0..(data.len() + 1).saturating_sub(self.len()))
+            .filter(move |&offset| self.matches(&data[offset..offset + self.len()]))
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        self.bytes
+            .iter()
+            .zip(data)
+            .all(|(pattern, byte)| pattern.map(|p| p == *byte).unwrap_or(true))
+    }
+}
+
+/// A chunk of memory read by [`scan_range`].
+pub struct ScanChunk<'a> {
+    pub addr: u64,
+    pub data: &'a [u8],
+    /// Number of bytes at the start of the chunk which are not part of the next chunk
+    pub owned: usize,
+    pages: Vec<bool>,
+}
+
+impl ScanChunk<'_> {
+    /// Returns true if all pages of `offset..offset + len` could be read.
+    pub fn is_readable(&self, offset: usize, len: usize) -> bool {
+        let first = page_index(self.addr, offset);
+        let last = page_index(self.addr, offset + len.max(1) - 1);
+        self.pages[first..=last].iter().all(|valid| *valid)
+    }
+}
+
+/// Splits `len` bytes into chunks of `chunk_size` bytes which are extended by up to `overlap` bytes.
+///
+/// Returns the offset, the number of owned bytes and the length of each chunk.
+fn chunks(len: u64, chunk_size: u64, overlap: usize) -> impl Iterator<Item = (u64, u64, usize)> {
+    (0u64..)
+        .map(move |idx| idx * chunk_size)
+        .take_while(move |&offset| offset < len)
+        .map(move |offset| {
+            let owned = min(chunk_size, len - offset);
+            let chunk_len = min(owned + overlap as u64, len - offset) as usize;
+            (offset, owned, chunk_len)
+        })
+}
+
+/// Reads `addr..addr + len` in chunks and passes each chunk to `f` until it returns false.
+///
+/// Consecutive chunks overlap by `overlap` bytes so values crossing a chunk boundary are found,
+/// only offsets below [`ScanChunk::owned`] should be reported to avoid duplicates.
+/// Unreadable pages are zero-filled, see [`ScanChunk::is_readable`].
+///
+/// Returns false if `f` stopped the scan.
+pub fn scan_range<F>(
+    virt_mem: &mut dyn VirtualMemory,
+    addr: u64,
+    len: u64,
+    overlap: usize,
+    mut f: F,
+) -> Result<bool>
+where
+    F: FnMut(&ScanChunk) -> bool,
+{
+    addr.checked_add(len).ok_or_else(|| {
+        Error::InvalidArgument(format!(
+            "range of {:x} bytes at {:x} overflows the address space",
+            len, addr
+        ))
+    })?;
+
+    let mut buf = vec![];
+    for (offset, owned, chunk_len) in chunks(len, SCAN_CHUNK_SIZE, overlap) {
+        let chunk_addr = addr + offset;
+
+        buf.resize(chunk_len, 0);
+        let pages = match virt_mem.virt_read_raw_into(chunk_addr.into(), &mut buf) {
+            Ok(_) => vec![true; split_pages(chunk_addr, chunk_len).count()],
+            // fall back to reading page by page so unmapped pages can be skipped
+            Err(_) => read_pages(chunk_addr, &mut buf, |addr, page| {
+                virt_mem.virt_read_raw_into(addr.into(), page).is_ok()
+            }),
+        };
+
+        let chunk = ScanChunk {
+            addr: chunk_addr,
+            data: &buf,
+            owned: owned as usize,
+            pages,
+        };
+        if !f(&chunk) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Returns the module containing the given address.
+pub fn module_at(modules: &[Win32ModuleInfo], addr: u64) -> Option<&Win32ModuleInfo> {
+    modules
+        .iter()
+        .find(|m| addr >= m.base.as_u64() && addr < m.base.as_u64() + m.size as u64)
+}
+
+/// Returns the module with the given name.
+pub fn find_module<'a>(modules: &'a [Win32ModuleInfo], name: &str) -> Result<&'a Win32ModuleInfo> {
+    modules
+        .iter()
+        .find(|m| m.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| Error::NotFound(format!("module {} not found", name)))
+}
+
+/// Scans the modules or virtual ranges of a process for a byte pattern.
+pub async fn pattern(msg: &ScanPatternRequest) -> Result<ScanPatternResponse> {
+    let pattern = Pattern::parse(&msg.pattern, &msg.mask)?;
+    let max_matches = if msg.max_matches == 0 {
+        DEFAULT_MAX_MATCHES
+    } else {
+        msg.max_matches as usize
+    };
+
+    let mut conn = lock_connection(&msg.conn_id).await?;
+    let kernel = conn.kernel_mut()?;
+
+    block_in_place(|| {
+        let proc_info = select_process(kernel, &msg.process)?;
+        let modules = kernel.module_list(&proc_info)?;
+
+        // collect the ranges to be scanned
+        let mut ranges = vec![];
+        for range in msg.ranges.iter() {
+            let base = if range.module.is_empty() {
+                0
+            } else {
+                find_module(&modules, &range.module)?.base.as_u64()
+            };
+            let addr = base.checked_add(range.addr).ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "address {:x} overflows the base address {:x}",
+                    range.addr, base
+                ))
+            })?;
+            ranges.push((addr, range.len));
+        }
+        for name in msg.modules.iter() {
+            let module = find_module(&modules, name)?;
+            ranges.push((module.base.as_u64(), module.size as u64));
+        }
+        if ranges.is_empty() {
+            ranges.extend(modules.iter().map(|m| (m.base.as_u64(), m.size as u64)));
+        }
+
+        info!(
+            "scanning {} ranges of process {} ({}) for pattern {}",
+            ranges.len(),
+            proc_info.name,
+            proc_info.pid,
+            msg.pattern
+        );
+
+        let mut virt_mem = kernel.virt_mem(&proc_info)?;
+
+        // one more match than requested is collected to tell whether the result is truncated
+        let mut matches = vec![];
+        for (addr, len) in ranges.into_iter() {
+            let complete = scan_range(virt_mem.as_mut(), addr, len, pattern.len() - 1, |chunk| {
+                for offset in pattern.find_all(chunk.data) {
+                    if offset >= chunk.owned {
+                        break;
+                    }
+                    if !chunk.is_readable(offset, pattern.len()) {
+                        continue;
+                    }
+
+                    matches.push(chunk.addr + offset as u64);
+                    if matches.len() > max_matches {
+                        return false;
+                    }
+                }
+                true
+            })?;
+            if !complete {
+                break;
+            }
+        }
+
+        let truncated = matches.len() > max_matches;
+        matches.truncate(max_matches);
+
+        Ok(ScanPatternResponse {
+            matches: matches
+                .into_iter()
+                .map(|addr| match module_at(&modules, addr) {
+                    Some(module) => PatternMatch {
+                        addr,
+                        module: module.name.clone(),
+                        offset: addr - module.base.as_u64(),
+                    },
+                    None => PatternMatch {
+                        addr,
+                        module: String::new(),
+                        offset: 0,
+                    },
+                })
+                .collect(),
+            truncated,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pattern() {
+        let pattern = Pattern::parse("48 8B 05 ?? ? 48", "").unwrap();
+        assert_eq!(
+            pattern.bytes,
+            vec![Some(0x48), Some(0x8b), Some(0x05), None, None, Some(0x48)]
+        );
+    }
+
+    #[test]
+    fn parse_packed_pattern() {
+        let pattern = Pattern::parse("488B05????48", "").unwrap();
+        assert_eq!(
+            pattern.bytes,
+            Pattern::parse("48 8B 05 ?? ?? 48", "").unwrap().bytes
+        );
+    }
+
+    #[test]
+    fn parse_mask() {
+        let pattern = Pattern::parse("48 8B 05 00", "xx?x").unwrap();
+        assert_eq!(
+            pattern.bytes,
+            vec![Some(0x48), Some(0x8b), None, Some(0x00)]
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for (pattern, mask) in [
+            ("", ""),
+            ("4", ""),
+            ("488", ""),
+            ("GG", ""),
+            ("?? ??", ""),
+            ("48 8B", "x"),
+            ("48 ??", "xx"),
+            ("48 8B", "xy"),
+            ("48 8B", "??"),
+        ]
+        .iter()
+        {
+            assert!(
+                Pattern::parse(pattern, mask).is_err(),
+                "{} {}",
+                pattern,
+                mask
+            );
+        }
+    }
+
+    #[test]
+    fn find_all_matches() {
+        let pattern = Pattern::parse("AA ?? AA", "").unwrap();
+        let data = [0xaa, 0x00, 0xaa, 0x11, 0xaa, 0xbb, 0xaa];
+        assert_eq!(pattern.find_all(&data).collect::<Vec<_>>(), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn find_all_short_data() {
+        let pattern = Pattern::parse("AA BB CC", "").unwrap();
+        assert_eq!(pattern.find_all(&[0xaa, 0xbb]).count(), 0);
+        assert_eq!(pattern.find_all(&[]).count(), 0);
+    }
+
+    #[test]
+    fn chunks_overlap() {
+        assert_eq!(
+            chunks(10, 4, 2).collect::<Vec<_>>(),
+            vec![(0, 4, 6), (4, 4, 6), (8, 2, 2)]
+        );
+        assert_eq!(
+            chunks(8, 4, 0).collect::<Vec<_>>(),
+            vec![(0, 4, 4), (4, 4, 4)]
+        );
+        assert_eq!(chunks(0, 4, 2).count(), 0);
+    }
+
+    #[test]
+    fn chunks_cover_range() {
+        for len in 0..32 {
+            for overlap in 0..8 {
+                let mut next = 0;
+                for (offset, owned, chunk_len) in chunks(len, 4, overlap) {
+                    assert_eq!(offset, next);
+                    assert_eq!(chunk_len as u64, min(owned + overlap as u64, len - offset));
+                    next += owned;
+                }
+                assert_eq!(next, len);
+            }
+        }
+    }
+}
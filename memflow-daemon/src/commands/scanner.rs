@@ -0,0 +1,347 @@
+use log::{info, warn};
+
+use super::process::{ensure_running, select_process};
+use super::scan::{module_at, scan_range};
+use crate::error::{Error, Result};
+use crate::scanner::{self, Candidates, ScanCondition, MAX_SESSION_RESULTS};
+use crate::state::{lock_connection, new_uuid, STATE};
+
+use memflow::VirtualMemory;
+use memflow_daemon::pages::PAGE_SIZE;
+use memflow_win32::win32::Win32ProcessInfo;
+
+use crate::memflow_rpc::{
+    CloseScanSessionRequest, CloseScanSessionResponse, CreateScanSessionRequest,
+    CreateScanSessionResponse, ListScanResultsRequest, ListScanResultsResponse,
+    ListScanSessionsRequest, ListScanSessionsResponse, NextScanRequest, NextScanResponse,
+    ScanCompare, ScanResult, ScanSession, ScanValueType,
+};
+
+use std::cmp::min;
+use tokio::task::block_in_place;
+
+/// Candidates closer than this are read as a single range by next scans.
+const NEXT_SCAN_GAP: u64 = 4 * PAGE_SIZE;
+
+/// Default number of results returned by [`results`].
+const DEFAULT_RESULT_LIMIT: u64 = 100;
+
+fn parse_value_type(value_type: i32) -> Result<ScanValueType> {
+    ScanValueType::from_i32(value_type)
+        .ok_or_else(|| Error::InvalidArgument(format!("invalid value type {}", value_type)))
+}
+
+fn parse_compare(compare: i32) -> Result<ScanCompare> {
+    ScanCompare::from_i32(compare)
+        .ok_or_else(|| Error::InvalidArgument(format!("invalid comparison {}", compare)))
+}
+
+/// Returns the first address above the user space of the process.
+pub fn user_space_end(proc_info: &Win32ProcessInfo) -> u64 {
+    if proc_info.sys_arch.bits() == 64 {
+        0x8000_0000_0000
+    } else {
+        0x8000_0000
+    }
+}
+
+/// Returns the sorted address and length of all mapped user space ranges of the process.
+pub fn user_regions(
+    virt_mem: &mut dyn VirtualMemory,
+    proc_info: &Win32ProcessInfo,
+) -> Vec<(u64, u64)> {
+    let end = user_space_end(proc_info);
+    virt_mem
+        .virt_page_map(0)
+        .into_iter()
+        .map(|(addr, size)| (addr.as_u64(), size as u64))
+        .filter(|(addr, _)| *addr < end)
+        .map(|(addr, size)| (addr, min(size, end - addr)))
+        .collect()
+}
+
+fn session_info(session: &scanner::ScanSession) -> ScanSession {
+    ScanSession {
+        id: session.id.clone(),
+        conn_id: session.conn_id.clone(),
+        pid: session.proc_info.pid,
+        value_type: session.value_type as i32,
+        scans: session.scans,
+        results: session.candidates.len() as u64,
+        truncated: session.truncated,
+    }
+}
+
+/// Scans all mapped user space pages of the process.
+///
+/// Returns the candidates and whether the scan stopped at [`MAX_SESSION_RESULTS`].
+fn first_scan(
+    virt_mem: &mut dyn VirtualMemory,
+    proc_info: &Win32ProcessInfo,
+    condition: &ScanCondition,
+    alignment: usize,
+) -> Result<(Candidates, bool)> {
+    let value_len = condition.value_len();
+
+    let mut candidates = Candidates::new(value_len);
+    let mut truncated = false;
+    for (addr, len) in user_regions(virt_mem, proc_info) {
+        let complete = scan_range(virt_mem, addr, len, value_len - 1, |chunk| {
+            // the offsets are aligned to the absolute address as chunks do not have to be aligned
+            let mut offset =
+                ((alignment as u64 - chunk.addr % alignment as u64) % alignment as u64) as usize;
+            while offset < chunk.owned && offset + value_len <= chunk.data.len() {
+                let value = &chunk.data[offset..offset + value_len];
+                if condition.matches(value, value) && chunk.is_readable(offset, value_len) {
+                    if candidates.len() >= MAX_SESSION_RESULTS {
+                        truncated = true;
+                        return false;
+                    }
+                    candidates.push(chunk.addr + offset as u64, value);
+                }
+                offset += alignment;
+            }
+            true
+        })?;
+        if !complete {
+            break;
+        }
+    }
+    Ok((candidates, truncated))
+}
+
+/// Reads the current values of all candidates and keeps the ones matching the condition.
+fn next_scan(
+    virt_mem: &mut dyn VirtualMemory,
+    candidates: &Candidates,
+    condition: &ScanCondition,
+) -> Result<Candidates> {
+    let value_len = candidates.value_len();
+    let mut next = Candidates::new(value_len);
+
+    let mut idx = 0;
+    while idx < candidates.len() {
+        // read nearby candidates as a single range
+        let start = candidates.addr(idx);
+        let mut end_idx = idx + 1;
+        while end_idx < candidates.len()
+            && candidates.addr(end_idx) < candidates.addr(end_idx - 1) + NEXT_SCAN_GAP
+        {
+            end_idx += 1;
+        }
+        let end = candidates.addr(end_idx - 1) + value_len as u64;
+
+        let mut i = idx;
+        scan_range(virt_mem, start, end - start, value_len - 1, |chunk| {
+            let chunk_end = chunk.addr + chunk.owned as u64;
+            while i < end_idx && candidates.addr(i) < chunk_end {
+                let offset = (candidates.addr(i) - chunk.addr) as usize;
+                let current = &chunk.data[offset..offset + value_len];
+                if chunk.is_readable(offset, value_len)
+                    && condition.matches(candidates.value(i), current)
+                {
+                    next.push(candidates.addr(i), current);
+                }
+                i += 1;
+            }
+            true
+        })?;
+
+        idx = end_idx;
+    }
+    Ok(next)
+}
+
+/// Creates a new scan session and performs the first scan.
+pub async fn create(msg: &CreateScanSessionRequest) -> Result<CreateScanSessionResponse> {
+    let value_type = parse_value_type(msg.value_type)?;
+    let condition = ScanCondition::new(
+        value_type,
+        parse_compare(msg.compare)?,
+        &msg.value,
+        &msg.upper,
+        true,
+    )?;
+    let alignment = if msg.alignment == 0 {
+        scanner::value_size(value_type).unwrap_or(1)
+    } else {
+        msg.alignment as usize
+    };
+
+    // fail early instead of discarding the result of a full scan
+    STATE.lock().await.scan_session_check(&msg.conn_id)?;
+
+    let (conn_id, proc_info, candidates, truncated) = {
+        let mut conn = lock_connection(&msg.conn_id).await?;
+        let conn_id = conn.id.clone();
+        let kernel = conn.kernel_mut()?;
+
+        block_in_place(|| -> Result<_> {
+            let proc_info = select_process(kernel, &msg.process)?;
+            let mut virt_mem = kernel.virt_mem(&proc_info)?;
+            let (candidates, truncated) =
+                first_scan(virt_mem.as_mut(), &proc_info, &condition, alignment)?;
+            Ok((conn_id, proc_info, candidates, truncated))
+        })?
+    };
+
+    let session = scanner::ScanSession {
+        id: new_uuid(),
+        conn_id,
+        proc_info,
+        value_type,
+        scans: 1,
+        truncated,
+        candidates,
+    };
+
+    info!(
+        "scan session {} for process {} ({}) created with {} results",
+        session.id,
+        session.proc_info.name,
+        session.proc_info.pid,
+        session.candidates.len()
+    );
+
+    let info = session_info(&session);
+    STATE.lock().await.scan_session_add(session)?;
+
+    Ok(CreateScanSessionResponse {
+        session: Some(info),
+    })
+}
+
+/// Narrows the candidates of a session down to the ones matching the new condition.
+pub async fn next(msg: &NextScanRequest) -> Result<NextScanResponse> {
+    let session = STATE.lock().await.scan_session(&msg.id)?;
+    let mut session = session.lock().await;
+
+    let condition = ScanCondition::new(
+        session.value_type,
+        parse_compare(msg.compare)?,
+        &msg.value,
+        &msg.upper,
+        false,
+    )?;
+    condition.check_value_len(session.candidates.value_len())?;
+
+    let mut conn = lock_connection(&session.conn_id).await?;
+    let kernel = conn.kernel_mut()?;
+
+    let candidates = block_in_place(|| -> Result<_> {
+        ensure_running(kernel, &session.proc_info)?;
+        let mut virt_mem = kernel.virt_mem(&session.proc_info)?;
+        next_scan(virt_mem.as_mut(), &session.candidates, &condition)
+    })?;
+
+    info!(
+        "scan session {} narrowed down from {} to {} results",
+        session.id,
+        session.candidates.len(),
+        candidates.len()
+    );
+
+    session.candidates = candidates;
+    session.scans += 1;
+
+    Ok(NextScanResponse {
+        session: Some(session_info(&session)),
+    })
+}
+
+pub async fn ls(_msg: &ListScanSessionsRequest) -> Result<ListScanSessionsResponse> {
+    // the sessions are locked after releasing the state as a scan might be running
+    let sessions = STATE
+        .lock()
+        .await
+        .scan_sessions
+        .values()
+        .map(|handle| handle.session.clone())
+        .collect::<Vec<_>>();
+
+    let mut infos = vec![];
+    for session in sessions.iter() {
+        infos.push(session_info(&*session.lock().await));
+    }
+
+    Ok(ListScanSessionsResponse { sessions: infos })
+}
+
+/// Lists a page of the candidates of a session.
+pub async fn results(msg: &ListScanResultsRequest) -> Result<ListScanResultsResponse> {
+    let session = STATE.lock().await.scan_session(&msg.id)?;
+    let session = session.lock().await;
+
+    let limit = if msg.limit == 0 {
+        DEFAULT_RESULT_LIMIT
+    } else {
+        msg.limit
+    };
+    let total = session.candidates.len();
+    let start = min(msg.offset, total as u64) as usize;
+    let end = min(msg.offset.saturating_add(limit), total as u64) as usize;
+
+    let mut conn = lock_connection(&session.conn_id).await?;
+    let kernel = conn.kernel_mut()?;
+
+    let results = block_in_place(|| -> Result<_> {
+        // the stored values can still be listed after the process exited
+        let modules = match ensure_running(kernel, &session.proc_info)
+            .and_then(|_| kernel.module_list(&session.proc_info))
+        {
+            Ok(modules) => modules,
+            Err(err) if !msg.refresh => {
+                warn!(
+                    "unable to list the modules of scan session {}: {}",
+                    session.id, err
+                );
+                vec![]
+            }
+            Err(err) => return Err(err),
+        };
+
+        let values = if msg.refresh {
+            let mut virt_mem = kernel.virt_mem(&session.proc_info)?;
+            (start..end)
+                .map(|idx| {
+                    let mut buf = vec![0u8; session.candidates.value_len()];
+                    virt_mem
+                        .virt_read_raw_into(session.candidates.addr(idx).into(), &mut buf)
+                        .ok()
+                        .map(|_| buf)
+                })
+                .collect::<Vec<_>>()
+        } else {
+            (start..end)
+                .map(|idx| Some(session.candidates.value(idx).to_vec()))
+                .collect()
+        };
+
+        Ok((start..end)
+            .zip(values.into_iter())
+            .map(|(idx, value)| {
+                let addr = session.candidates.addr(idx);
+                let module = module_at(&modules, addr);
+                ScanResult {
+                    addr,
+                    value: value
+                        .map(|value| scanner::format(session.value_type, &value))
+                        .unwrap_or_default(),
+                    module: module.map(|m| m.name.clone()).unwrap_or_default(),
+                    offset: module.map(|m| addr - m.base.as_u64()).unwrap_or_default(),
+                }
+            })
+            .collect())
+    })?;
+
+    Ok(ListScanResultsResponse {
+        results,
+        total: total as u64,
+    })
+}
+
+pub async fn close(msg: &CloseScanSessionRequest) -> Result<CloseScanSessionResponse> {
+    STATE.lock().await.scan_session_remove(&msg.id)?;
+    info!("scan session {} closed", msg.id);
+    Ok(CloseScanSessionResponse {})
+}
@@ -0,0 +1,132 @@
+use super::process::select_process;
+use crate::error::{Error, Result};
+use crate::state::lock_connection;
+
+use memflow::architecture::x86::x64;
+use memflow::{DirectTranslate, PhysicalMemory, VirtualTranslate};
+
+use crate::memflow_rpc::{
+    PageTableEntry, PageTableLevel, TranslateAddressesRequest, TranslateAddressesResponse,
+    Translation,
+};
+
+use tokio::task::block_in_place;
+
+/// Bits of a page table entry holding the physical address of the next table or page.
+const ADDR_MASK: u64 = 0x000f_ffff_ffff_f000;
+
+/// The levels of the x64 page tables and the shift of their index in the virtual address.
+const LEVELS: [(PageTableLevel, u32); 4] = [
+    (PageTableLevel::Pml4, 39),
+    (PageTableLevel::Pdpt, 30),
+    (PageTableLevel::Pd, 21),
+    (PageTableLevel::Pt, 12),
+];
+
+const PRESENT: u64 = 1;
+const WRITABLE: u64 = 1 << 1;
+const USER: u64 = 1 << 2;
+const ACCESSED: u64 = 1 << 5;
+const DIRTY: u64 = 1 << 6;
+const LARGE_PAGE: u64 = 1 << 7;
+const NO_EXECUTE: u64 = 1 << 63;
+
+fn read_entry(phys_mem: &mut dyn PhysicalMemory, addr: u64) -> Option<u64> {
+    let mut buf = [0u8; 8];
+    phys_mem.phys_read_raw_into(addr.into(), &mut buf).ok()?;
+    Some(u64::from_le_bytes(buf))
+}
+
+/// Returns the entries of the x64 page tables at `dtb` which are used to translate a virtual address.
+///
+/// The walk is only informational, the translation itself is done by memflow.
+fn walk(phys_mem: &mut dyn PhysicalMemory, dtb: u64, virt_addr: u64) -> Vec<PageTableEntry> {
+    let mut walk = vec![];
+
+    let mut table = dtb & ADDR_MASK;
+    for (level, shift) in LEVELS.iter() {
+        let addr = table + ((virt_addr >> shift) & 0x1ff) * 8;
+        let value = read_entry(phys_mem, addr);
+        let entry = value.unwrap_or_default();
+
+        // large pages can only be mapped by the pdpt and the pd
+        let large_page =
+            matches!(level, PageTableLevel::Pdpt | PageTableLevel::Pd) && entry & LARGE_PAGE != 0;
+        walk.push(PageTableEntry {
+            level: *level as i32,
+            addr,
+            value: entry,
+            readable: value.is_some(),
+            present: entry & PRESENT != 0,
+            writable: entry & WRITABLE != 0,
+            user: entry & USER != 0,
+            accessed: entry & ACCESSED != 0,
+            dirty: entry & DIRTY != 0,
+            large_page,
+            no_execute: entry & NO_EXECUTE != 0,
+        });
+
+        if entry & PRESENT == 0 || large_page || *level == PageTableLevel::Pt {
+            break;
+        }
+
+        table = entry & ADDR_MASK;
+    }
+
+    walk
+}
+
+fn ensure_x64(bits: u8) -> Result<()> {
+    if bits == 64 {
+        Ok(())
+    } else {
+        Err(Error::FailedPrecondition(
+            "address translation is only supported for x64 page tables".to_string(),
+        ))
+    }
+}
+
+/// Translates virtual addresses of a process or an explicit page table base to physical addresses.
+pub async fn translate(msg: &TranslateAddressesRequest) -> Result<TranslateAddressesResponse> {
+    let mut conn = lock_connection(&msg.conn_id).await?;
+
+    block_in_place(|| {
+        let dtb = if msg.dtb != 0 {
+            // without an os the architecture is unknown and the page tables are assumed to be x64
+            if let Some(arch) = conn.kernel.as_ref().and_then(|kernel| kernel.arch()) {
+                ensure_x64(arch.bits())?;
+            }
+            msg.dtb
+        } else {
+            let kernel = conn.kernel_mut()?;
+            let proc_info = select_process(kernel, &msg.process)?;
+            ensure_x64(proc_info.sys_arch.bits())?;
+            proc_info.dtb.as_u64()
+        };
+
+        let translator = x64::new_translator(dtb.into());
+        let mut vat = DirectTranslate::new();
+        let phys_mem = conn.phys_mem();
+        let translations = msg
+            .addrs
+            .iter()
+            .map(|addr| {
+                let mut translation = Translation {
+                    virt_addr: *addr,
+                    ..Default::default()
+                };
+                if let Ok(phys_addr) = vat.virt_to_phys(phys_mem, &translator, (*addr).into()) {
+                    translation.phys_addr = phys_addr.as_u64();
+                    translation.page_size = phys_addr.page_size() as u64;
+                    translation.valid = true;
+                }
+                if msg.walk {
+                    translation.walk = walk(phys_mem, dtb, *addr);
+                }
+                translation
+            })
+            .collect();
+
+        Ok(TranslateAddressesResponse { translations })
+    })
+}
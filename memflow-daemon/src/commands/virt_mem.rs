@@ -1,95 +1,188 @@
+use super::phys_mem::read_status;
+use super::process::{select_process, selector_or_pid};
+use crate::audit::{self, AuditWrite};
 use crate::error::{Error, Result};
-use crate::state::{KernelHandle, STATE};
+use crate::state::{lock_connection, KernelHandle};
 
+use memflow::error::PartialError;
 use memflow::{VirtualMemory, VirtualReadData, VirtualWriteData};
+use memflow_daemon::pages::read_pages;
+use memflow_win32::Win32ProcessInfo;
 
 use crate::memflow_rpc::{
     ReadVirtualMemoryEntryResponse, ReadVirtualMemoryRequest, ReadVirtualMemoryResponse,
     WriteVirtualMemoryRequest, WriteVirtualMemoryResponse,
 };
 
+use tokio::task::block_in_place;
+
+/// Returns the address virtual addresses of a request are relative to.
+///
+/// Addresses are relative to the given module if one is set,
+/// to the main image if `base_offsets` is set or absolute otherwise.
+pub fn base_address(
+    kernel: &mut KernelHandle,
+    proc_info: &Win32ProcessInfo,
+    base_offsets: bool,
+    module: &str,
+) -> Result<u64> {
+    // We need u64 here, because memflow::Address does not implement Add
+    if !module.is_empty() {
+        kernel
+            .module_list(proc_info)?
+            .into_iter()
+            .find(|m| m.name.eq_ignore_ascii_case(module))
+            .map(|m| m.base.as_u64())
+            .ok_or_else(|| {
+                Error::NotFound(format!(
+                    "module {} not found in process {} ({})",
+                    module, proc_info.name, proc_info.pid
+                ))
+            })
+    } else if base_offsets {
+        Ok(proc_info.section_base.as_u64())
+    } else {
+        Ok(0)
+    }
+}
+
+/// Adds a client supplied address to the base address of the request.
+fn absolute_address(offset: u64, addr: u64) -> Result<u64> {
+    offset.checked_add(addr).ok_or_else(|| {
+        Error::InvalidArgument(format!(
+            "address {:x} overflows the base address {:x}",
+            addr, offset
+        ))
+    })
+}
+
 pub async fn read(msg: &ReadVirtualMemoryRequest) -> Result<ReadVirtualMemoryResponse> {
-    let mut state = STATE.lock().await;
-    if let Some(conn) = state.connection_mut(&msg.conn_id) {
-        match &mut conn.kernel {
-            KernelHandle::Win32(kernel) => {
-                // create [VirtualReadData]
-                let mut result_reads = Vec::new();
-                let mut read_data = Vec::new();
-                for read in msg.reads.iter() {
-                    result_reads.push(ReadVirtualMemoryEntryResponse {
-                        data: vec![0u8; read.len as usize],
-                    });
-                }
+    let mut conn = lock_connection(&msg.conn_id).await?;
 
-                let mut process = kernel.process_pid(msg.pid)?;
+    // create [VirtualReadData]
+    let mut result_reads = Vec::new();
+    for read in msg.reads.iter() {
+        result_reads.push(ReadVirtualMemoryEntryResponse {
+            data: vec![0u8; read.len as usize],
+            ..Default::default()
+        });
+    }
 
-                // We need u64 here, because memflow::Address does not implement Add
-                let offset = if msg.base_offsets {
-                    process.proc_info.section_base.as_u64()
-                } else {
-                    0
-                };
+    let kernel = conn.kernel_mut()?;
 
-                for read in msg.reads.iter().zip(result_reads.iter_mut()) {
-                    read_data.push(VirtualReadData(
-                        (offset + read.0.addr).into(),
-                        &mut read.1.data[..],
-                    ));
-                }
+    let base = block_in_place(|| -> Result<u64> {
+        let proc_info = select_process(kernel, &selector_or_pid(&msg.process, msg.pid))?;
 
-                process
-                    .virt_mem
-                    .virt_read_raw_list(&mut read_data.as_mut_slice())?;
+        let offset = base_address(kernel, &proc_info, msg.base_offsets, &msg.module)?;
+        let addrs = msg
+            .reads
+            .iter()
+            .map(|read| absolute_address(offset, read.addr))
+            .collect::<Result<Vec<_>>>()?;
 
-                Ok(ReadVirtualMemoryResponse {
-                    reads: result_reads,
-                })
+        let mut virt_mem = kernel.virt_mem(&proc_info)?;
+
+        let mut read_data = Vec::new();
+        for (addr, entry) in addrs.iter().zip(result_reads.iter_mut()) {
+            read_data.push(VirtualReadData((*addr).into(), &mut entry.data[..]));
+        }
+
+        match virt_mem.virt_read_raw_list(&mut read_data.as_mut_slice()) {
+            Ok(_) => (),
+            Err(PartialError::Error(err)) => return Err(Error::from(err)),
+            Err(_) => {
+                // retry each entry page by page so a single unmapped page does not discard the entire batch
+                for (addr, entry) in addrs.iter().zip(result_reads.iter_mut()) {
+                    let pages = read_pages(*addr, &mut entry.data, |addr, buf| {
+                        virt_mem.virt_read_raw_into(addr.into(), buf).is_ok()
+                    });
+                    let (status, valid_pages) = read_status(&pages);
+                    entry.set_status(status);
+                    entry.valid_pages = valid_pages;
+                }
             }
         }
-    } else {
-        Err(Error::Connector(format!(
-            "no connection with id {} found",
-            msg.conn_id
-        )))
-    }
+        Ok(offset)
+    })?;
+
+    Ok(ReadVirtualMemoryResponse {
+        reads: result_reads,
+        base,
+    })
 }
 
-pub async fn write(msg: &WriteVirtualMemoryRequest) -> Result<WriteVirtualMemoryResponse> {
-    let mut state = STATE.lock().await;
-    if let Some(conn) = state.connection_mut(&msg.conn_id) {
-        match &mut conn.kernel {
-            KernelHandle::Win32(kernel) => {
-                // create [VirtualWriteData]
-                let mut write_data = Vec::new();
-
-                let mut process = kernel.process_pid(msg.pid)?;
-
-                // We need u64 here, because memflow::Address does not implement Add
-                let offset = if msg.base_offsets {
-                    process.proc_info.section_base.as_u64()
-                } else {
-                    0
-                };
-
-                for write in msg.writes.iter() {
-                    write_data.push(VirtualWriteData(
-                        (offset + write.addr).into(),
-                        &write.data.as_slice(),
-                    ));
-                }
+pub async fn write(
+    msg: &WriteVirtualMemoryRequest,
+    peer: &str,
+) -> Result<WriteVirtualMemoryResponse> {
+    let mut conn = lock_connection(&msg.conn_id).await?;
+    let conn_id = conn.id.clone();
 
-                process
-                    .virt_mem
-                    .virt_write_raw_list(&write_data.as_slice())?;
+    // the original bytes are required for the journal, the audit log records them if possible
+    let journal = conn.journal.is_some();
 
-                Ok(WriteVirtualMemoryResponse {})
-            }
+    // create [VirtualWriteData]
+    let mut write_data = Vec::new();
+
+    let kernel = conn.kernel_mut()?;
+
+    let (proc_info, offset, addrs, old_data) = block_in_place(|| -> Result<_> {
+        let proc_info = select_process(kernel, &selector_or_pid(&msg.process, msg.pid))?;
+
+        let offset = base_address(kernel, &proc_info, msg.base_offsets, &msg.module)?;
+        let addrs = msg
+            .writes
+            .iter()
+            .map(|write| absolute_address(offset, write.addr))
+            .collect::<Result<Vec<_>>>()?;
+
+        for (addr, write) in addrs.iter().zip(msg.writes.iter()) {
+            write_data.push(VirtualWriteData((*addr).into(), &write.data.as_slice()));
+        }
+
+        let mut virt_mem = kernel.virt_mem(&proc_info)?;
+
+        let old_data = if journal || audit::record_data() {
+            addrs
+                .iter()
+                .zip(msg.writes.iter())
+                .map(|(addr, write)| {
+                    let mut buf = vec![0u8; write.data.len()];
+                    match virt_mem.virt_read_raw_into((*addr).into(), &mut buf) {
+                        Ok(_) => Ok(Some(buf)),
+                        Err(err) if journal => Err(err.into()),
+                        Err(_) => Ok(None),
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            vec![None; msg.writes.len()]
+        };
+
+        virt_mem.virt_write_raw_list(&write_data.as_slice())?;
+
+        Ok((proc_info, offset, addrs, old_data))
+    })?;
+
+    for ((addr, write), old) in addrs
+        .into_iter()
+        .zip(msg.writes.iter())
+        .zip(old_data.into_iter())
+    {
+        audit::record(AuditWrite {
+            source: "rpc",
+            peer,
+            conn_id: &conn_id,
+            pid: Some(proc_info.pid),
+            addr,
+            old: old.as_deref(),
+            new: &write.data,
+        });
+
+        if let (Some(journal), Some(old)) = (conn.journal.as_mut(), old) {
+            journal.record(Some(proc_info.clone()), addr, old);
         }
-    } else {
-        Err(Error::Connector(format!(
-            "no connection with id {} found",
-            msg.conn_id
-        )))
     }
+
+    Ok(WriteVirtualMemoryResponse { base: offset })
 }
@@ -5,5 +5,175 @@ pub struct Config {
     pub verbosity: Option<String>,
     pub pid_file: Option<String>,
     pub log_file: Option<String>,
+    /// Either a tcp address (e.g. `127.0.0.1:8000`) or a unix socket (e.g. `unix:///run/memflow.sock`).
     pub socket_addr: String,
+    /// Owner of the unix socket
+    pub socket_user: Option<String>,
+    /// Group of the unix socket, e.g. `memflow`
+    pub socket_group: Option<String>,
+    /// Octal permissions of the unix socket, e.g. `0660`
+    pub socket_mode: Option<String>,
+    /// Enables TLS on the daemon socket.
+    /// Clients have to connect via `https://` and provide the ca certificate of the daemon.
+    pub tls: Option<TlsConfig>,
+    /// Tokens which are accepted by the daemon, authentication is disabled if empty
+    #[serde(default)]
+    pub auth_tokens: Vec<String>,
+    /// Access policy of the daemon.
+    /// If no policy is set all clients with a valid token (or all clients if no tokens are configured) are admins.
+    pub policy: Option<PolicyConfig>,
+    /// Records all memory writes if set
+    pub audit_log: Option<AuditLogConfig>,
+    /// Connections which are opened when the daemon starts
+    #[serde(default)]
+    pub connections: Vec<ConnectionConfig>,
+}
+
+/// TLS settings of the daemon.
+///
+/// The daemon requires `cert` and `key`, if `ca_cert` is set only clients
+/// presenting a certificate signed by it are accepted.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// Path to the pem encoded certificate
+    pub cert: Option<String>,
+    /// Path to the pem encoded private key
+    pub key: Option<String>,
+    /// Path to the pem encoded ca certificate used to verify clients
+    pub ca_cert: Option<String>,
+}
+
+/// Settings of the append-only audit log.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AuditLogConfig {
+    /// Path of the JSON lines file
+    pub path: String,
+    /// Also records the previous and the written bytes of each write
+    #[serde(default)]
+    pub record_data: bool,
+}
+
+/// Roles which can be granted to clients, each role includes the permissions of the previous ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    /// Listing and reading memory
+    ReadOnly,
+    /// Writing memory
+    ReadWrite,
+    /// Managing connections, file systems and gdb stubs
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::ReadOnly => "read-only",
+            Role::ReadWrite => "read-write",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+/// Binds roles to client credentials.
+///
+/// If multiple bindings match a client the highest role is granted.
+/// Tokens from `auth_tokens` are treated as admin tokens.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PolicyConfig {
+    /// Role of clients without any matching binding, these clients are rejected if not set
+    pub default_role: Option<Role>,
+    pub tokens: Vec<TokenBinding>,
+    /// Bindings for peers connected via the unix socket
+    pub uids: Vec<UidBinding>,
+    /// Role of clients which presented a certificate signed by `tls.ca_cert`
+    pub client_cert_role: Option<Role>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenBinding {
+    pub token: String,
+    pub role: Role,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct UidBinding {
+    pub uid: u32,
+    pub role: Role,
+}
+
+/// A persistent connection definition.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ConnectionConfig {
+    /// Name of the connector
+    pub name: String,
+    pub args: Option<String>,
+    pub alias: Option<String>,
+    /// The os layer to use on top of the connector, defaults to "win32"
+    pub os: Option<String>,
+    pub cache: Option<CacheConfigOptions>,
+    /// Records the original bytes of all writes so they can be reverted
+    #[serde(default)]
+    pub journal: bool,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    /// File systems which are mounted once the connection has been opened
+    #[serde(default)]
+    pub fuse: Vec<FuseConfig>,
+    /// Gdb stubs which are spawned once the connection has been opened
+    #[serde(default)]
+    pub gdb: Vec<GdbConfig>,
+}
+
+/// Cache options of a persistent connection.
+/// Zero values will be replaced by the daemon defaults.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CacheConfigOptions {
+    pub disabled: bool,
+    pub page_cache_size: u64,
+    pub page_cache_validity_ms: u64,
+    pub tlb_size: u64,
+    pub tlb_validity_ms: u64,
+}
+
+/// Controls how often opening a persistent connection is retried if the target is not available yet.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Delay before the first retry, doubled after every failed attempt.
+    /// Values below 100ms are raised to 100ms.
+    pub initial_delay_ms: u64,
+    /// Upper bound for the delay, raised to `initial_delay_ms` if it is lower
+    pub max_delay_ms: u64,
+    /// Maximum number of attempts, 0 retries forever
+    pub max_attempts: u32,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 1000,
+            max_delay_ms: 60000,
+            max_attempts: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct FuseConfig {
+    pub mount_point: String,
+    #[serde(default)]
+    pub uid: u32,
+    #[serde(default)]
+    pub gid: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GdbConfig {
+    /// A pid, an exact process name or a name pattern containing '*' or '?'
+    pub process: String,
+    pub addr: String,
 }
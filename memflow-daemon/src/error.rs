@@ -28,6 +28,18 @@ pub enum Error {
     GDB,
     /// Connector error
     Connector(String),
+    /// A connection, process, module or file was not found
+    NotFound(String),
+    /// The request contains invalid arguments
+    InvalidArgument(String),
+    /// The object to be created already exists
+    AlreadyExists(String),
+    /// The connection is not in a state required for the operation, e.g. no os has been attached
+    FailedPrecondition(String),
+    /// The target is not available, e.g. the connector could not be created
+    Unavailable(String),
+    /// A limit of the daemon has been reached, e.g. the maximum number of scan sessions
+    ResourceExhausted(String),
     /// memflow core error
     Core(memflow::error::Error),
     /// memflow win32 error
@@ -104,6 +116,12 @@ impl Error {
             Error::SocketWrite => ("socket write error", None),
             Error::GDB => ("gdb stub error", None),
             Error::Connector(e) => ("connector error", Some(e)),
+            Error::NotFound(e) => ("not found", Some(e)),
+            Error::InvalidArgument(e) => ("invalid argument", Some(e)),
+            Error::AlreadyExists(e) => ("already exists", Some(e)),
+            Error::FailedPrecondition(e) => ("failed precondition", Some(e)),
+            Error::Unavailable(e) => ("unavailable", Some(e)),
+            Error::ResourceExhausted(e) => ("resource exhausted", Some(e)),
             Error::Core(e) => ("memflow core error", Some(e.to_str())),
             Error::Win32(e) => ("memflow win32 error", Some(e.to_str())),
             Error::PartialError(e) => ("memflow partial error", Some(e.to_str())),
@@ -113,6 +131,32 @@ impl Error {
         }
     }
 
+    /// Returns a stable identifier of the error kind which is sent to clients.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Other(_) => "other",
+            Error::IO => "io",
+            Error::Serialize => "serialize",
+            Error::Deserialize => "deserialize",
+            Error::SocketRead => "socket_read",
+            Error::SocketWrite => "socket_write",
+            Error::GDB => "gdb",
+            Error::Connector(_) => "connector",
+            Error::NotFound(_) => "not_found",
+            Error::InvalidArgument(_) => "invalid_argument",
+            Error::AlreadyExists(_) => "already_exists",
+            Error::FailedPrecondition(_) => "failed_precondition",
+            Error::Unavailable(_) => "unavailable",
+            Error::ResourceExhausted(_) => "resource_exhausted",
+            Error::Core(_) => "memflow_core",
+            Error::Win32(_) => "memflow_win32",
+            Error::PartialError(_) => "memflow_partial",
+            Error::PE(_) => "pe",
+            Error::TonicTransport(_) => "transport",
+            Error::TonicStatus(_) => "status",
+        }
+    }
+
     /// Returns a simple string representation of the error.
     pub fn to_str(&self) -> &str {
         self.to_str_pair().0
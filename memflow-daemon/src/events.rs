@@ -0,0 +1,55 @@
+use crate::memflow_rpc::{Event, EventKind};
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lazy_static::lazy_static;
+use tokio::sync::broadcast;
+
+/// Number of events which are buffered for slow subscribers.
+const EVENT_QUEUE_SIZE: usize = 256;
+
+lazy_static! {
+    static ref EVENTS: broadcast::Sender<Event> = broadcast::channel(EVENT_QUEUE_SIZE).0;
+}
+
+/// Sends an event to all subscribers, the event is dropped if there are none.
+pub fn emit(kind: EventKind, conn_id: &str, id: &str, message: &str) {
+    send(new_event(kind, conn_id, id, message));
+}
+
+/// Sends the new refcount of a connection to all subscribers.
+pub fn emit_refcount(conn_id: &str, refcount: usize) {
+    send(Event {
+        refcount: refcount as u64,
+        ..new_event(EventKind::ConnectionRefcount, conn_id, "", "")
+    });
+}
+
+/// Sends an error of a background operation to all subscribers.
+pub fn emit_error(conn_id: &str, id: &str, message: &str) {
+    emit(EventKind::Error, conn_id, id, message);
+}
+
+/// Returns a receiver for all events emitted from now on.
+pub fn subscribe() -> broadcast::Receiver<Event> {
+    EVENTS.subscribe()
+}
+
+pub fn new_event(kind: EventKind, conn_id: &str, id: &str, message: &str) -> Event {
+    Event {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default(),
+        kind: kind as i32,
+        conn_id: conn_id.to_string(),
+        id: id.to_string(),
+        message: message.to_string(),
+        refcount: 0,
+    }
+}
+
+fn send(event: Event) {
+    // sending only fails if nobody is subscribed
+    EVENTS.send(event).ok();
+}
@@ -0,0 +1,76 @@
+use log::warn;
+use memflow_win32::win32::Win32ProcessInfo;
+
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Maximum number of bytes kept by a journal, the oldest entries are dropped once it is exceeded.
+pub const MAX_JOURNAL_SIZE: usize = 0x400_0000;
+
+/// Maximum number of entries kept by a journal.
+pub const MAX_JOURNAL_ENTRIES: usize = 100_000;
+
+/// The bytes which have been overwritten by a single write.
+pub struct JournalEntry {
+    /// Unix timestamp in milliseconds
+    pub timestamp: u64,
+    /// The process which has been written to, not set for physical writes
+    pub proc_info: Option<Win32ProcessInfo>,
+    pub addr: u64,
+    pub data: Vec<u8>,
+}
+
+impl JournalEntry {
+    pub fn pid(&self) -> Option<u32> {
+        self.proc_info.as_ref().map(|p| p.pid)
+    }
+}
+
+/// Journal of the original bytes of all writes to a connection.
+///
+/// Reverting restores the entries in reverse order so overlapping writes
+/// end up with the bytes from before the first write.
+#[derive(Default)]
+pub struct WriteJournal {
+    entries: VecDeque<JournalEntry>,
+    size: usize,
+}
+
+impl WriteJournal {
+    pub fn record(&mut self, proc_info: Option<Win32ProcessInfo>, addr: u64, data: Vec<u8>) {
+        self.size += data.len();
+        self.entries.push_back(JournalEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            proc_info,
+            addr,
+            data,
+        });
+
+        while self.size > MAX_JOURNAL_SIZE || self.entries.len() > MAX_JOURNAL_ENTRIES {
+            let dropped = match self.entries.pop_front() {
+                Some(entry) => entry,
+                None => break,
+            };
+            self.size -= dropped.data.len();
+            warn!(
+                "write journal is full, dropped the write at {:x} from {}",
+                dropped.addr, dropped.timestamp
+            );
+        }
+    }
+
+    /// Removes all entries recorded at or after `since` and returns them, latest first.
+    pub fn take_since(&mut self, since: u64) -> Vec<JournalEntry> {
+        let idx = self
+            .entries
+            .iter()
+            .position(|e| e.timestamp >= since)
+            .unwrap_or_else(|| self.entries.len());
+        let entries = self.entries.drain(idx..).rev().collect::<Vec<_>>();
+        self.size -= entries.iter().map(|e| e.data.len()).sum::<usize>();
+        entries
+    }
+}
@@ -3,6 +3,8 @@ pub use config::*;
 
 pub mod error;
 
+pub mod pages;
+
 pub mod memflow_rpc {
     tonic::include_proto!("memflow_rpc");
 }
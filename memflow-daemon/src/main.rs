@@ -1,21 +1,36 @@
-use std::{ffi::CString, fs::File};
+use std::{ffi::CString, fs::File, pin::Pin};
 
 use clap::{crate_authors, crate_description, crate_name, crate_version, App, Arg};
-use log::LevelFilter;
+use futures::{Stream, StreamExt};
+use log::{error, info, warn, LevelFilter};
 use memflow_daemon::Config;
 use memflow_rpc::memflow_server::{Memflow, MemflowServer};
 use memflow_rpc::{
-    CloseConnectionRequest, CloseConnectionResponse, FuseListRequest, FuseListResponse,
-    FuseMountRequest, FuseMountResponse, GdbAttachRequest, GdbAttachResponse, GdbListRequest,
-    GdbListResponse, ListConnectionsRequest, ListConnectionsResponse, ListProcessesRequest,
-    ListProcessesResponse, NewConnectionRequest, NewConnectionResponse,
-    PhysicalMemoryMetadataRequest, PhysicalMemoryMetadataResponse, ProcessInfoRequest,
-    ProcessInfoResponse, ReadPhysicalMemoryRequest, ReadPhysicalMemoryResponse,
-    ReadVirtualMemoryRequest, ReadVirtualMemoryResponse, WritePhysicalMemoryRequest,
-    WritePhysicalMemoryResponse, WriteVirtualMemoryRequest, WriteVirtualMemoryResponse,
+    AttachOsRequest, AttachOsResponse, CloseConnectionRequest, CloseConnectionResponse,
+    CloseScanSessionRequest, CloseScanSessionResponse, ConnectionCacheRequest,
+    ConnectionCacheResponse, CreateScanSessionRequest, CreateScanSessionResponse,
+    DumpMemoryResponse, DumpPhysicalMemoryRequest, DumpVirtualMemoryRequest, ErrorDetails, Event,
+    FuseListRequest, FuseListResponse, FuseMountRequest, FuseMountResponse, FuseUnmountRequest,
+    FuseUnmountResponse, GdbAttachRequest, GdbAttachResponse, GdbDetachRequest, GdbDetachResponse,
+    GdbListRequest, GdbListResponse, ListConnectionsRequest, ListConnectionsResponse,
+    ListMemoryRegionsRequest, ListMemoryRegionsResponse, ListProcessesRequest,
+    ListProcessesResponse, ListScanResultsRequest, ListScanResultsResponse,
+    ListScanSessionsRequest, ListScanSessionsResponse, ModuleEvent, NewConnectionRequest,
+    NewConnectionResponse, NextScanRequest, NextScanResponse, PhysicalMemoryMetadataRequest,
+    PhysicalMemoryMetadataResponse, PointerPath, ProcessEvent, ProcessInfoRequest,
+    ProcessInfoResponse, QueryAuditLogRequest, QueryAuditLogResponse, ReadPhysicalMemoryRequest,
+    ReadPhysicalMemoryResponse, ReadVirtualMemoryRequest, ReadVirtualMemoryResponse,
+    ResolvePointerRequest, ResolvePointerResponse, RevertWritesRequest, RevertWritesResponse,
+    ScanPatternRequest, ScanPatternResponse, ScanPointersRequest, TranslateAddressesRequest,
+    TranslateAddressesResponse, WatchEventsRequest, WatchModulesRequest, WatchProcessesRequest,
+    WritePhysicalMemoryRequest, WritePhysicalMemoryResponse, WriteVirtualMemoryRequest,
+    WriteVirtualMemoryResponse,
 };
+use prost::Message;
 use simplelog::{CombinedLogger, SharedLogger, TermLogger, TerminalMode, WriteLogger};
-use tonic::{transport::Server, Request, Response, Status};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Server, Code, Request, Response, Status};
 
 mod memflow_rpc {
     tonic::include_proto!("memflow_rpc");
@@ -28,12 +43,70 @@ mod config;
 
 mod state;
 
+mod cache;
+
+mod os;
+
 mod commands;
 
+mod persistent;
+
+mod auth;
+
+mod unix;
+
+mod audit;
+
+mod journal;
+
+mod scanner;
+
+mod events;
+
+/// Maps an error to the matching grpc status code.
+/// The error kind is attached as `ErrorDetails` so clients do not have to parse the message.
+fn map_to_status(err: Error) -> Status {
+    let code = match &err {
+        Error::NotFound(_) => Code::NotFound,
+        Error::InvalidArgument(_) => Code::InvalidArgument,
+        Error::AlreadyExists(_) => Code::AlreadyExists,
+        Error::FailedPrecondition(_) => Code::FailedPrecondition,
+        Error::Unavailable(_) | Error::Connector(_) => Code::Unavailable,
+        Error::ResourceExhausted(_) => Code::ResourceExhausted,
+        Error::Core(memflow::error::Error::VirtualTranslate) | Error::PartialError(_) => {
+            Code::OutOfRange
+        }
+        _ => Code::Internal,
+    };
+
+    let details = ErrorDetails {
+        kind: err.kind().to_string(),
+        message: err.to_string(),
+    };
+    let mut buf = vec![];
+    details.encode(&mut buf).ok();
+
+    Status::with_details(code, err.to_string(), buf.into())
+}
+
 fn map_to_tonic<T>(res: Result<T>) -> core::result::Result<tonic::Response<T>, Status> {
     match res {
         Ok(val) => Ok(tonic::Response::new(val)),
-        Err(err) => Err(Status::internal(format!("{:#?}", err))),
+        Err(err) => Err(map_to_status(err)),
+    }
+}
+
+type ResponseStream<T> =
+    Pin<Box<dyn Stream<Item = core::result::Result<T, Status>> + Send + Sync + 'static>>;
+
+fn map_stream_to_tonic<T: Send + 'static>(
+    res: Result<mpsc::Receiver<Result<T>>>,
+) -> core::result::Result<tonic::Response<ResponseStream<T>>, Status> {
+    match res {
+        Ok(rx) => Ok(tonic::Response::new(Box::pin(
+            ReceiverStream::new(rx).map(|item| item.map_err(map_to_status)),
+        ))),
+        Err(err) => Err(map_to_status(err)),
     }
 }
 
@@ -70,6 +143,31 @@ impl Memflow for MyMemflow {
         map_to_tonic(commands::connection::rm(&message).await)
     }
 
+    async fn attach_os(
+        &self,
+        request: Request<AttachOsRequest>,
+    ) -> core::result::Result<Response<AttachOsResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::connection::attach_os(&message).await)
+    }
+
+    async fn connection_cache(
+        &self,
+        request: Request<ConnectionCacheRequest>,
+    ) -> core::result::Result<Response<ConnectionCacheResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::connection::cache(&message).await)
+    }
+
+    async fn revert_writes(
+        &self,
+        request: Request<RevertWritesRequest>,
+    ) -> core::result::Result<Response<RevertWritesResponse>, Status> {
+        let peer = audit::peer(&request);
+        let message = request.into_inner();
+        map_to_tonic(commands::connection::revert(&message, &peer).await)
+    }
+
     async fn read_physical_memory(
         &self,
         request: Request<ReadPhysicalMemoryRequest>,
@@ -81,8 +179,9 @@ impl Memflow for MyMemflow {
         &self,
         request: Request<WritePhysicalMemoryRequest>,
     ) -> std::result::Result<Response<WritePhysicalMemoryResponse>, Status> {
+        let peer = audit::peer(&request);
         let message = request.into_inner();
-        map_to_tonic(commands::phys_mem::write(&message).await)
+        map_to_tonic(commands::phys_mem::write(&message, &peer).await)
     }
     async fn physical_memory_metadata(
         &self,
@@ -91,6 +190,16 @@ impl Memflow for MyMemflow {
         let message = request.into_inner();
         map_to_tonic(commands::phys_mem::metadata(&message).await)
     }
+
+    type DumpPhysicalMemoryStream = ResponseStream<DumpMemoryResponse>;
+
+    async fn dump_physical_memory(
+        &self,
+        request: Request<DumpPhysicalMemoryRequest>,
+    ) -> std::result::Result<Response<Self::DumpPhysicalMemoryStream>, Status> {
+        let message = request.into_inner();
+        map_stream_to_tonic(commands::dump::dump_physical(&message).await)
+    }
     async fn read_virtual_memory(
         &self,
         request: Request<ReadVirtualMemoryRequest>,
@@ -102,8 +211,19 @@ impl Memflow for MyMemflow {
         &self,
         request: Request<WriteVirtualMemoryRequest>,
     ) -> std::result::Result<Response<WriteVirtualMemoryResponse>, Status> {
+        let peer = audit::peer(&request);
         let message = request.into_inner();
-        map_to_tonic(commands::virt_mem::write(&message).await)
+        map_to_tonic(commands::virt_mem::write(&message, &peer).await)
+    }
+
+    type DumpVirtualMemoryStream = ResponseStream<DumpMemoryResponse>;
+
+    async fn dump_virtual_memory(
+        &self,
+        request: Request<DumpVirtualMemoryRequest>,
+    ) -> std::result::Result<Response<Self::DumpVirtualMemoryStream>, Status> {
+        let message = request.into_inner();
+        map_stream_to_tonic(commands::dump::dump_virtual(&message).await)
     }
     async fn list_processes(
         &self,
@@ -119,6 +239,101 @@ impl Memflow for MyMemflow {
         let message = request.into_inner();
         map_to_tonic(commands::process::process_info(&message).await)
     }
+
+    type WatchProcessesStream = ResponseStream<ProcessEvent>;
+
+    async fn watch_processes(
+        &self,
+        request: Request<WatchProcessesRequest>,
+    ) -> std::result::Result<Response<Self::WatchProcessesStream>, Status> {
+        let message = request.into_inner();
+        map_stream_to_tonic(commands::process::watch(&message).await)
+    }
+
+    type WatchModulesStream = ResponseStream<ModuleEvent>;
+
+    async fn watch_modules(
+        &self,
+        request: Request<WatchModulesRequest>,
+    ) -> std::result::Result<Response<Self::WatchModulesStream>, Status> {
+        let message = request.into_inner();
+        map_stream_to_tonic(commands::process::watch_modules(&message).await)
+    }
+    async fn scan_pattern(
+        &self,
+        request: Request<ScanPatternRequest>,
+    ) -> std::result::Result<Response<ScanPatternResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::scan::pattern(&message).await)
+    }
+    async fn create_scan_session(
+        &self,
+        request: Request<CreateScanSessionRequest>,
+    ) -> std::result::Result<Response<CreateScanSessionResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::scanner::create(&message).await)
+    }
+    async fn next_scan(
+        &self,
+        request: Request<NextScanRequest>,
+    ) -> std::result::Result<Response<NextScanResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::scanner::next(&message).await)
+    }
+    async fn list_scan_sessions(
+        &self,
+        request: Request<ListScanSessionsRequest>,
+    ) -> std::result::Result<Response<ListScanSessionsResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::scanner::ls(&message).await)
+    }
+    async fn list_scan_results(
+        &self,
+        request: Request<ListScanResultsRequest>,
+    ) -> std::result::Result<Response<ListScanResultsResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::scanner::results(&message).await)
+    }
+    async fn close_scan_session(
+        &self,
+        request: Request<CloseScanSessionRequest>,
+    ) -> std::result::Result<Response<CloseScanSessionResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::scanner::close(&message).await)
+    }
+    async fn resolve_pointer(
+        &self,
+        request: Request<ResolvePointerRequest>,
+    ) -> std::result::Result<Response<ResolvePointerResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::pointer::resolve(&message).await)
+    }
+
+    type ScanPointersStream = ResponseStream<PointerPath>;
+
+    async fn scan_pointers(
+        &self,
+        request: Request<ScanPointersRequest>,
+    ) -> std::result::Result<Response<Self::ScanPointersStream>, Status> {
+        let message = request.into_inner();
+        map_stream_to_tonic(commands::pointer::scan(&message).await)
+    }
+
+    async fn list_memory_regions(
+        &self,
+        request: Request<ListMemoryRegionsRequest>,
+    ) -> std::result::Result<Response<ListMemoryRegionsResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::regions::ls(&message).await)
+    }
+
+    async fn translate_addresses(
+        &self,
+        request: Request<TranslateAddressesRequest>,
+    ) -> std::result::Result<Response<TranslateAddressesResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::translate::translate(&message).await)
+    }
     async fn fuse_mount(
         &self,
         request: Request<FuseMountRequest>,
@@ -133,6 +348,13 @@ impl Memflow for MyMemflow {
         let message = request.into_inner();
         map_to_tonic(commands::fuse::ls(&message).await)
     }
+    async fn fuse_unmount(
+        &self,
+        request: Request<FuseUnmountRequest>,
+    ) -> std::result::Result<Response<FuseUnmountResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::fuse::unmount(&message).await)
+    }
     async fn gdb_attach(
         &self,
         request: Request<GdbAttachRequest>,
@@ -147,6 +369,31 @@ impl Memflow for MyMemflow {
         let message = request.into_inner();
         map_to_tonic(commands::gdb::ls(&message).await)
     }
+    async fn gdb_detach(
+        &self,
+        request: Request<GdbDetachRequest>,
+    ) -> std::result::Result<Response<GdbDetachResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::gdb::detach(&message).await)
+    }
+
+    async fn query_audit_log(
+        &self,
+        request: Request<QueryAuditLogRequest>,
+    ) -> core::result::Result<Response<QueryAuditLogResponse>, Status> {
+        let message = request.into_inner();
+        map_to_tonic(commands::audit::query(&message).await)
+    }
+
+    type WatchEventsStream = ResponseStream<Event>;
+
+    async fn watch_events(
+        &self,
+        request: Request<WatchEventsRequest>,
+    ) -> core::result::Result<Response<Self::WatchEventsStream>, Status> {
+        let message = request.into_inner();
+        map_stream_to_tonic(commands::events::watch(&message).await)
+    }
 }
 
 pub struct PidFile {
@@ -268,17 +515,69 @@ fn main() {
     )
     .expect("Failed to create PID file. Insufficent privileges? (rerun with -E)");
 
-    // setup the listening socket
-    let addr = config.socket_addr.parse().unwrap();
-    // todo!("Read address from config");
+    if let Some(audit_log) = &config.audit_log {
+        audit::init(audit_log).expect("failed to open the audit log");
+    }
+
     let memflow = MyMemflow::default();
     let rt = tokio::runtime::Runtime::new().expect("failed to obtain a new RunTime object");
 
-    println!("MemflowServer listening on {}", addr);
+    // open persistent connections in the background while the server is starting up
+    {
+        let _guard = rt.enter();
+        persistent::restore_connections(&config.connections);
+    }
+
+    let mut builder = Server::builder();
+    if let Some(tls) = &config.tls {
+        let tls_config = match auth::server_tls_config(tls) {
+            Ok(tls_config) => tls_config,
+            Err(err) => {
+                error!("failed to load the tls config: {}", err);
+                std::process::exit(1);
+            }
+        };
+        builder = match builder.tls_config(tls_config) {
+            Ok(builder) => builder,
+            Err(err) => {
+                error!("failed to setup tls: {}", err);
+                std::process::exit(1);
+            }
+        };
+        info!("tls enabled");
+    }
+
+    if config.auth_tokens.is_empty() && config.policy.is_none() {
+        warn!("no auth_tokens or policy configured, the daemon accepts unauthenticated requests");
+    }
+
+    let policy = auth::Policy::new(&config.auth_tokens, config.policy.as_ref());
+    let router = builder.add_service(auth::Authorize::new(MemflowServer::new(memflow), policy));
 
-    let server = Server::builder()
-        .add_service(MemflowServer::new(memflow))
-        .serve(addr);
-    rt.block_on(server)
-        .expect("failed to run the server on tokio::runtime");
+    // setup the listening socket
+    let socket_addr = config.socket_addr;
+    println!("MemflowServer listening on {}", socket_addr);
+    let (socket_user, socket_group, socket_mode) =
+        (config.socket_user, config.socket_group, config.socket_mode);
+    rt.block_on(async move {
+        match socket_addr.strip_prefix("unix://") {
+            Some(path) => {
+                let incoming = unix::bind(
+                    path,
+                    socket_user.as_deref(),
+                    socket_group.as_deref(),
+                    socket_mode.as_deref(),
+                )
+                .expect("failed to bind the unix socket");
+                router.serve_with_incoming(incoming).await
+            }
+            None => {
+                let addr = socket_addr
+                    .parse()
+                    .expect("socket_addr is not a valid address");
+                router.serve(addr).await
+            }
+        }
+    })
+    .expect("failed to run the server on tokio::runtime");
 }
@@ -0,0 +1,170 @@
+mod physical;
+pub use physical::PhysicalOs;
+
+mod win32;
+pub use win32::Win32Os;
+
+use crate::cache::{ConnectionCache, StatsMemory};
+use crate::error::{Error, Result};
+
+use memflow::architecture::ArchitectureObj;
+use memflow::{
+    ConnectorInstance, DirectTranslate, PhysicalMemory, PhysicalMemoryMetadata, VirtualMemory, PID,
+};
+use memflow_win32::{Win32ModuleInfo, Win32ProcessInfo};
+
+/// Trait describing an operating system layer on top of a connector.
+///
+/// All commands, the virtual filesystem and the gdb stubs access the target
+/// exclusively through this trait. Processes and modules are still described by
+/// the memflow-win32 info types, so an os layer for a different operating system
+/// has to map its process and module information onto these types.
+pub trait Os: Send {
+    /// The short identifier of this os layer (e.g. `win32`)
+    fn name(&self) -> &str;
+
+    /// Clones this os layer into a new boxed instance
+    fn box_clone(&self) -> Box<dyn Os>;
+
+    /// Returns the physical memory object this os is running on
+    fn phys_mem(&mut self) -> &mut dyn PhysicalMemory;
+
+    /// Returns the metadata of the underlying physical memory
+    fn phys_metadata(&self) -> PhysicalMemoryMetadata;
+
+    /// Returns the architecture of the target if it is known
+    fn arch(&self) -> Option<ArchitectureObj> {
+        None
+    }
+
+    /// Drops all cached pages and address translations
+    fn flush_caches(&mut self) {}
+
+    /// Returns the version of the os as (major, minor, build) if available
+    fn version(&self) -> Option<(u32, u32, u32)> {
+        None
+    }
+
+    /// Retrieves a list of all processes running on the target
+    fn process_info_list(&mut self) -> Result<Vec<Win32ProcessInfo>> {
+        Err(unsupported(self.name(), "process listing"))
+    }
+
+    /// Retrieves the process with the given pid
+    fn process_info_pid(&mut self, pid: PID) -> Result<Win32ProcessInfo> {
+        self.process_info_list()?
+            .into_iter()
+            .find(|p| p.pid == pid)
+            .ok_or_else(|| Error::NotFound(format!("process with pid {} not found", pid)))
+    }
+
+    /// Retrieves the kernel process and all loaded kernel modules (drivers)
+    fn kernel_module_list(&mut self) -> Result<(Win32ProcessInfo, Vec<Win32ModuleInfo>)> {
+        Err(unsupported(self.name(), "kernel module listing"))
+    }
+
+    /// Retrieves all modules loaded into the given process
+    fn module_list(&mut self, _proc_info: &Win32ProcessInfo) -> Result<Vec<Win32ModuleInfo>> {
+        Err(unsupported(self.name(), "module listing"))
+    }
+
+    /// Returns the virtual memory of the given process borrowing this os instance
+    fn virt_mem<'a>(
+        &'a mut self,
+        _proc_info: &Win32ProcessInfo,
+    ) -> Result<Box<dyn VirtualMemory + 'a>> {
+        Err(unsupported(self.name(), "virtual memory"))
+    }
+
+    /// Consumes this os instance and returns the virtual memory of the given process
+    fn into_virt_mem(
+        self: Box<Self>,
+        _proc_info: Win32ProcessInfo,
+    ) -> Result<Box<dyn VirtualMemory>> {
+        Err(unsupported(self.name(), "virtual memory"))
+    }
+}
+
+fn unsupported(os: &str, feature: &str) -> Error {
+    if os == "none" {
+        Error::FailedPrecondition(format!("{} requires an os to be attached", feature))
+    } else {
+        Error::FailedPrecondition(format!("the {} os does not support {}", os, feature))
+    }
+}
+
+impl Clone for Box<dyn Os> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// Page size of the page cache of connections without an os.
+///
+/// The architecture of the target is not known without an os,
+/// 4 KiB is the smallest page size of all supported architectures.
+const PHYSICAL_PAGE_SIZE: usize = 0x1000;
+
+/// Creates the os layer with the given name on top of the connector.
+/// An empty name will default to `win32`.
+///
+/// Connections without an os (`none`) do not go through this function
+/// and only use the layer created by [`create_physical`].
+pub fn create_os(
+    name: &str,
+    connector: ConnectorInstance,
+    cache: &ConnectionCache,
+) -> Result<Box<dyn Os>> {
+    let config = cache.config.clone();
+    let stats = cache.stats.clone();
+    let phys_mem = StatsMemory::connector(connector.clone(), stats.clone());
+
+    match name {
+        "" | "win32" => {
+            if config.enabled {
+                Ok(Box::new(Win32Os::new(connector, move |arch| {
+                    Ok((
+                        StatsMemory::requests(
+                            config.build_page_cache(phys_mem.clone(), arch.page_size())?,
+                            stats.clone(),
+                        ),
+                        config.build_vat_cache(arch)?,
+                    ))
+                })?))
+            } else {
+                Ok(Box::new(Win32Os::new(connector, move |_| {
+                    Ok((
+                        StatsMemory::requests(phys_mem.clone(), stats.clone()),
+                        DirectTranslate::new(),
+                    ))
+                })?))
+            }
+        }
+        _ => Err(Error::InvalidArgument(format!("unsupported os: {}", name))),
+    }
+}
+
+/// Creates the physical os layer used by connections while no os is attached.
+///
+/// Physical memory accesses go through the same page cache and statistics as with an attached os.
+pub fn create_physical(
+    connector: ConnectorInstance,
+    cache: &ConnectionCache,
+) -> Result<Box<dyn Os>> {
+    let config = cache.config.clone();
+    let stats = cache.stats.clone();
+    let phys_mem = StatsMemory::connector(connector, stats.clone());
+
+    if config.enabled {
+        Ok(Box::new(PhysicalOs::new(move || {
+            Ok(StatsMemory::requests(
+                config.build_page_cache(phys_mem.clone(), PHYSICAL_PAGE_SIZE)?,
+                stats.clone(),
+            ))
+        })?))
+    } else {
+        Ok(Box::new(PhysicalOs::new(move || {
+            Ok(StatsMemory::requests(phys_mem.clone(), stats.clone()))
+        })?))
+    }
+}
@@ -0,0 +1,59 @@
+use super::Os;
+use crate::error::Result;
+
+use log::error;
+use std::sync::Arc;
+
+use memflow::*;
+
+/// Function that creates the physical memory layer.
+pub type PhysicalLayer<T> = Arc<dyn Fn() -> Result<T> + Send + Sync>;
+
+/// Raw os layer that only provides access to the physical memory of the target.
+/// It is used by connections while no os is attached (`none`).
+///
+/// This layer does not know anything about processes, modules or the architecture
+/// of the target and can be used on any target regardless of the operating system running on it.
+#[derive(Clone)]
+pub struct PhysicalOs<T> {
+    phys_mem: T,
+    layer: PhysicalLayer<T>,
+}
+
+impl<T: PhysicalMemory + Clone + 'static> PhysicalOs<T> {
+    pub fn new<F>(layer: F) -> Result<Self>
+    where
+        F: Fn() -> Result<T> + Send + Sync + 'static,
+    {
+        Ok(Self {
+            phys_mem: layer()?,
+            layer: Arc::new(layer),
+        })
+    }
+}
+
+impl<T: PhysicalMemory + Clone + 'static> Os for PhysicalOs<T> {
+    fn name(&self) -> &str {
+        "none"
+    }
+
+    fn box_clone(&self) -> Box<dyn Os> {
+        Box::new(self.clone())
+    }
+
+    fn phys_mem(&mut self) -> &mut dyn PhysicalMemory {
+        &mut self.phys_mem
+    }
+
+    fn phys_metadata(&self) -> PhysicalMemoryMetadata {
+        self.phys_mem.metadata()
+    }
+
+    fn flush_caches(&mut self) {
+        // recreating the layer drops all cached pages
+        match (self.layer)() {
+            Ok(phys_mem) => self.phys_mem = phys_mem,
+            Err(err) => error!("unable to recreate the page cache: {}", err),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use super::Os;
+use crate::error::{Error, Result};
+
+use log::error;
+use std::sync::Arc;
+
+use memflow::architecture::ArchitectureObj;
+use memflow::{
+    ConnectorInstance, PhysicalMemory, PhysicalMemoryMetadata, VirtualMemory, VirtualTranslate, PID,
+};
+use memflow_win32::{Kernel, Win32ModuleInfo, Win32Process, Win32ProcessInfo};
+
+/// Function that creates the physical memory and translation layers of the kernel.
+pub type Win32Layers<T, V> = Arc<dyn Fn(ArchitectureObj) -> Result<(T, V)> + Send + Sync>;
+
+/// Windows os layer backed by a memflow-win32 kernel.
+#[derive(Clone)]
+pub struct Win32Os<T, V> {
+    kernel: Kernel<T, V>,
+    layers: Win32Layers<T, V>,
+}
+
+impl<T, V> Win32Os<T, V>
+where
+    T: PhysicalMemory + Clone + 'static,
+    V: VirtualTranslate + Clone + 'static,
+{
+    /// Scans for a windows kernel on the given connector.
+    ///
+    /// Once the kernel has been found the memory and translation layers
+    /// for all further accesses are created via `layers`.
+    pub fn new<F>(connector: ConnectorInstance, layers: F) -> Result<Self>
+    where
+        F: Fn(ArchitectureObj) -> Result<(T, V)> + Send + Sync + 'static,
+    {
+        let scan = Kernel::builder(connector).build_default_caches().build()?;
+
+        let (phys_mem, vat) = layers(scan.kernel_info.start_block.arch)?;
+        let kernel = Kernel::new(phys_mem, vat, scan.offsets, scan.kernel_info);
+
+        Ok(Self {
+            kernel,
+            layers: Arc::new(layers),
+        })
+    }
+}
+
+impl<T, V> Os for Win32Os<T, V>
+where
+    T: PhysicalMemory + Clone + 'static,
+    V: VirtualTranslate + Clone + 'static,
+{
+    fn name(&self) -> &str {
+        "win32"
+    }
+
+    fn box_clone(&self) -> Box<dyn Os> {
+        Box::new(self.clone())
+    }
+
+    fn phys_mem(&mut self) -> &mut dyn PhysicalMemory {
+        &mut self.kernel.phys_mem
+    }
+
+    fn phys_metadata(&self) -> PhysicalMemoryMetadata {
+        self.kernel.phys_mem.metadata()
+    }
+
+    fn arch(&self) -> Option<ArchitectureObj> {
+        Some(self.kernel.kernel_info.start_block.arch)
+    }
+
+    fn flush_caches(&mut self) {
+        // recreating the layers drops all cached pages and translations
+        let (phys_mem, vat) = match (self.layers)(self.kernel.kernel_info.start_block.arch) {
+            Ok(layers) => layers,
+            Err(err) => {
+                error!("unable to recreate the caches: {}", err);
+                return;
+            }
+        };
+        self.kernel = Kernel::new(
+            phys_mem,
+            vat,
+            self.kernel.offsets.clone(),
+            self.kernel.kernel_info.clone(),
+        );
+    }
+
+    fn version(&self) -> Option<(u32, u32, u32)> {
+        Some(self.kernel.kernel_info.kernel_winver.as_tuple())
+    }
+
+    fn process_info_list(&mut self) -> Result<Vec<Win32ProcessInfo>> {
+        self.kernel.process_info_list().map_err(Error::from)
+    }
+
+    fn process_info_pid(&mut self, pid: PID) -> Result<Win32ProcessInfo> {
+        self.kernel.process_info_pid(pid).map_err(Error::from)
+    }
+
+    fn kernel_module_list(&mut self) -> Result<(Win32ProcessInfo, Vec<Win32ModuleInfo>)> {
+        let mut kernel_proc = self.kernel.kernel_process()?;
+        let modules = kernel_proc.module_list()?;
+        Ok((kernel_proc.proc_info, modules))
+    }
+
+    fn module_list(&mut self, proc_info: &Win32ProcessInfo) -> Result<Vec<Win32ModuleInfo>> {
+        let mut process = Win32Process::with_kernel_ref(&mut self.kernel, proc_info.clone());
+        process.module_list().map_err(Error::from)
+    }
+
+    fn virt_mem<'a>(
+        &'a mut self,
+        proc_info: &Win32ProcessInfo,
+    ) -> Result<Box<dyn VirtualMemory + 'a>> {
+        let process = Win32Process::with_kernel_ref(&mut self.kernel, proc_info.clone());
+        Ok(Box::new(process.virt_mem))
+    }
+
+    fn into_virt_mem(
+        self: Box<Self>,
+        proc_info: Win32ProcessInfo,
+    ) -> Result<Box<dyn VirtualMemory>> {
+        let process = Win32Process::with_kernel(self.kernel, proc_info);
+        Ok(Box::new(process.virt_mem))
+    }
+}
@@ -0,0 +1,81 @@
+/// Granularity in which unreadable memory is detected and reported.
+pub const PAGE_SIZE: u64 = 0x1000;
+
+/// Splits the given range at page boundaries.
+///
+/// Returns the address, the offset relative to `addr` and the length of each piece.
+pub fn split_pages(addr: u64, len: usize) -> impl Iterator<Item = (u64, usize, usize)> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        if offset >= len {
+            return None;
+        }
+
+        let page_addr = addr + offset as u64;
+        let page_len = std::cmp::min((PAGE_SIZE - page_addr % PAGE_SIZE) as usize, len - offset);
+
+        let page = (page_addr, offset, page_len);
+        offset += page_len;
+        Some(page)
+    })
+}
+
+/// Reads the buffer page by page via `read`.
+///
+/// Pages which could not be read are zero-filled.
+/// Returns whether each of the pages has been read successfully.
+pub fn read_pages<F>(addr: u64, buf: &mut [u8], mut read: F) -> Vec<bool>
+where
+    F: FnMut(u64, &mut [u8]) -> bool,
+{
+    split_pages(addr, buf.len())
+        .map(|(page_addr, offset, page_len)| {
+            let page = &mut buf[offset..offset + page_len];
+            let valid = read(page_addr, page);
+            if !valid {
+                page.iter_mut().for_each(|b| *b = 0);
+            }
+            valid
+        })
+        .collect()
+}
+
+/// Packs the page states into a bitmap, least significant bit first.
+pub fn pack_bitmap(pages: &[bool]) -> Vec<u8> {
+    let mut bitmap = vec![0u8; (pages.len() + 7) / 8];
+    for (i, _) in pages.iter().enumerate().filter(|(_, valid)| **valid) {
+        bitmap[i / 8] |= 1 << (i % 8);
+    }
+    bitmap
+}
+
+/// Returns whether the page with the given index is marked as valid in the bitmap.
+pub fn is_page_valid(bitmap: &[u8], page: usize) -> bool {
+    bitmap
+        .get(page / 8)
+        .map(|b| b & (1 << (page % 8)) != 0)
+        .unwrap_or_default()
+}
+
+/// Returns the index of the page containing `addr + offset` relative to the first page touched at `addr`.
+pub fn page_index(addr: u64, offset: usize) -> usize {
+    ((addr + offset as u64) / PAGE_SIZE - addr / PAGE_SIZE) as usize
+}
+
+/// Returns the ranges of `addr..addr + len` which are marked as valid in the bitmap.
+///
+/// Adjacent valid pages are merged into a single range.
+pub fn valid_ranges(addr: u64, len: usize, bitmap: &[u8]) -> Vec<(u64, u64)> {
+    let mut ranges: Vec<(u64, u64)> = vec![];
+    for (i, (page_addr, _, page_len)) in split_pages(addr, len).enumerate() {
+        if !is_page_valid(bitmap, i) {
+            continue;
+        }
+
+        match ranges.last_mut() {
+            Some(last) if last.0 + last.1 == page_addr => last.1 += page_len as u64,
+            _ => ranges.push((page_addr, page_len as u64)),
+        }
+    }
+    ranges
+}
@@ -0,0 +1,159 @@
+use crate::commands;
+use crate::error::{Error, Result};
+use crate::events;
+
+use log::{error, info, warn};
+use memflow_daemon::config::{CacheConfigOptions, ConnectionConfig, GdbConfig};
+use std::time::Duration;
+
+use crate::memflow_rpc::{
+    process_selector::Selector, CacheOptions, FuseMountRequest, GdbAttachRequest,
+    NewConnectionRequest, ProcessSelector,
+};
+
+/// Lower bound for the retry delays so a misconfigured connection does not retry in a busy loop.
+const MIN_RETRY_DELAY_MS: u64 = 100;
+
+/// Opens all persistent connections from the daemon config in the background.
+///
+/// Each connection is retried independently so a single unavailable target
+/// does not delay the other connections.
+pub fn restore_connections(connections: &[ConnectionConfig]) {
+    for conf in connections.iter().cloned() {
+        tokio::spawn(async move {
+            if let Ok(conn_id) = open_connection(&conf).await {
+                setup_connection(&conn_id, &conf).await;
+            }
+        });
+    }
+}
+
+async fn open_connection(conf: &ConnectionConfig) -> Result<String> {
+    let request = NewConnectionRequest {
+        name: conf.name.clone(),
+        args: conf.args.clone().unwrap_or_default(),
+        alias: conf.alias.clone().unwrap_or_default(),
+        os: conf.os.clone().unwrap_or_default(),
+        cache: conf.cache.as_ref().map(cache_options),
+        journal: conf.journal,
+    };
+
+    let initial_delay_ms = conf.retry.initial_delay_ms.max(MIN_RETRY_DELAY_MS);
+    let mut delay = Duration::from_millis(initial_delay_ms);
+    let max_delay = Duration::from_millis(conf.retry.max_delay_ms.max(initial_delay_ms));
+    let mut attempt = 1;
+    loop {
+        match commands::connection::new(&request).await {
+            Ok(response) => {
+                info!(
+                    "persistent connection {} opened with id {}",
+                    display_name(conf),
+                    response.conn_id
+                );
+                return Ok(response.conn_id);
+            }
+            Err(err)
+                if is_retryable(&err)
+                    && (conf.retry.max_attempts == 0 || attempt < conf.retry.max_attempts) =>
+            {
+                warn!(
+                    "unable to open persistent connection {} (attempt {}), retrying in {:?}: {}",
+                    display_name(conf),
+                    attempt,
+                    delay,
+                    err
+                );
+            }
+            Err(err) => {
+                let msg = format!(
+                    "giving up on persistent connection {} after {} attempts: {}",
+                    display_name(conf),
+                    attempt,
+                    err
+                );
+                error!("{}", msg);
+                events::emit_error("", "", &msg);
+                return Err(err);
+            }
+        }
+
+        tokio::time::sleep(delay).await;
+        delay = std::cmp::min(delay * 2, max_delay);
+        attempt += 1;
+    }
+}
+
+/// Mounts the file systems and spawns the gdb stubs of a freshly opened connection.
+async fn setup_connection(conn_id: &str, conf: &ConnectionConfig) {
+    for fuse in conf.fuse.iter() {
+        let request = FuseMountRequest {
+            conn_id: conn_id.to_string(),
+            mount_point: fuse.mount_point.clone(),
+            uid: fuse.uid,
+            gid: fuse.gid,
+        };
+        if let Err(err) = commands::fuse::mount(&request).await {
+            let msg = format!(
+                "unable to mount {} for connection {}: {}",
+                fuse.mount_point,
+                display_name(conf),
+                err
+            );
+            error!("{}", msg);
+            events::emit_error(conn_id, "", &msg);
+        }
+    }
+
+    for gdb in conf.gdb.iter() {
+        let request = GdbAttachRequest {
+            conn_id: conn_id.to_string(),
+            process: Some(process_selector(gdb)),
+            addr: gdb.addr.clone(),
+            ..Default::default()
+        };
+        if let Err(err) = commands::gdb::attach(&request).await {
+            let msg = format!(
+                "unable to attach gdb stub to {} for connection {}: {}",
+                gdb.process,
+                display_name(conf),
+                err
+            );
+            error!("{}", msg);
+            events::emit_error(conn_id, "", &msg);
+        }
+    }
+}
+
+/// Only errors of targets which are not available yet are retried,
+/// invalid configurations fail immediately.
+fn is_retryable(err: &Error) -> bool {
+    matches!(err, Error::NotFound(_) | Error::Unavailable(_))
+}
+
+fn display_name(conf: &ConnectionConfig) -> &str {
+    conf.alias.as_deref().unwrap_or(&conf.name)
+}
+
+fn cache_options(conf: &CacheConfigOptions) -> CacheOptions {
+    CacheOptions {
+        disabled: conf.disabled,
+        page_cache_size: conf.page_cache_size,
+        page_cache_validity_ms: conf.page_cache_validity_ms,
+        tlb_size: conf.tlb_size,
+        tlb_validity_ms: conf.tlb_validity_ms,
+    }
+}
+
+fn process_selector(conf: &GdbConfig) -> ProcessSelector {
+    let selector = if let Ok(pid) = conf.process.parse::<u32>() {
+        Selector::Pid(pid)
+    } else if conf.process.contains('*') || conf.process.contains('?') {
+        Selector::NameGlob(conf.process.clone())
+    } else {
+        Selector::Name(conf.process.clone())
+    };
+
+    ProcessSelector {
+        selector: Some(selector),
+    }
+}
@@ -0,0 +1,489 @@
+use crate::error::{Error, Result};
+use crate::memflow_rpc::{ScanCompare, ScanValueType};
+
+use memflow_win32::win32::Win32ProcessInfo;
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+/// Maximum number of candidates kept by a session, the first scan stops once it is reached.
+pub const MAX_SESSION_RESULTS: usize = 10_000_000;
+
+/// Maximum number of open scan sessions per connection.
+pub const MAX_CONNECTION_SESSIONS: usize = 4;
+
+/// Maximum number of open scan sessions of the daemon.
+pub const MAX_SESSIONS: usize = 16;
+
+/// A value scan whose candidates are kept between scans.
+pub struct ScanSession {
+    pub id: String,
+    pub conn_id: String,
+    pub proc_info: Win32ProcessInfo,
+    pub value_type: ScanValueType,
+    /// Number of scans including the first one
+    pub scans: u32,
+    pub truncated: bool,
+    pub candidates: Candidates,
+}
+
+/// Candidates of a value scan.
+///
+/// The addresses are sorted and the value of each candidate at the last scan
+/// is stored in a flat buffer with `value_len` bytes per candidate.
+pub struct Candidates {
+    value_len: usize,
+    addrs: Vec<u64>,
+    values: Vec<u8>,
+}
+
+impl Candidates {
+    pub fn new(value_len: usize) -> Self {
+        Self {
+            value_len,
+            addrs: vec![],
+            values: vec![],
+        }
+    }
+
+    pub fn value_len(&self) -> usize {
+        self.value_len
+    }
+
+    pub fn len(&self) -> usize {
+        self.addrs.len()
+    }
+
+    pub fn addr(&self, idx: usize) -> u64 {
+        self.addrs[idx]
+    }
+
+    /// Returns the value of the candidate at the last scan.
+    pub fn value(&self, idx: usize) -> &[u8] {
+        let start = idx * self.value_len;
+        &self.values[start..start + self.value_len]
+    }
+
+    /// Appends a candidate, addresses have to be pushed in ascending order.
+    pub fn push(&mut self, addr: u64, value: &[u8]) {
+        self.addrs.push(addr);
+        self.values.extend_from_slice(value);
+    }
+}
+
+/// A decoded number used for ordering comparisons.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Number {
+    Int(i128),
+    Float(f64),
+}
+
+/// Returns the size of values of the given type, `None` for strings.
+pub fn value_size(value_type: ScanValueType) -> Option<usize> {
+    match value_type {
+        ScanValueType::U8 | ScanValueType::I8 => Some(1),
+        ScanValueType::U16 | ScanValueType::I16 => Some(2),
+        ScanValueType::U32 | ScanValueType::I32 | ScanValueType::F32 => Some(4),
+        ScanValueType::U64 | ScanValueType::I64 | ScanValueType::F64 => Some(8),
+        ScanValueType::String => None,
+    }
+}
+
+fn parse_int(value: &str) -> Option<i128> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    let num = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+        None => value.parse().ok()?,
+    };
+    Some(if negative { -num } else { num })
+}
+
+/// Encodes the value as little endian bytes of the given type.
+pub fn encode(value_type: ScanValueType, value: &str) -> Result<Vec<u8>> {
+    let invalid = || {
+        Error::InvalidArgument(format!(
+            "invalid {} value: {}",
+            type_name(value_type),
+            value
+        ))
+    };
+    let int = || parse_int(value).ok_or_else(invalid);
+
+    Ok(match value_type {
+        ScanValueType::U8 => u8::try_from(int()?)
+            .map_err(|_| invalid())?
+            .to_le_bytes()
+            .to_vec(),
+        ScanValueType::U16 => u16::try_from(int()?)
+            .map_err(|_| invalid())?
+            .to_le_bytes()
+            .to_vec(),
+        ScanValueType::U32 => u32::try_from(int()?)
+            .map_err(|_| invalid())?
+            .to_le_bytes()
+            .to_vec(),
+        ScanValueType::U64 => u64::try_from(int()?)
+            .map_err(|_| invalid())?
+            .to_le_bytes()
+            .to_vec(),
+        ScanValueType::I8 => i8::try_from(int()?)
+            .map_err(|_| invalid())?
+            .to_le_bytes()
+            .to_vec(),
+        ScanValueType::I16 => i16::try_from(int()?)
+            .map_err(|_| invalid())?
+            .to_le_bytes()
+            .to_vec(),
+        ScanValueType::I32 => i32::try_from(int()?)
+            .map_err(|_| invalid())?
+            .to_le_bytes()
+            .to_vec(),
+        ScanValueType::I64 => i64::try_from(int()?)
+            .map_err(|_| invalid())?
+            .to_le_bytes()
+            .to_vec(),
+        ScanValueType::F32 => value
+            .trim()
+            .parse::<f32>()
+            .map_err(|_| invalid())?
+            .to_le_bytes()
+            .to_vec(),
+        ScanValueType::F64 => value
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| invalid())?
+            .to_le_bytes()
+            .to_vec(),
+        ScanValueType::String if value.is_empty() => return Err(invalid()),
+        ScanValueType::String => value.as_bytes().to_vec(),
+    })
+}
+
+fn decode(value_type: ScanValueType, data: &[u8]) -> Number {
+    let mut buf = [0u8; 8];
+    buf[..data.len().min(8)].copy_from_slice(&data[..data.len().min(8)]);
+    match value_type {
+        ScanValueType::U8 => Number::Int(buf[0] as i128),
+        ScanValueType::U16 => Number::Int(u16::from_le_bytes([buf[0], buf[1]]) as i128),
+        ScanValueType::U32 => {
+            Number::Int(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as i128)
+        }
+        ScanValueType::U64 => Number::Int(u64::from_le_bytes(buf) as i128),
+        ScanValueType::I8 => Number::Int(buf[0] as i8 as i128),
+        ScanValueType::I16 => Number::Int(i16::from_le_bytes([buf[0], buf[1]]) as i128),
+        ScanValueType::I32 => {
+            Number::Int(i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as i128)
+        }
+        ScanValueType::I64 => Number::Int(i64::from_le_bytes(buf) as i128),
+        ScanValueType::F32 => {
+            Number::Float(f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64)
+        }
+        ScanValueType::F64 => Number::Float(f64::from_le_bytes(buf)),
+        // strings are only compared for equality
+        ScanValueType::String => Number::Int(0),
+    }
+}
+
+/// Formats a value of the given type for display.
+pub fn format(value_type: ScanValueType, data: &[u8]) -> String {
+    match (value_type, decode(value_type, data)) {
+        (ScanValueType::String, _) => String::from_utf8_lossy(data).to_string(),
+        (_, Number::Int(num)) => num.to_string(),
+        (_, Number::Float(num)) => num.to_string(),
+    }
+}
+
+fn type_name(value_type: ScanValueType) -> &'static str {
+    match value_type {
+        ScanValueType::U8 => "u8",
+        ScanValueType::U16 => "u16",
+        ScanValueType::U32 => "u32",
+        ScanValueType::U64 => "u64",
+        ScanValueType::I8 => "i8",
+        ScanValueType::I16 => "i16",
+        ScanValueType::I32 => "i32",
+        ScanValueType::I64 => "i64",
+        ScanValueType::F32 => "f32",
+        ScanValueType::F64 => "f64",
+        ScanValueType::String => "string",
+    }
+}
+
+/// The condition candidates of a first or next scan have to satisfy.
+pub struct ScanCondition {
+    value_type: ScanValueType,
+    compare: ScanCompare,
+    value: Vec<u8>,
+    upper: Vec<u8>,
+}
+
+impl ScanCondition {
+    /// Parses the condition, `first` is set for the first scan of a session
+    /// which has no previous values to compare against.
+    pub fn new(
+        value_type: ScanValueType,
+        compare: ScanCompare,
+        value: &str,
+        upper: &str,
+        first: bool,
+    ) -> Result<Self> {
+        let relative = !matches!(compare, ScanCompare::Equal | ScanCompare::Range);
+        if first && relative {
+            return Err(Error::InvalidArgument(
+                "the first scan only supports equal and range comparisons".to_string(),
+            ));
+        }
+        if value_type == ScanValueType::String
+            && matches!(
+                compare,
+                ScanCompare::Increased | ScanCompare::Decreased | ScanCompare::Range
+            )
+        {
+            return Err(Error::InvalidArgument(
+                "strings only support equal, changed and unchanged comparisons".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            value_type,
+            compare,
+            value: if relative {
+                vec![]
+            } else {
+                encode(value_type, value)?
+            },
+            upper: if compare == ScanCompare::Range {
+                encode(value_type, upper)?
+            } else {
+                vec![]
+            },
+        })
+    }
+
+    /// Length of the value searched by the first scan.
+    pub fn value_len(&self) -> usize {
+        value_size(self.value_type).unwrap_or_else(|| self.value.len())
+    }
+
+    /// Fails if the value of the condition can not be compared to values of the given length,
+    /// e.g. a string of a different length than the one searched by the first scan.
+    pub fn check_value_len(&self, value_len: usize) -> Result<()> {
+        if !self.value.is_empty() && self.value.len() != value_len {
+            return Err(Error::InvalidArgument(format!(
+                "the value has {} bytes but the values of the session have {} bytes",
+                self.value.len(),
+                value_len
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns true if the current value satisfies the condition,
+    /// `previous` is the value at the last scan of the candidate.
+    pub fn matches(&self, previous: &[u8], current: &[u8]) -> bool {
+        let cmp = |a: &[u8], b: &[u8]| {
+            decode(self.value_type, a).partial_cmp(&decode(self.value_type, b))
+        };
+
+        match self.compare {
+            ScanCompare::Equal if self.value_type == ScanValueType::String => {
+                current == self.value.as_slice()
+            }
+            ScanCompare::Equal => cmp(current, &self.value) == Some(Ordering::Equal),
+            ScanCompare::Changed => current != previous,
+            ScanCompare::Unchanged => current == previous,
+            ScanCompare::Increased => cmp(current, previous) == Some(Ordering::Greater),
+            ScanCompare::Decreased => cmp(current, previous) == Some(Ordering::Less),
+            ScanCompare::Range => {
+                matches!(
+                    cmp(current, &self.value),
+                    Some(Ordering::Greater) | Some(Ordering::Equal)
+                ) && matches!(
+                    cmp(current, &self.upper),
+                    Some(Ordering::Less) | Some(Ordering::Equal)
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn condition(value_type: ScanValueType, compare: ScanCompare, value: &str) -> ScanCondition {
+        ScanCondition::new(value_type, compare, value, "", false).unwrap()
+    }
+
+    fn range(value_type: ScanValueType, lower: &str, upper: &str) -> ScanCondition {
+        ScanCondition::new(value_type, ScanCompare::Range, lower, upper, true).unwrap()
+    }
+
+    #[test]
+    fn encode_ints() {
+        assert_eq!(encode(ScanValueType::U8, "255").unwrap(), vec![0xff]);
+        assert_eq!(
+            encode(ScanValueType::U16, "0x1234").unwrap(),
+            vec![0x34, 0x12]
+        );
+        assert_eq!(encode(ScanValueType::I16, "-2").unwrap(), vec![0xfe, 0xff]);
+        assert_eq!(
+            encode(ScanValueType::U64, " 0X10 ").unwrap(),
+            16u64.to_le_bytes().to_vec()
+        );
+        assert_eq!(
+            encode(ScanValueType::I64, "-0x10").unwrap(),
+            (-16i64).to_le_bytes().to_vec()
+        );
+    }
+
+    #[test]
+    fn encode_floats_and_strings() {
+        assert_eq!(
+            encode(ScanValueType::F32, "1.5").unwrap(),
+            1.5f32.to_le_bytes().to_vec()
+        );
+        assert_eq!(
+            encode(ScanValueType::F64, "-0.25").unwrap(),
+            (-0.25f64).to_le_bytes().to_vec()
+        );
+        assert_eq!(
+            encode(ScanValueType::String, "abc").unwrap(),
+            b"abc".to_vec()
+        );
+    }
+
+    #[test]
+    fn encode_invalid() {
+        for (value_type, value) in [
+            (ScanValueType::U8, "256"),
+            (ScanValueType::U8, "-1"),
+            (ScanValueType::I8, "128"),
+            (ScanValueType::U32, "abc"),
+            (ScanValueType::U32, ""),
+            (ScanValueType::F32, "1.5x"),
+            (ScanValueType::String, ""),
+        ]
+        .iter()
+        {
+            assert!(encode(*value_type, value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn format_values() {
+        assert_eq!(format(ScanValueType::I32, &(-5i32).to_le_bytes()), "-5");
+        assert_eq!(
+            format(ScanValueType::U64, &u64::MAX.to_le_bytes()),
+            "18446744073709551615"
+        );
+        assert_eq!(format(ScanValueType::F32, &1.5f32.to_le_bytes()), "1.5");
+        assert_eq!(format(ScanValueType::String, b"abc"), "abc");
+    }
+
+    #[test]
+    fn parse_condition() {
+        assert!(
+            ScanCondition::new(ScanValueType::U32, ScanCompare::Changed, "", "", true).is_err()
+        );
+        assert!(
+            ScanCondition::new(ScanValueType::String, ScanCompare::Range, "a", "b", true).is_err()
+        );
+        assert!(
+            ScanCondition::new(ScanValueType::String, ScanCompare::Increased, "", "", false)
+                .is_err()
+        );
+        assert!(ScanCondition::new(ScanValueType::U32, ScanCompare::Range, "1", "", true).is_err());
+    }
+
+    #[test]
+    fn match_equal() {
+        let cond = condition(ScanValueType::I32, ScanCompare::Equal, "-5");
+        assert!(cond.matches(&[], &(-5i32).to_le_bytes()));
+        assert!(!cond.matches(&[], &5i32.to_le_bytes()));
+
+        let cond = condition(ScanValueType::F64, ScanCompare::Equal, "0.5");
+        assert!(cond.matches(&[], &0.5f64.to_le_bytes()));
+        assert!(!cond.matches(&[], &f64::NAN.to_le_bytes()));
+
+        let cond = condition(ScanValueType::String, ScanCompare::Equal, "abc");
+        assert!(cond.matches(&[], b"abc"));
+        assert!(!cond.matches(&[], b"abd"));
+    }
+
+    #[test]
+    fn match_relative() {
+        let (one, two) = (1u16.to_le_bytes(), 2u16.to_le_bytes());
+        let matches = |compare, previous: &[u8], current: &[u8]| {
+            condition(ScanValueType::U16, compare, "").matches(previous, current)
+        };
+        assert!(matches(ScanCompare::Increased, &one, &two));
+        assert!(!matches(ScanCompare::Increased, &two, &one));
+        assert!(matches(ScanCompare::Decreased, &two, &one));
+        assert!(matches(ScanCompare::Changed, &one, &two));
+        assert!(!matches(ScanCompare::Changed, &one, &one));
+        assert!(matches(ScanCompare::Unchanged, &one, &one));
+
+        // signed values are compared as such
+        let cond = condition(ScanValueType::I8, ScanCompare::Decreased, "");
+        assert!(cond.matches(&[1], &[0xff]));
+    }
+
+    #[test]
+    fn match_range() {
+        let cond = range(ScanValueType::U32, "10", "20");
+        for (value, expected) in [
+            (9u32, false),
+            (10, true),
+            (15, true),
+            (20, true),
+            (21, false),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                cond.matches(&[], &value.to_le_bytes()),
+                *expected,
+                "{}",
+                value
+            );
+        }
+
+        let cond = range(ScanValueType::F32, "-1.5", "1.5");
+        for (value, expected) in [
+            (-1.6f32, false),
+            (-1.5, true),
+            (0.0, true),
+            (1.5, true),
+            (1.6, false),
+            (f32::NAN, false),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                cond.matches(&[], &value.to_le_bytes()),
+                *expected,
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn check_string_len() {
+        let cond = condition(ScanValueType::String, ScanCompare::Equal, "abcd");
+        assert!(cond.check_value_len(4).is_ok());
+        assert!(cond.check_value_len(3).is_err());
+
+        // relative comparisons have no value to check
+        let cond = condition(ScanValueType::String, ScanCompare::Changed, "");
+        assert!(cond.check_value_len(3).is_ok());
+    }
+}
@@ -1,13 +1,21 @@
+use crate::cache::ConnectionCache;
 use crate::error::{Error, Result};
+use crate::events;
+use crate::journal::WriteJournal;
+use crate::os::{create_physical, Os};
+use crate::scanner::{ScanSession, MAX_CONNECTION_SESSIONS, MAX_SESSIONS};
 
 use std::collections::HashMap;
-use tokio::sync::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::{Mutex, MutexGuard, OwnedMutexGuard};
 
 use lazy_static::lazy_static;
 use uuid::Uuid;
 
 use memflow::*;
-use memflow_win32::*;
+
+use crate::memflow_rpc::EventKind;
 
 lazy_static! {
     pub static ref STATE: Mutex<State> = Mutex::new(State::new());
@@ -18,6 +26,18 @@ pub fn state_lock_sync<'a>() -> MutexGuard<'a, State> {
     rt.block_on(STATE.lock())
 }
 
+/// Locks the connection with the given id or alias.
+///
+/// The global state is only locked while looking up the connection so
+/// operations on other connections can proceed while the returned guard is held.
+///
+/// The global state must never be locked while a connection is locked,
+/// otherwise two requests locking them in a different order could deadlock.
+pub async fn lock_connection(id: &str) -> Result<OwnedMutexGuard<ConnectionTarget>> {
+    let target = STATE.lock().await.connection_target(id)?;
+    Ok(target.lock_owned().await)
+}
+
 pub fn new_uuid() -> String {
     let uuid = Uuid::new_v4();
     uuid.to_simple()
@@ -28,12 +48,16 @@ pub fn new_uuid() -> String {
 }
 
 /// Contains the entire global state of the daemon.
+///
+/// The state should only be locked for short bookkeeping operations.
+/// Anything touching the memory of a connection has to go through [`lock_connection`].
 pub struct State {
     pub connections: HashMap<String, OpenedConnection>,
     pub connection_aliases: HashMap<String, String>,
 
     pub file_systems: HashMap<String, FileSystemHandle>,
     pub gdb_stubs: HashMap<String, GdbStubHandle>,
+    pub scan_sessions: HashMap<String, ScanSessionHandle>,
 }
 
 impl State {
@@ -44,6 +68,7 @@ impl State {
 
             file_systems: HashMap::new(),
             gdb_stubs: HashMap::new(),
+            scan_sessions: HashMap::new(),
         }
     }
 
@@ -52,26 +77,39 @@ impl State {
         name: &str,
         args: Option<String>,
         alias: Option<String>,
-        kernel: KernelHandle,
+        connector: ConnectorInstance,
+        cache: ConnectionCache,
+        kernel: Option<KernelHandle>,
+        journal: bool,
     ) -> Result<String> {
         if alias.is_some()
             && self
                 .connection_aliases
                 .contains_key(alias.as_ref().unwrap())
         {
-            return Err(Error::Connector(
+            return Err(Error::AlreadyExists(
                 "a connection with this alias already exists".into(),
             ));
         }
 
         let id = new_uuid();
-        let conn = OpenedConnection::new(&id, alias.clone(), name, args, kernel);
+        let conn = OpenedConnection::new(
+            &id,
+            alias.clone(),
+            name,
+            args,
+            connector,
+            cache,
+            kernel,
+            journal,
+        )?;
 
         self.connections.insert(id.clone(), conn);
         if let Some(a) = alias {
             self.connection_aliases.insert(a, id.clone());
         }
 
+        events::emit(EventKind::ConnectionOpened, &id, "", name);
         Ok(id)
     }
 
@@ -97,17 +135,24 @@ impl State {
         }
     }
 
+    /// Returns the lockable target of the connection with the given id or alias.
+    pub fn connection_target(&self, id: &str) -> Result<Arc<Mutex<ConnectionTarget>>> {
+        self.connection(id)
+            .map(|conn| conn.target.clone())
+            .ok_or_else(|| Error::NotFound(format!("no connection with id {} found", id)))
+    }
+
     pub fn connection_remove(&mut self, id: &str) -> Result<()> {
         let (id, alias) = if let Some(conn) = self.connection(id) {
             if conn.refcount == 0 {
                 (conn.id.clone(), conn.alias.clone())
             } else {
-                return Err(Error::Connector(
+                return Err(Error::FailedPrecondition(
                     "connection still has open references".into(),
                 ));
             }
         } else {
-            return Err(Error::Connector("connection not found".into()));
+            return Err(Error::NotFound("connection not found".into()));
         };
 
         if let Some(alias) = &alias {
@@ -115,33 +160,136 @@ impl State {
         }
         self.connections.remove(&id);
 
+        events::emit(EventKind::ConnectionClosed, &id, "", "");
         Ok(())
     }
-}
 
-pub type CachedConnectorInstance =
-    CachedMemoryAccess<'static, ConnectorInstance, TimedCacheValidator>;
+    /// Removes the file system and releases its reference on the connection.
+    ///
+    /// Returns `None` if the file system has already been removed.
+    pub fn file_system_remove(&mut self, id: &str) -> Option<FileSystemHandle> {
+        let fs = self.file_systems.remove(id)?;
+        if let Some(conn) = self.connection_mut(&fs.conn_id) {
+            conn.release();
+        }
+
+        events::emit(EventKind::FuseUnmounted, &fs.conn_id, id, &fs.mount_point);
+        Some(fs)
+    }
+
+    /// Adds the gdb stub and takes a reference on its connection.
+    pub fn gdb_stub_add(&mut self, stub: GdbStubHandle) -> Result<()> {
+        let conn = self
+            .connection_mut(&stub.conn_id)
+            .ok_or_else(|| Error::NotFound("connection not found".into()))?;
+        conn.add_ref();
+
+        events::emit(
+            EventKind::GdbStubStarted,
+            &stub.conn_id,
+            &stub.id,
+            &stub.addr,
+        );
+        self.gdb_stubs.insert(stub.id.clone(), stub);
+        Ok(())
+    }
+
+    /// Removes the gdb stub and releases its reference on the connection.
+    ///
+    /// Returns `None` if the gdb stub has already been removed.
+    pub fn gdb_stub_remove(&mut self, id: &str) -> Option<GdbStubHandle> {
+        let stub = self.gdb_stubs.remove(id)?;
+        if let Some(conn) = self.connection_mut(&stub.conn_id) {
+            conn.release();
+        }
+
+        events::emit(EventKind::GdbStubStopped, &stub.conn_id, id, "");
+        Some(stub)
+    }
+
+    /// Fails if no further scan session can be opened on the connection.
+    pub fn scan_session_check(&self, conn_id: &str) -> Result<()> {
+        let conn = self
+            .connection(conn_id)
+            .ok_or_else(|| Error::NotFound("connection not found".into()))?;
+
+        if self.scan_sessions.len() >= MAX_SESSIONS {
+            return Err(Error::ResourceExhausted(format!(
+                "the daemon already has {} open scan sessions",
+                MAX_SESSIONS
+            )));
+        }
+
+        let count = self
+            .scan_sessions
+            .values()
+            .filter(|handle| handle.conn_id == conn.id)
+            .count();
+        if count >= MAX_CONNECTION_SESSIONS {
+            return Err(Error::ResourceExhausted(format!(
+                "connection {} already has {} open scan sessions",
+                conn.id, MAX_CONNECTION_SESSIONS
+            )));
+        }
+        Ok(())
+    }
+
+    /// Adds the scan session and takes a reference on its connection.
+    pub fn scan_session_add(&mut self, session: ScanSession) -> Result<()> {
+        self.scan_session_check(&session.conn_id)?;
 
-pub type CachedTranslate = CachedVirtualTranslate<DirectTranslate, TimedCacheValidator>;
+        let conn = self
+            .connection_mut(&session.conn_id)
+            .ok_or_else(|| Error::NotFound("connection not found".into()))?;
+        conn.add_ref();
 
-pub type CachedWin32Kernel = memflow_win32::Kernel<CachedConnectorInstance, CachedTranslate>;
+        self.scan_sessions.insert(
+            session.id.clone(),
+            ScanSessionHandle {
+                id: session.id.clone(),
+                conn_id: session.conn_id.clone(),
+                session: Arc::new(Mutex::new(session)),
+            },
+        );
+        Ok(())
+    }
 
-pub type CachedWin32Process = memflow_win32::Win32Process<
-    VirtualDMA<CachedConnectorInstance, CachedTranslate, Win32VirtualTranslate>,
->;
+    /// Returns the lockable scan session with the given id.
+    pub fn scan_session(&self, id: &str) -> Result<Arc<Mutex<ScanSession>>> {
+        self.scan_sessions
+            .get(id)
+            .map(|handle| handle.session.clone())
+            .ok_or_else(|| Error::NotFound(format!("no scan session with id {} found", id)))
+    }
 
-#[derive(Debug, Clone)]
-pub enum KernelHandle {
-    Win32(CachedWin32Kernel),
+    /// Removes the scan session and releases its reference on the connection.
+    pub fn scan_session_remove(&mut self, id: &str) -> Result<()> {
+        let handle = self
+            .scan_sessions
+            .remove(id)
+            .ok_or_else(|| Error::NotFound(format!("no scan session with id {} found", id)))?;
+        if let Some(conn) = self.connection_mut(&handle.conn_id) {
+            conn.release();
+        }
+        Ok(())
+    }
 }
 
+/// Handle to the os layer of a connection.
+pub type KernelHandle = Box<dyn Os>;
+
+/// Bookkeeping information of a connection.
+///
+/// The connector and the os layer live in a separate [`ConnectionTarget`]
+/// with its own lock so a long running read does not block the global state.
 pub struct OpenedConnection {
     pub id: String,
     pub alias: Option<String>,
     pub refcount: usize,
     pub name: String,
     pub args: Option<String>,
-    pub kernel: KernelHandle,
+    pub os: Option<String>,
+    pub target: Arc<Mutex<ConnectionTarget>>,
 }
 
 impl OpenedConnection {
@@ -150,15 +298,98 @@ impl OpenedConnection {
         alias: Option<String>,
         name: &str,
         args: Option<String>,
-        kernel: KernelHandle,
-    ) -> Self {
-        Self {
+        connector: ConnectorInstance,
+        cache: ConnectionCache,
+        kernel: Option<KernelHandle>,
+        journal: bool,
+    ) -> Result<Self> {
+        Ok(Self {
             id: id.to_string(),
             alias,
             refcount: 0,
             name: name.to_string(),
             args,
-            kernel,
+            os: kernel.as_ref().map(|k| k.name().to_string()),
+            target: Arc::new(Mutex::new(ConnectionTarget {
+                id: id.to_string(),
+                physical: create_physical(connector.clone(), &cache)?,
+                connector,
+                cache,
+                kernel,
+                journal: if journal {
+                    Some(WriteJournal::default())
+                } else {
+                    None
+                },
+            })),
+        })
+    }
+
+    /// Registers a file system, gdb stub or scan session using this connection.
+    pub fn add_ref(&mut self) {
+        self.refcount += 1;
+        events::emit_refcount(&self.id, self.refcount);
+    }
+
+    /// Unregisters a file system, gdb stub or scan session using this connection.
+    pub fn release(&mut self) {
+        self.refcount -= 1;
+        events::emit_refcount(&self.id, self.refcount);
+    }
+}
+
+/// The memory backends of a connection.
+pub struct ConnectionTarget {
+    pub id: String,
+    pub connector: ConnectorInstance,
+    pub cache: ConnectionCache,
+    pub kernel: Option<KernelHandle>,
+    /// Physical os layer with the caches of this connection, used while no os is attached
+    pub physical: KernelHandle,
+    /// Original bytes of all writes, only set if journaling is enabled for this connection
+    pub journal: Option<WriteJournal>,
+}
+
+impl ConnectionTarget {
+    /// Returns the os attached to this connection.
+    pub fn kernel(&self) -> Result<&KernelHandle> {
+        self.kernel.as_ref().ok_or_else(|| {
+            Error::FailedPrecondition(format!("connection {} has no os attached", self.id))
+        })
+    }
+
+    /// Returns the os attached to this connection.
+    pub fn kernel_mut(&mut self) -> Result<&mut KernelHandle> {
+        let id = &self.id;
+        self.kernel.as_mut().ok_or_else(|| {
+            Error::FailedPrecondition(format!("connection {} has no os attached", id))
+        })
+    }
+
+    /// Returns the os attached to this connection or
+    /// the physical os layer if no os has been attached yet.
+    pub fn kernel_or_physical(&self) -> KernelHandle {
+        match &self.kernel {
+            Some(kernel) => kernel.clone(),
+            None => self.physical.clone(),
+        }
+    }
+
+    /// Returns the physical memory of this connection.
+    ///
+    /// If an os is attached its physical memory is used so reads and writes share the os caches.
+    pub fn phys_mem(&mut self) -> &mut dyn PhysicalMemory {
+        match &mut self.kernel {
+            Some(kernel) => kernel.phys_mem(),
+            None => self.physical.phys_mem(),
+        }
+    }
+
+    /// Returns the metadata of the physical memory of this connection.
+    pub fn phys_metadata(&self) -> PhysicalMemoryMetadata {
+        match &self.kernel {
+            Some(kernel) => kernel.phys_metadata(),
+            None => self.physical.phys_metadata(),
         }
     }
 }
@@ -183,14 +414,59 @@ pub struct GdbStubHandle {
     pub id: String,
     pub conn_id: String,
     pub addr: String,
+    pub control: Arc<GdbStubControl>,
 }
 
 impl GdbStubHandle {
-    pub fn new(id: &str, conn_id: &str, addr: &str) -> Self {
+    pub fn new(id: &str, conn_id: &str, addr: &str, control: Arc<GdbStubControl>) -> Self {
         Self {
             id: id.to_string(),
             conn_id: conn_id.to_string(),
             addr: addr.to_string(),
+            control,
+        }
+    }
+}
+
+/// A scan session, the candidates live behind their own lock so
+/// a running scan does not block the global state.
+pub struct ScanSessionHandle {
+    pub id: String,
+    pub conn_id: String,
+    pub session: Arc<Mutex<ScanSession>>,
+}
+
+/// Allows stopping a gdb stub thread from the outside.
+#[derive(Default)]
+pub struct GdbStubControl {
+    stopped: AtomicBool,
+    disconnect: StdMutex<Option<Box<dyn FnOnce() + Send>>>,
+}
+
+impl GdbStubControl {
+    /// Returns true once [`stop`](Self::stop) has been called.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Sets the function which disconnects the debugger once it has connected.
+    ///
+    /// The function is called right away if the stub has already been stopped.
+    pub fn set_disconnect(&self, disconnect: Box<dyn FnOnce() + Send>) {
+        let mut guard = self.disconnect.lock().unwrap();
+        if self.is_stopped() {
+            disconnect();
+        } else {
+            *guard = Some(disconnect);
+        }
+    }
+
+    /// Stops waiting for a debugger and disconnects the current one.
+    pub fn stop(&self) {
+        let mut guard = self.disconnect.lock().unwrap();
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(disconnect) = guard.take() {
+            disconnect();
         }
     }
 }
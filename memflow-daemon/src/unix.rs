@@ -0,0 +1,161 @@
+use crate::error::{Error, Result};
+
+use log::info;
+
+use futures::{Stream, StreamExt};
+use std::ffi::CString;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::UnixListener;
+use tokio_stream::wrappers::UnixListenerStream;
+use tonic::transport::server::Connected;
+
+/// Binds the unix socket at `path` and applies the given ownership and octal permissions.
+///
+/// A stale socket file from a previous run is removed before binding,
+/// any other file at `path` is left untouched and fails the bind.
+pub fn bind(
+    path: &str,
+    user: Option<&str>,
+    group: Option<&str>,
+    mode: Option<&str>,
+) -> Result<impl Stream<Item = std::io::Result<UnixStream>>> {
+    let mode = mode
+        .map(|mode| {
+            u32::from_str_radix(mode, 8)
+                .map_err(|_| Error::Other(format!("invalid socket_mode: {}", mode)))
+        })
+        .transpose()?;
+    let uid = user.map(user_id).transpose()?;
+    let gid = group.map(group_id).transpose()?;
+
+    remove_stale_socket(path)?;
+
+    // the socket is only accessible by the owner until the configured permissions are applied
+    let umask = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(path);
+    unsafe { libc::umask(umask) };
+    let listener =
+        listener.map_err(|err| Error::Other(format!("unable to bind {}: {}", path, err)))?;
+
+    if uid.is_some() || gid.is_some() {
+        chown(path, uid, gid)?;
+    }
+
+    let mode = mode.unwrap_or(0o777 & !(umask as u32));
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .map_err(|err| Error::Other(format!("unable to set permissions of {}: {}", path, err)))?;
+
+    info!("listening on unix socket {}", path);
+    Ok(UnixListenerStream::new(listener).map(|stream| {
+        stream.map(|stream| {
+            let uid = stream.peer_cred().ok().map(|cred| cred.uid());
+            UnixStream { stream, uid }
+        })
+    }))
+}
+
+fn remove_stale_socket(path: &str) -> Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)
+            .map_err(|err| Error::Other(format!("unable to remove {}: {}", path, err))),
+        Ok(_) => Err(Error::Other(format!(
+            "unable to bind {}: file exists and is not a socket",
+            path
+        ))),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(Error::Other(format!("unable to access {}: {}", path, err))),
+    }
+}
+
+fn user_id(name: &str) -> Result<libc::uid_t> {
+    let cname = CString::new(name).map_err(|_| Error::Other(format!("invalid user {}", name)))?;
+    let passwd = unsafe { libc::getpwnam(cname.as_ptr()) };
+    if passwd.is_null() {
+        Err(Error::Other(format!("user {} not found", name)))
+    } else {
+        Ok(unsafe { (*passwd).pw_uid })
+    }
+}
+
+fn group_id(name: &str) -> Result<libc::gid_t> {
+    let cname = CString::new(name).map_err(|_| Error::Other(format!("invalid group {}", name)))?;
+    let group = unsafe { libc::getgrnam(cname.as_ptr()) };
+    if group.is_null() {
+        Err(Error::Other(format!("group {} not found", name)))
+    } else {
+        Ok(unsafe { (*group).gr_gid })
+    }
+}
+
+fn chown(path: &str, uid: Option<libc::uid_t>, gid: Option<libc::gid_t>) -> Result<()> {
+    let cpath = CString::new(path).map_err(|_| Error::Other(format!("invalid path {}", path)))?;
+    // -1 leaves the respective id unchanged
+    let ret = unsafe {
+        libc::chown(
+            cpath.as_ptr(),
+            uid.unwrap_or(libc::uid_t::MAX),
+            gid.unwrap_or(libc::gid_t::MAX),
+        )
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(Error::Other(format!(
+            "unable to change ownership of {}: {}",
+            path,
+            std::io::Error::last_os_error()
+        )))
+    }
+}
+
+/// Connection info of a unix socket peer, tonic attaches it to the extensions of each request.
+#[derive(Clone, Debug)]
+pub struct UnixConnectInfo {
+    pub uid: Option<u32>,
+}
+
+/// Wrapper around a unix stream so it can be served by tonic.
+#[derive(Debug)]
+pub struct UnixStream {
+    stream: tokio::net::UnixStream,
+    uid: Option<u32>,
+}
+
+impl Connected for UnixStream {
+    type ConnectInfo = UnixConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        UnixConnectInfo { uid: self.uid }
+    }
+}
+
+impl AsyncRead for UnixStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for UnixStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}